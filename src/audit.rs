@@ -0,0 +1,523 @@
+//! Grade the security headers of a response.
+
+use crate::cookies::Cookie;
+use crate::csp::{self, ContentSecurityPolicy};
+use crate::leaks::LEAKY_HEADERS;
+use crate::{ParseError, ReferrerOptions, ReferrerPolicy, RequestContext};
+use http::header::{HeaderName, SERVER, SET_COOKIE};
+use http::HeaderMap;
+use std::cmp::Reverse;
+use std::fmt;
+use std::str::FromStr;
+
+/// `Strict-Transport-Security` max-age below which HSTS is considered weak: 180 days.
+const MIN_HSTS_MAX_AGE: u64 = 15_552_000;
+
+/// How serious a finding is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    /// Worth knowing, doesn't affect the grade.
+    Info,
+    /// A minor weakness.
+    Low,
+    /// A weakness that makes attacks easier.
+    Medium,
+    /// A missing or broken core protection.
+    High,
+}
+
+impl Severity {
+    fn penalty(self) -> u32 {
+        match self {
+            Severity::Info => 0,
+            Severity::Low => 5,
+            Severity::Medium => 10,
+            Severity::High => 20,
+        }
+    }
+}
+
+impl AsRef<str> for Severity {
+    fn as_ref(&self) -> &str {
+        match *self {
+            Severity::Info => "info",
+            Severity::Low => "low",
+            Severity::Medium => "medium",
+            Severity::High => "high",
+        }
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_ref())
+    }
+}
+
+impl FromStr for Severity {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "info" => Ok(Severity::Info),
+            "low" => Ok(Severity::Low),
+            "medium" => Ok(Severity::Medium),
+            "high" => Ok(Severity::High),
+            _ => Err(ParseError::new(format!("unknown severity `{}`", s))),
+        }
+    }
+}
+
+/// What kind of problem a finding describes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Category {
+    /// A protection is missing.
+    Missing,
+    /// A protection is present but configured weakly.
+    Weak,
+    /// A header is deprecated or ignored by current browsers.
+    Deprecated,
+    /// A header leaks implementation details.
+    Leak,
+}
+
+impl AsRef<str> for Category {
+    fn as_ref(&self) -> &str {
+        match *self {
+            Category::Missing => "missing",
+            Category::Weak => "weak",
+            Category::Deprecated => "deprecated",
+            Category::Leak => "leak",
+        }
+    }
+}
+
+impl fmt::Display for Category {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_ref())
+    }
+}
+
+/// A single problem found by [`audit`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Finding {
+    /// What kind of problem this is.
+    pub category: Category,
+    /// How serious the problem is.
+    pub severity: Severity,
+    /// The header the problem concerns.
+    pub header: String,
+    /// A description of the problem.
+    pub message: String,
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} ({}) {}: {}",
+            self.severity, self.category, self.header, self.message
+        )
+    }
+}
+
+/// An overall grade, from `F` to `A+`.
+///
+/// Grades are ordered, so `grade >= Grade::A` checks for an `A` or better.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Grade {
+    /// Score below 40
+    F,
+    /// Score of at least 40
+    E,
+    /// Score of at least 55
+    D,
+    /// Score of at least 70
+    C,
+    /// Score of at least 80
+    B,
+    /// Score of at least 90
+    A,
+    /// No findings above [`Severity::Info`]
+    APlus,
+}
+
+impl Grade {
+    fn from_score(score: u32) -> Self {
+        match score {
+            100 => Grade::APlus,
+            90..=99 => Grade::A,
+            80..=89 => Grade::B,
+            70..=79 => Grade::C,
+            55..=69 => Grade::D,
+            40..=54 => Grade::E,
+            _ => Grade::F,
+        }
+    }
+}
+
+impl AsRef<str> for Grade {
+    fn as_ref(&self) -> &str {
+        match *self {
+            Grade::APlus => "A+",
+            Grade::A => "A",
+            Grade::B => "B",
+            Grade::C => "C",
+            Grade::D => "D",
+            Grade::E => "E",
+            Grade::F => "F",
+        }
+    }
+}
+
+impl fmt::Display for Grade {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_ref())
+    }
+}
+
+/// The result of [`audit`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuditReport {
+    findings: Vec<Finding>,
+}
+
+impl AuditReport {
+    /// All findings, most severe first.
+    pub fn findings(&self) -> &[Finding] {
+        &self.findings
+    }
+
+    /// The findings of at least a given severity.
+    pub fn findings_at_least(&self, severity: Severity) -> impl Iterator<Item = &Finding> {
+        self.findings
+            .iter()
+            .filter(move |finding| finding.severity >= severity)
+    }
+
+    /// The highest severity found, if any.
+    pub fn max_severity(&self) -> Option<Severity> {
+        self.findings.iter().map(|finding| finding.severity).max()
+    }
+
+    /// A score from 0 to 100, reduced by every finding above [`Severity::Info`].
+    pub fn score(&self) -> u32 {
+        let penalty: u32 = self
+            .findings
+            .iter()
+            .map(|finding| finding.severity.penalty())
+            .sum();
+        100u32.saturating_sub(penalty)
+    }
+
+    /// The overall grade.
+    pub fn grade(&self) -> Grade {
+        Grade::from_score(self.score())
+    }
+}
+
+struct Auditor<'a> {
+    headers: &'a HeaderMap,
+    findings: Vec<Finding>,
+}
+
+impl Auditor<'_> {
+    fn push(&mut self, category: Category, severity: Severity, header: &str, message: String) {
+        self.findings.push(Finding {
+            category,
+            severity,
+            header: header.to_string(),
+            message,
+        });
+    }
+
+    fn get(&self, name: &str) -> Option<String> {
+        self.headers
+            .get(name)
+            .map(|value| String::from_utf8_lossy(value.as_bytes()).trim().to_string())
+    }
+}
+
+/// Inspect a response's headers and grade its security.
+///
+/// The report lists missing protections, weak values, deprecated headers and information
+/// leaks. `Strict-Transport-Security` and cookie `Secure` flags are only expected for HTTPS
+/// requests to non-local hosts, as described by `ctx`.
+///
+/// ## Examples
+/// ```
+/// use armor::{Armor, Grade, RequestContext};
+///
+/// let mut ctx = RequestContext::new();
+/// ctx.scheme("https").host("example.com");
+///
+/// let mut headers = http::HeaderMap::new();
+/// Armor::new().apply_for(&mut headers, &ctx);
+/// assert!(armor::audit(&headers, &ctx).grade() >= Grade::A);
+///
+/// let report = armor::audit(&http::HeaderMap::new(), &ctx);
+/// assert_eq!(report.grade(), Grade::F);
+/// ```
+pub fn audit(headers: &HeaderMap, ctx: &RequestContext) -> AuditReport {
+    let mut auditor = Auditor {
+        headers,
+        findings: Vec::new(),
+    };
+    let secure_context = ctx.is_https() && !ctx.is_localhost();
+
+    audit_csp(&mut auditor);
+    audit_hsts(&mut auditor, secure_context);
+    audit_simple_headers(&mut auditor);
+    audit_referrer_policy(&mut auditor);
+    audit_cookies(&mut auditor, secure_context);
+    audit_deprecated(&mut auditor);
+    audit_leaks(&mut auditor);
+
+    let mut findings = auditor.findings;
+    findings.sort_by_key(|finding| Reverse(finding.severity));
+    AuditReport { findings }
+}
+
+fn audit_csp(auditor: &mut Auditor<'_>) {
+    let header = "Content-Security-Policy";
+    let policies = ContentSecurityPolicy::from_headers(auditor.headers);
+    let frame_ancestors = policies
+        .iter()
+        .any(|policy| !policy.is_report_only() && policy.has_directive("frame-ancestors"));
+    if !frame_ancestors && auditor.get("X-Frame-Options").is_none() {
+        let message = "neither `frame-ancestors` nor `X-Frame-Options` prevents framing";
+        auditor.push(
+            Category::Missing,
+            Severity::Medium,
+            header,
+            message.to_string(),
+        );
+    }
+
+    if policies.is_empty() {
+        let message = "no policy restricts where content can load from".to_string();
+        auditor.push(Category::Missing, Severity::High, header, message);
+        return;
+    }
+    if policies.iter().all(ContentSecurityPolicy::is_report_only) {
+        let message = "the policy is only reported, not enforced".to_string();
+        auditor.push(Category::Weak, Severity::High, header, message);
+    }
+    for lint in csp::lint_all(&policies) {
+        auditor.push(Category::Weak, lint.severity, header, lint.message);
+    }
+}
+
+fn audit_hsts(auditor: &mut Auditor<'_>, secure_context: bool) {
+    let header = "Strict-Transport-Security";
+    let value = match auditor.get(header) {
+        Some(value) => value,
+        None if secure_context => {
+            let message = "browsers may connect over plain HTTP".to_string();
+            auditor.push(Category::Missing, Severity::High, header, message);
+            return;
+        }
+        None => return,
+    };
+    if !secure_context {
+        let message = "ignored by browsers over plain HTTP and on local hosts".to_string();
+        auditor.push(Category::Weak, Severity::Info, header, message);
+        return;
+    }
+
+    let max_age = value.split(';').find_map(|directive| {
+        let (name, value) = directive.split_once('=')?;
+        if name.trim().eq_ignore_ascii_case("max-age") {
+            value.trim().trim_matches('"').parse::<u64>().ok()
+        } else {
+            None
+        }
+    });
+    match max_age {
+        None => {
+            let message = "`max-age` is missing or invalid".to_string();
+            auditor.push(Category::Weak, Severity::High, header, message);
+        }
+        Some(0) => {
+            let message = "`max-age=0` disables HSTS".to_string();
+            auditor.push(Category::Weak, Severity::High, header, message);
+        }
+        Some(max_age) if max_age < MIN_HSTS_MAX_AGE => {
+            let message = format!("`max-age={}` is shorter than 180 days", max_age);
+            auditor.push(Category::Weak, Severity::Medium, header, message);
+        }
+        Some(_) => {}
+    }
+}
+
+fn audit_simple_headers(auditor: &mut Auditor<'_>) {
+    match auditor.get("X-Content-Type-Options") {
+        Some(ref value) if value.eq_ignore_ascii_case("nosniff") => {}
+        Some(value) => {
+            let message = format!("`{}` isn't `nosniff`", value);
+            auditor.push(
+                Category::Weak,
+                Severity::Medium,
+                "X-Content-Type-Options",
+                message,
+            );
+        }
+        None => {
+            let message = "browsers may sniff content types".to_string();
+            auditor.push(
+                Category::Missing,
+                Severity::Medium,
+                "X-Content-Type-Options",
+                message,
+            );
+        }
+    }
+
+    let missing: &[(&str, Severity, &str)] = &[
+        (
+            "Permissions-Policy",
+            Severity::Low,
+            "powerful browser features aren't restricted",
+        ),
+        (
+            "Cross-Origin-Opener-Policy",
+            Severity::Low,
+            "cross-origin windows keep a reference to the page",
+        ),
+    ];
+    for (header, severity, message) in missing {
+        if auditor.get(header).is_none() {
+            auditor.push(Category::Missing, *severity, header, message.to_string());
+        }
+    }
+}
+
+fn audit_referrer_policy(auditor: &mut Auditor<'_>) {
+    let header = "Referrer-Policy";
+    let effective = match ReferrerPolicy::from_headers(auditor.headers) {
+        Some(policy) => policy.effective(),
+        None => {
+            let message = "browsers default to `strict-origin-when-cross-origin`".to_string();
+            auditor.push(Category::Missing, Severity::Low, header, message);
+            return;
+        }
+    };
+    match effective {
+        Some(ReferrerOptions::UnsafeUrl) => {
+            let message = "`unsafe-url` leaks full URLs, even over plain HTTP".to_string();
+            auditor.push(Category::Weak, Severity::Medium, header, message);
+        }
+        Some(ReferrerOptions::NoReferrerDowngrade) => {
+            let message = "`no-referrer-when-downgrade` leaks full URLs to other sites".to_string();
+            auditor.push(Category::Weak, Severity::Low, header, message);
+        }
+        Some(_) => {}
+        None => {
+            let message = "no recognized policy".to_string();
+            auditor.push(Category::Weak, Severity::Low, header, message);
+        }
+    }
+}
+
+fn audit_cookies(auditor: &mut Auditor<'_>, secure_context: bool) {
+    let values: Vec<String> = auditor
+        .headers
+        .get_all(SET_COOKIE)
+        .iter()
+        .map(|value| String::from_utf8_lossy(value.as_bytes()).into_owned())
+        .collect();
+    for value in values {
+        let cookie = match Cookie::parse(&value) {
+            Some(cookie) => cookie,
+            None => continue,
+        };
+        if secure_context && !cookie.secure {
+            let message = format!("cookie `{}` is sent without `Secure`", cookie.name);
+            auditor.push(Category::Weak, Severity::Medium, "Set-Cookie", message);
+        }
+        if !cookie.http_only {
+            let message = format!("cookie `{}` is readable from JavaScript", cookie.name);
+            auditor.push(Category::Weak, Severity::Info, "Set-Cookie", message);
+        }
+        for violation in cookie.violations() {
+            auditor.push(
+                Category::Weak,
+                Severity::Medium,
+                "Set-Cookie",
+                violation.to_string(),
+            );
+        }
+    }
+}
+
+fn audit_deprecated(auditor: &mut Auditor<'_>) {
+    if let Some(value) = auditor.get("X-XSS-Protection") {
+        if value != "0" {
+            let message = "the XSS auditor is removed from browsers and can introduce leaks; \
+                           send `0` or omit the header";
+            auditor.push(
+                Category::Deprecated,
+                Severity::Low,
+                "X-XSS-Protection",
+                message.to_string(),
+            );
+        }
+    }
+    let deprecated: &[(&str, Severity, &str)] = &[
+        (
+            "Public-Key-Pins",
+            Severity::Low,
+            "key pinning can lock users out of the site",
+        ),
+        (
+            "Expect-CT",
+            Severity::Info,
+            "Certificate Transparency is enforced by default",
+        ),
+        (
+            "Feature-Policy",
+            Severity::Info,
+            "replaced by `Permissions-Policy`",
+        ),
+        ("P3P", Severity::Info, "ignored by browsers"),
+    ];
+    for (header, severity, message) in deprecated {
+        if auditor.get(header).is_some() {
+            auditor.push(Category::Deprecated, *severity, header, message.to_string());
+        }
+    }
+}
+
+fn audit_leaks(auditor: &mut Auditor<'_>) {
+    for name in LEAKY_HEADERS {
+        let name = HeaderName::from_static(name);
+        let value = match auditor.headers.get(&name) {
+            Some(value) => String::from_utf8_lossy(value.as_bytes()).trim().to_string(),
+            None => continue,
+        };
+        // A generic `Server` value without a version is common and harmless.
+        let versioned = value.contains('/') || value.bytes().any(|byte| byte.is_ascii_digit());
+        let severity = if name == SERVER && !versioned {
+            Severity::Info
+        } else {
+            Severity::Low
+        };
+        let message = format!("discloses `{}`", value);
+        auditor.push(Category::Leak, severity, &display_name(&name), message);
+    }
+}
+
+/// Capitalize each word of a header name, e.g. `X-Powered-By`.
+fn display_name(name: &HeaderName) -> String {
+    name.as_str()
+        .split('-')
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_ascii_uppercase().to_string() + chars.as_str(),
+                None => String::new(),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("-")
+}
@@ -0,0 +1,105 @@
+//! `armor audit`: grade saved responses.
+
+use crate::response::parse_headers;
+use crate::{print_table, read_bytes, wants_help, Args, Format, EXIT_FINDINGS, USAGE};
+use armor::{AuditReport, RequestContext, Severity};
+use serde_json::json;
+
+pub fn run(args: &[String]) -> Result<i32, String> {
+    if wants_help(args) {
+        print!("{}", USAGE);
+        return Ok(0);
+    }
+    let args = Args::parse(args, &["--format", "--fail-on", "--scheme", "--host"])?;
+    let format = Format::parse(args.option("--format"))?;
+    let fail_on = args.option("--fail-on").unwrap_or("high");
+    let fail_on: Severity = fail_on
+        .parse()
+        .map_err(|_| format!("unknown severity `{}`", fail_on))?;
+
+    let mut ctx = RequestContext::new();
+    ctx.scheme(args.option("--scheme").unwrap_or("https"));
+    if let Some(host) = args.option("--host") {
+        ctx.host(host);
+    }
+
+    let stdin = ["-".to_string()];
+    let paths = match args.positional() {
+        [] => &stdin[..],
+        paths => paths,
+    };
+    let mut reports = Vec::new();
+    for path in paths {
+        let headers = parse_headers(&read_bytes(path)?);
+        reports.push((path.as_str(), armor::audit(&headers, &ctx)));
+    }
+
+    match format {
+        Format::Table => print_reports(&reports),
+        Format::Json => {
+            let reports: Vec<_> = reports
+                .iter()
+                .map(|(path, report)| report_json(path, report))
+                .collect();
+            println!("{}", serde_json::to_string_pretty(&reports).unwrap());
+        }
+    }
+
+    let failed = reports
+        .iter()
+        .any(|(_, report)| report.max_severity().is_some_and(|max| max >= fail_on));
+    Ok(if failed { EXIT_FINDINGS } else { 0 })
+}
+
+/// Print each report's grade and a table of its findings.
+pub fn print_reports(reports: &[(&str, AuditReport)]) {
+    for (index, (path, report)) in reports.iter().enumerate() {
+        if index > 0 {
+            println!();
+        }
+        println!(
+            "{}: grade {} ({}/100)",
+            path,
+            report.grade(),
+            report.score()
+        );
+        if report.findings().is_empty() {
+            continue;
+        }
+        let rows: Vec<Vec<String>> = report
+            .findings()
+            .iter()
+            .map(|finding| {
+                vec![
+                    finding.severity.to_string(),
+                    finding.category.to_string(),
+                    finding.header.clone(),
+                    finding.message.clone(),
+                ]
+            })
+            .collect();
+        print_table(&["SEVERITY", "CATEGORY", "HEADER", "MESSAGE"], &rows);
+    }
+}
+
+/// Serialize a report for `--format json`.
+pub fn report_json(source: &str, report: &AuditReport) -> serde_json::Value {
+    let findings: Vec<_> = report
+        .findings()
+        .iter()
+        .map(|finding| {
+            json!({
+                "severity": finding.severity.as_ref(),
+                "category": finding.category.as_ref(),
+                "header": finding.header,
+                "message": finding.message,
+            })
+        })
+        .collect();
+    json!({
+        "source": source,
+        "grade": report.grade().as_ref(),
+        "score": report.score(),
+        "findings": findings,
+    })
+}
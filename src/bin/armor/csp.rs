@@ -0,0 +1,232 @@
+//! `armor csp`: inspect Content Security Policies.
+
+use crate::response::parse_headers;
+use crate::{print_table, read_input, wants_help, Args, Format, EXIT_FINDINGS, USAGE};
+use armor::csp::{self, ContentSecurityPolicy, HashAlgorithm};
+use armor::Severity;
+use serde_json::json;
+use std::path::Path;
+
+pub fn run(args: &[String]) -> Result<i32, String> {
+    if args.is_empty() || wants_help(args) {
+        print!("{}", USAGE);
+        return Ok(0);
+    }
+    let command = args[0].as_str();
+    let options: &[&str] = match command {
+        "parse" => &["--format"],
+        "lint" => &["--format", "--fail-on"],
+        "hash" => &["--algorithm"],
+        "diff" | "explain" => &[],
+        _ => return Err(format!("unknown csp command `{}`", command)),
+    };
+    let args = Args::parse(&args[1..], options)?;
+    match command {
+        "parse" => parse(&args),
+        "lint" => lint(&args),
+        "diff" => diff(&args),
+        "explain" => explain(&args),
+        _ => hash(&args),
+    }
+}
+
+/// The single input of a command, defaulting to stdin.
+fn single_input(args: &Args) -> Result<&str, String> {
+    match args.positional() {
+        [] => Ok("-"),
+        [input] => Ok(input),
+        _ => Err("expected a single policy, file or `-`".to_string()),
+    }
+}
+
+/// Read an argument that is either a file, `-` for stdin, or the policy itself.
+fn read_argument(input: &str) -> Result<String, String> {
+    if input == "-" || Path::new(input).is_file() {
+        read_input(input)
+    } else {
+        Ok(input.to_string())
+    }
+}
+
+fn is_html(content: &str) -> bool {
+    content.trim_start().starts_with('<')
+}
+
+/// Whether the content is a saved response or a list of headers rather than a bare policy.
+fn is_response(content: &str) -> bool {
+    let first = content.trim_start().lines().next().unwrap_or("");
+    first.starts_with("HTTP/")
+        || first
+            .split_once(':')
+            .is_some_and(|(name, _)| !name.is_empty() && !name.contains(char::is_whitespace))
+}
+
+/// Load the policies from a header value, a saved response or an HTML document.
+fn load_policies(input: &str) -> Result<Vec<ContentSecurityPolicy>, String> {
+    let content = read_argument(input)?;
+    if is_html(&content) {
+        let policy = csp::from_html(&content).ok_or_else(|| {
+            format!(
+                "{}: no `<meta http-equiv=\"Content-Security-Policy\">` element",
+                input
+            )
+        })?;
+        Ok(vec![policy])
+    } else if is_response(&content) {
+        let headers = parse_headers(content.as_bytes());
+        let policies = ContentSecurityPolicy::from_headers(&headers);
+        if policies.is_empty() {
+            return Err(format!("{}: no Content-Security-Policy header", input));
+        }
+        Ok(policies)
+    } else {
+        Ok(content
+            .split(',')
+            .filter(|policy| !policy.trim().is_empty())
+            .map(|policy| ContentSecurityPolicy::parse(policy.trim()))
+            .collect())
+    }
+}
+
+/// Load a single policy, for commands that can't combine several.
+fn load_policy(input: &str) -> Result<ContentSecurityPolicy, String> {
+    let mut policies = load_policies(input)?;
+    match policies.len() {
+        0 => Ok(ContentSecurityPolicy::new()),
+        1 => Ok(policies.remove(0)),
+        count => Err(format!(
+            "{}: contains {} policies; pass them one at a time",
+            input, count
+        )),
+    }
+}
+
+fn parse(args: &Args) -> Result<i32, String> {
+    let format = Format::parse(args.option("--format"))?;
+    let policy = load_policy(single_input(args)?)?;
+    let names = policy.directive_names();
+    match format {
+        Format::Table => {
+            if policy.is_report_only() {
+                println!("# report-only");
+            }
+            for name in names {
+                let sources = policy.directive(name).unwrap_or(&[]);
+                println!("{}", [&[name.to_string()], sources].concat().join(" "));
+            }
+        }
+        Format::Json => {
+            let directives: serde_json::Map<_, _> = names
+                .iter()
+                .map(|name| (name.to_string(), json!(policy.directive(name))))
+                .collect();
+            let policy = json!({
+                "report_only": policy.is_report_only(),
+                "directives": directives,
+            });
+            println!("{}", serde_json::to_string_pretty(&policy).unwrap());
+        }
+    }
+    Ok(0)
+}
+
+fn lint(args: &Args) -> Result<i32, String> {
+    let format = Format::parse(args.option("--format"))?;
+    let fail_on = args.option("--fail-on").unwrap_or("high");
+    let fail_on: Severity = fail_on
+        .parse()
+        .map_err(|_| format!("unknown severity `{}`", fail_on))?;
+    let policies = load_policies(single_input(args)?)?;
+
+    let mut lints = csp::lint_all(&policies);
+    lints.sort_by_key(|lint| std::cmp::Reverse(lint.severity));
+    match format {
+        Format::Table if lints.is_empty() => println!("no issues found"),
+        Format::Table => {
+            let rows: Vec<Vec<String>> = lints
+                .iter()
+                .map(|lint| {
+                    vec![
+                        lint.severity.to_string(),
+                        lint.directive.clone().unwrap_or_else(|| "-".to_string()),
+                        lint.message.clone(),
+                    ]
+                })
+                .collect();
+            print_table(&["SEVERITY", "DIRECTIVE", "MESSAGE"], &rows);
+        }
+        Format::Json => {
+            let lints: Vec<_> = lints
+                .iter()
+                .map(|lint| {
+                    json!({
+                        "severity": lint.severity.as_ref(),
+                        "directive": lint.directive,
+                        "message": lint.message,
+                    })
+                })
+                .collect();
+            println!("{}", serde_json::to_string_pretty(&lints).unwrap());
+        }
+    }
+
+    let failed = lints.iter().any(|lint| lint.severity >= fail_on);
+    Ok(if failed { EXIT_FINDINGS } else { 0 })
+}
+
+fn diff(args: &Args) -> Result<i32, String> {
+    let (old, new) = match args.positional() {
+        [old, new] => (load_policy(old)?, load_policy(new)?),
+        _ => return Err("expected an old and a new policy".to_string()),
+    };
+    let changes = old.diff(&new);
+    for change in &changes {
+        println!("{}", change);
+    }
+    Ok(if changes.is_empty() { 0 } else { 1 })
+}
+
+fn explain(args: &Args) -> Result<i32, String> {
+    let policy = load_policy(single_input(args)?)?;
+    if policy.is_report_only() {
+        println!("Report-only: violations are reported but not blocked.\n");
+    }
+    for (index, name) in policy.directive_names().into_iter().enumerate() {
+        if index > 0 {
+            println!();
+        }
+        match csp::describe_directive(name) {
+            Some(description) => println!("{}: {}", name, description),
+            None => println!("{}: unknown directive, ignored by browsers", name),
+        }
+        for source in policy.directive(name).unwrap_or(&[]) {
+            println!("  {:<28}  {}", source, csp::describe_source(source));
+        }
+    }
+    Ok(0)
+}
+
+fn hash(args: &Args) -> Result<i32, String> {
+    let algorithm = match args.option("--algorithm") {
+        Some(algorithm) => algorithm
+            .parse()
+            .map_err(|_| format!("unknown hash algorithm `{}`", algorithm))?,
+        None => HashAlgorithm::Sha256,
+    };
+    let stdin = ["-".to_string()];
+    let paths = match args.positional() {
+        [] => &stdin[..],
+        paths => paths,
+    };
+    for path in paths {
+        let content = read_input(path)?;
+        if is_html(&content) {
+            for (element, hash) in csp::inline_hashes(&content, algorithm) {
+                println!("{}  {}-src  {}", hash, element, path);
+            }
+        } else {
+            println!("{}  {}", csp::hash_source(algorithm, &content), path);
+        }
+    }
+    Ok(0)
+}
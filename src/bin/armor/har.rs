@@ -0,0 +1,162 @@
+//! `armor har`: analyze browser captures.
+
+use crate::audit::{print_reports, report_json};
+use crate::{print_table, read_input, wants_help, Args, Format, EXIT_FINDINGS, USAGE};
+use armor::har::{self, Har, PageReport, PolicyGenerator};
+use armor::Severity;
+use serde_json::json;
+
+pub fn run(args: &[String]) -> Result<i32, String> {
+    if args.is_empty() || wants_help(args) {
+        print!("{}", USAGE);
+        return Ok(0);
+    }
+    match args[0].as_str() {
+        "audit" => audit(&Args::parse(&args[1..], &["--format", "--fail-on"])?),
+        "csp" => generate(&Args::parse(&args[1..], &["--page", "--threshold"])?),
+        command => Err(format!("unknown har command `{}`", command)),
+    }
+}
+
+/// Load the HAR file named by the only positional argument, or stdin.
+pub fn load(args: &Args) -> Result<Har, String> {
+    let path = match args.positional() {
+        [] => "-",
+        [path] => path,
+        _ => return Err("expected a single HAR file or `-`".to_string()),
+    };
+    Har::parse(&read_input(path)?).map_err(|err| format!("{}: {}", path, err))
+}
+
+fn audit(args: &Args) -> Result<i32, String> {
+    let format = Format::parse(args.option("--format"))?;
+    let fail_on = args.option("--fail-on").unwrap_or("high");
+    let fail_on: Severity = fail_on
+        .parse()
+        .map_err(|_| format!("unknown severity `{}`", fail_on))?;
+    let reports = har::analyze(&load(args)?);
+
+    match format {
+        Format::Table => {
+            for (index, page) in reports.iter().enumerate() {
+                if index > 0 {
+                    println!();
+                }
+                print_page(page);
+            }
+        }
+        Format::Json => {
+            let reports: Vec<_> = reports.iter().map(page_json).collect();
+            println!("{}", serde_json::to_string_pretty(&reports).unwrap());
+        }
+    }
+
+    let failed = reports.iter().any(|page| {
+        let enforced = page.policies.iter().any(|policy| !policy.is_report_only());
+        (enforced && !page.blocked.is_empty())
+            || page
+                .documents
+                .iter()
+                .any(|(_, report)| report.max_severity().is_some_and(|max| max >= fail_on))
+    });
+    Ok(if failed { EXIT_FINDINGS } else { 0 })
+}
+
+fn generate(args: &Args) -> Result<i32, String> {
+    let mut generator = PolicyGenerator::new();
+    if let Some(threshold) = args.option("--threshold") {
+        let threshold = threshold
+            .parse()
+            .map_err(|_| format!("invalid threshold `{}`", threshold))?;
+        generator.subdomain_threshold(threshold);
+    }
+    if let Some(directives) = args.option("--exact") {
+        for directive in directives.split(',') {
+            generator.wildcards(directive.trim(), false);
+        }
+    }
+    let har = load(args)?;
+    let mut pages = har.pages();
+    if let Some(id) = args.option("--page") {
+        pages.retain(|page| page.id() == id);
+        if pages.is_empty() {
+            return Err(format!("no page `{}` in the capture", id));
+        }
+    }
+    println!("{}", generator.generate(&pages).value());
+    Ok(0)
+}
+
+fn page_name(page: &PageReport) -> String {
+    let id = if page.id.is_empty() { "-" } else { &page.id };
+    match page.title.as_str() {
+        "" => id.to_string(),
+        title => format!("{} ({})", id, title),
+    }
+}
+
+fn print_page(page: &PageReport) {
+    println!("== page {}", page_name(page));
+    if page.documents.is_empty() {
+        println!("no document response recorded");
+        return;
+    }
+    let documents: Vec<_> = page
+        .documents
+        .iter()
+        .map(|(url, report)| (url.as_str(), report.clone()))
+        .collect();
+    print_reports(&documents);
+
+    let header = match page.policies.first() {
+        None => return,
+        Some(policy) if policy.is_report_only() => "Content-Security-Policy-Report-Only",
+        Some(_) => "Content-Security-Policy",
+    };
+    println!();
+    if page.blocked.is_empty() {
+        println!("{} allows every subresource load", header);
+        return;
+    }
+    println!("{} blocks {} load(s):", header, page.blocked.len());
+    let rows: Vec<Vec<String>> = page
+        .blocked
+        .iter()
+        .map(|load| {
+            vec![
+                load.directive.to_string(),
+                load.kind.to_string(),
+                load.url.clone(),
+            ]
+        })
+        .collect();
+    print_table(&["DIRECTIVE", "KIND", "URL"], &rows);
+}
+
+fn page_json(page: &PageReport) -> serde_json::Value {
+    let documents: Vec<_> = page
+        .documents
+        .iter()
+        .map(|(url, report)| report_json(url, report))
+        .collect();
+    let blocked: Vec<_> = page
+        .blocked
+        .iter()
+        .map(|load| {
+            json!({
+                "url": load.url,
+                "kind": load.kind.as_ref(),
+                "directive": load.directive,
+            })
+        })
+        .collect();
+    json!({
+        "page": page.id,
+        "title": page.title,
+        "url": page.url,
+        "policies": page.policies.iter().map(|policy| policy.value()).collect::<Vec<_>>(),
+        "report_only": page.policies.first().is_some_and(|policy| policy.is_report_only()),
+        "documents": documents,
+        "blocked": blocked,
+    })
+}
@@ -0,0 +1,201 @@
+//! Audit the security headers of saved HTTP responses and browser captures, and inspect
+//! Content Security Policies.
+//!
+//! Requires the `cli` feature: `cargo install armor --features cli`.
+
+mod audit;
+mod csp;
+mod har;
+mod response;
+
+use std::fs;
+use std::io::{self, Read};
+use std::process;
+
+pub const USAGE: &str = "\
+armor - audit HTTP security headers
+
+USAGE:
+    armor audit [OPTIONS] [FILE]...
+    armor csp parse [--format <table|json>] [POLICY]
+    armor csp lint [--format <table|json>] [--fail-on <SEVERITY>] [POLICY]
+    armor csp diff OLD NEW
+    armor csp explain [POLICY]
+    armor csp hash [--algorithm <sha256|sha384|sha512>] [FILE]...
+    armor har audit [--format <table|json>] [--fail-on <SEVERITY>] [FILE]
+    armor har csp [--page <ID>] [--threshold <N>] [--exact <DIRECTIVES>] [FILE]
+
+`audit` reads raw HTTP/1.1 responses (status line and headers) from each FILE, or
+from stdin when no FILE or `-` is given.
+
+`csp` commands take a POLICY as a header value, or as a file or stdin (`-`, the
+default) holding a header value, a saved response or an HTML document with a
+`<meta http-equiv=\"Content-Security-Policy\">` element. `diff` exits with 1 when
+the policies differ. `hash` prints a hash source for each inline script and style
+of an HTML document, or for the whole content of any other file.
+
+`har audit` reads a HAR capture from FILE or stdin, audits every document response
+and lists the subresource loads each page's Content-Security-Policy blocks. It
+exits with 1 when an enforced policy blocks a load, too. `har csp` prints a starter
+Content-Security-Policy allowing what the captured pages loaded.
+
+OPTIONS:
+    --format <table|json>    Output format [default: table]
+    --fail-on <SEVERITY>     Exit with 1 when a finding is at least this severe:
+                             info, low, medium or high [default: high]
+    --scheme <SCHEME>        Scheme the responses were served over [default: https]
+    --host <HOST>            Host the responses were served from
+    --algorithm <ALGORITHM>  Hash algorithm [default: sha256]
+    --page <ID>              Only use the requests of this HAR page
+    --threshold <N>          Collapse this many subdomains of a domain into a
+                             wildcard source [default: 3]
+    --exact <DIRECTIVES>     Never use wildcard sources in these comma-separated
+                             directives
+    -h, --help               Print this help
+";
+
+/// Exit code for findings at or above the threshold.
+pub const EXIT_FINDINGS: i32 = 1;
+/// Exit code for invalid arguments or unreadable input.
+const EXIT_ERROR: i32 = 2;
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let result = match args.first().map(String::as_str) {
+        Some("audit") => audit::run(&args[1..]),
+        Some("csp") => csp::run(&args[1..]),
+        Some("har") => har::run(&args[1..]),
+        Some("-h") | Some("--help") | Some("help") => {
+            print!("{}", USAGE);
+            Ok(0)
+        }
+        Some(command) => Err(format!("unknown command `{}`", command)),
+        None => Err("missing command".to_string()),
+    };
+    match result {
+        Ok(code) => process::exit(code),
+        Err(message) => {
+            eprintln!("error: {}\n\n{}", message, USAGE);
+            process::exit(EXIT_ERROR);
+        }
+    }
+}
+
+/// Command-line arguments split into `--name value` options and positional arguments.
+pub struct Args {
+    options: Vec<(String, String)>,
+    positional: Vec<String>,
+}
+
+impl Args {
+    /// Parse arguments, where every name in `options` takes a value.
+    pub fn parse(args: &[String], options: &[&str]) -> Result<Self, String> {
+        let mut parsed = Args {
+            options: Vec::new(),
+            positional: Vec::new(),
+        };
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            if arg.starts_with("--") || (arg.starts_with('-') && arg.len() > 1) {
+                let (name, inline) = match arg.split_once('=') {
+                    Some((name, value)) => (name, Some(value.to_string())),
+                    None => (arg.as_str(), None),
+                };
+                if !options.contains(&name) {
+                    return Err(format!("unknown option `{}`", name));
+                }
+                let value = match inline {
+                    Some(value) => value,
+                    None => args
+                        .next()
+                        .cloned()
+                        .ok_or_else(|| format!("missing value for `{}`", name))?,
+                };
+                parsed.options.push((name.to_string(), value));
+            } else {
+                parsed.positional.push(arg.clone());
+            }
+        }
+        Ok(parsed)
+    }
+
+    /// The last value of an option.
+    pub fn option(&self, name: &str) -> Option<&str> {
+        self.options
+            .iter()
+            .rev()
+            .find(|(option, _)| option == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// The positional arguments.
+    pub fn positional(&self) -> &[String] {
+        &self.positional
+    }
+}
+
+/// Read a file, or stdin for `-`.
+pub fn read_input(path: &str) -> Result<String, String> {
+    String::from_utf8(read_bytes(path)?).map_err(|_| format!("{}: not valid UTF-8", path))
+}
+
+/// Read a file, or stdin for `-`, as raw bytes.
+pub fn read_bytes(path: &str) -> Result<Vec<u8>, String> {
+    if path == "-" {
+        let mut input = Vec::new();
+        io::stdin()
+            .read_to_end(&mut input)
+            .map_err(|err| format!("stdin: {}", err))?;
+        Ok(input)
+    } else {
+        fs::read(path).map_err(|err| format!("{}: {}", path, err))
+    }
+}
+
+/// Whether `--help` or `-h` was passed.
+pub fn wants_help(args: &[String]) -> bool {
+    args.iter().any(|arg| arg == "--help" || arg == "-h")
+}
+
+/// Output format for reports.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Table,
+    Json,
+}
+
+impl Format {
+    pub fn parse(format: Option<&str>) -> Result<Self, String> {
+        match format {
+            None | Some("table") => Ok(Format::Table),
+            Some("json") => Ok(Format::Json),
+            Some(format) => Err(format!("unknown format `{}`", format)),
+        }
+    }
+}
+
+/// Print rows as a table with aligned columns.
+pub fn print_table(header: &[&str], rows: &[Vec<String>]) {
+    let mut widths: Vec<usize> = header.iter().map(|cell| cell.len()).collect();
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.len());
+        }
+    }
+    let line = |cells: Vec<&str>| {
+        let last = cells.len() - 1;
+        let mut line = String::new();
+        for (index, cell) in cells.into_iter().enumerate() {
+            if index == last {
+                line.push_str(cell);
+            } else {
+                line.push_str(&format!("{:width$}  ", cell, width = widths[index]));
+            }
+        }
+        println!("{}", line.trim_end());
+    };
+    line(header.to_vec());
+    for row in rows {
+        line(row.iter().map(String::as_str).collect());
+    }
+}
@@ -0,0 +1,78 @@
+//! Parse saved HTTP/1.1 responses.
+
+use http::header::{HeaderName, HeaderValue};
+use http::HeaderMap;
+
+/// Parse the headers of a raw HTTP/1.1 response, ignoring the status line and body.
+///
+/// Accepts both CRLF and LF line endings and obsolete folded header lines. A leading status
+/// line is optional, so a bare list of headers works too. Values are kept as raw bytes, so
+/// UTF-8 and other non-ASCII values are accepted. Lines that aren't valid headers are skipped
+/// with a warning.
+pub fn parse_headers(raw: &[u8]) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    let mut current: Option<(usize, HeaderName, Vec<u8>)> = None;
+    let mut lines = raw
+        .split(|&byte| byte == b'\n')
+        .map(|line| line.strip_suffix(b"\r").unwrap_or(line))
+        .enumerate()
+        .map(|(index, line)| (index + 1, line))
+        .peekable();
+
+    if lines
+        .peek()
+        .is_some_and(|(_, line)| line.starts_with(b"HTTP/"))
+    {
+        lines.next();
+    }
+    for (number, line) in lines {
+        if line.is_empty() {
+            break;
+        }
+        if line.starts_with(b" ") || line.starts_with(b"\t") {
+            match current {
+                Some((_, _, ref mut value)) => {
+                    value.push(b' ');
+                    value.extend_from_slice(line.trim_ascii());
+                }
+                None => warn(number, "continuation without a header"),
+            }
+            continue;
+        }
+        if let Some((number, name, value)) = current.take() {
+            append(&mut headers, number, name, &value);
+        }
+        let colon = match line.iter().position(|&byte| byte == b':') {
+            Some(colon) => colon,
+            None => {
+                warn(number, "expected `name: value`");
+                continue;
+            }
+        };
+        let name = line[..colon].trim_ascii();
+        match HeaderName::from_bytes(name) {
+            Ok(name) => current = Some((number, name, line[colon + 1..].trim_ascii().to_vec())),
+            Err(_) => warn(
+                number,
+                &format!("invalid header name `{}`", String::from_utf8_lossy(name)),
+            ),
+        }
+    }
+    if let Some((number, name, value)) = current {
+        append(&mut headers, number, name, &value);
+    }
+    headers
+}
+
+fn append(headers: &mut HeaderMap, number: usize, name: HeaderName, value: &[u8]) {
+    match HeaderValue::from_bytes(value) {
+        Ok(value) => {
+            headers.append(name, value);
+        }
+        Err(_) => warn(number, &format!("invalid value for header `{}`", name)),
+    }
+}
+
+fn warn(number: usize, message: &str) {
+    eprintln!("warning: line {}: {}, skipped", number, message);
+}
@@ -0,0 +1,190 @@
+//! Information about the request a response is being built for.
+
+use http::header::{HeaderMap, HOST};
+use http::{Request, Uri};
+use std::net::IpAddr;
+
+/// Describe the request a response is being built for.
+///
+/// Some protections only make sense in a given context. Browsers ignore
+/// `Strict-Transport-Security` when it's sent over plain HTTP, and sending it
+/// from `localhost` pins every project served from that host to HTTPS.
+///
+/// Server-side request URIs usually don't carry a scheme, so when TLS is
+/// terminated by the application itself the scheme should be set explicitly.
+/// When TLS is terminated by a reverse proxy, `trust_proxy` makes the context
+/// honor the `Forwarded` and `X-Forwarded-Proto` / `X-Forwarded-Host` headers.
+/// Only enable it when the proxy overwrites those headers, since clients can
+/// set them freely.
+///
+/// ## Examples
+/// ```
+/// let req = http::Request::get("/")
+///     .header("Host", "example.com")
+///     .header("X-Forwarded-Proto", "https")
+///     .body(())
+///     .unwrap();
+///
+/// let mut ctx = armor::RequestContext::from_request(&req);
+/// assert!(!ctx.is_https());
+///
+/// ctx.trust_proxy(true);
+/// assert!(ctx.is_https());
+/// assert_eq!(ctx.effective_host(), Some("example.com"));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct RequestContext {
+    scheme: Option<String>,
+    host: Option<String>,
+    forwarded_proto: Option<String>,
+    forwarded_host: Option<String>,
+    trust_proxy: bool,
+}
+
+impl RequestContext {
+    /// Create a new instance.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a context from a request.
+    pub fn from_request<B>(req: &Request<B>) -> Self {
+        Self::from_parts(req.uri(), req.headers())
+    }
+
+    /// Create a context from a request's URI and headers.
+    pub fn from_parts(uri: &Uri, headers: &HeaderMap) -> Self {
+        let header = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+        };
+
+        let host = uri
+            .authority_part()
+            .map(|authority| authority.as_str())
+            .or_else(|| header(HOST.as_str()));
+
+        let forwarded = header("Forwarded").map(parse_forwarded).unwrap_or_default();
+        let first = |value: &str| value.split(',').next().unwrap_or("").trim().to_string();
+
+        Self {
+            scheme: uri.scheme_str().map(String::from),
+            host: host.map(String::from),
+            forwarded_proto: forwarded
+                .0
+                .or_else(|| header("X-Forwarded-Proto").map(first)),
+            forwarded_host: forwarded
+                .1
+                .or_else(|| header("X-Forwarded-Host").map(first)),
+            trust_proxy: false,
+        }
+    }
+
+    /// Set the scheme the request was received over.
+    pub fn scheme<T: AsRef<str>>(&mut self, scheme: T) -> &mut Self {
+        self.scheme = Some(scheme.as_ref().to_string());
+        self
+    }
+
+    /// Set the host the request was sent to.
+    pub fn host<T: AsRef<str>>(&mut self, host: T) -> &mut Self {
+        self.host = Some(host.as_ref().to_string());
+        self
+    }
+
+    /// Honor `Forwarded`, `X-Forwarded-Proto` and `X-Forwarded-Host` headers.
+    pub fn trust_proxy(&mut self, trust: bool) -> &mut Self {
+        self.trust_proxy = trust;
+        self
+    }
+
+    /// The scheme the client used to reach us, if known.
+    pub fn effective_scheme(&self) -> Option<&str> {
+        let forwarded = self.forwarded_proto.as_ref().filter(|_| self.trust_proxy);
+        forwarded.or(self.scheme.as_ref()).map(String::as_str)
+    }
+
+    /// The host the client used to reach us, if known. May include a port.
+    pub fn effective_host(&self) -> Option<&str> {
+        let forwarded = self.forwarded_host.as_ref().filter(|_| self.trust_proxy);
+        forwarded.or(self.host.as_ref()).map(String::as_str)
+    }
+
+    /// Whether the request was made over HTTPS.
+    pub fn is_https(&self) -> bool {
+        self.effective_scheme()
+            .is_some_and(|scheme| scheme.eq_ignore_ascii_case("https"))
+    }
+
+    /// Whether the request was made to a loopback or `localhost` address.
+    ///
+    /// ## Examples
+    /// ```
+    /// let mut ctx = armor::RequestContext::new();
+    /// assert!(ctx.host("localhost:8080").is_localhost());
+    /// assert!(ctx.host("app.localhost").is_localhost());
+    /// assert!(ctx.host("[::1]:3000").is_localhost());
+    /// assert!(!ctx.host("example.com").is_localhost());
+    /// ```
+    pub fn is_localhost(&self) -> bool {
+        self.effective_host().is_some_and(is_local_host)
+    }
+
+    /// Whether the request was made over HTTPS to a non-local host.
+    ///
+    /// Protections that break plain-HTTP and local development setups, like
+    /// `Strict-Transport-Security` and `Secure` cookies, are only applied then.
+    pub fn is_secure(&self) -> bool {
+        self.is_https() && !self.is_localhost()
+    }
+}
+
+/// Extract the `proto` and `host` parameters from the first `Forwarded` element.
+fn parse_forwarded(value: &str) -> (Option<String>, Option<String>) {
+    let mut proto = None;
+    let mut host = None;
+    let element = value.split(',').next().unwrap_or("");
+    for pair in element.split(';') {
+        let mut parts = pair.splitn(2, '=');
+        let key = parts.next().unwrap_or("").trim();
+        let value = parts.next().unwrap_or("").trim().trim_matches('"');
+        if value.is_empty() {
+            continue;
+        }
+        if key.eq_ignore_ascii_case("proto") {
+            proto = Some(value.to_string());
+        } else if key.eq_ignore_ascii_case("host") {
+            host = Some(value.to_string());
+        }
+    }
+    (proto, host)
+}
+
+/// Strip the port from a host, including bracketed IPv6 literals.
+pub(crate) fn strip_port(host: &str) -> &str {
+    if host.starts_with('[') {
+        return match host.find(']') {
+            Some(end) => &host[1..end],
+            None => host,
+        };
+    }
+    match host.rfind(':') {
+        // A bare IPv6 literal has more than one colon and no port.
+        Some(idx) if host[..idx].find(':').is_none() => &host[..idx],
+        _ => host,
+    }
+}
+
+fn is_local_host(host: &str) -> bool {
+    let host = strip_port(host).trim_end_matches('.').to_ascii_lowercase();
+    if host == "localhost" || host.ends_with(".localhost") {
+        return true;
+    }
+    match host.parse::<IpAddr>() {
+        Ok(addr) => addr.is_loopback() || addr.is_unspecified(),
+        Err(_) => false,
+    }
+}
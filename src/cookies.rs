@@ -0,0 +1,306 @@
+//! Harden outgoing `Set-Cookie` headers.
+//!
+//! Cookies are often sent without the attributes that protect them. A [`CookiePolicy`] adds
+//! `Secure`, `HttpOnly` and a default `SameSite` attribute to every `Set-Cookie` header that
+//! lacks them, and reports cookies that browsers would reject or that break the rules of the
+//! `__Host-` and `__Secure-` name prefixes.
+//!
+//! [MDN | Set-Cookie](https://developer.mozilla.org/en-US/docs/Web/HTTP/Headers/Set-Cookie)
+//!
+//! # Examples
+//!
+//! ```
+//! let mut headers = http::HeaderMap::new();
+//! headers.append("Set-Cookie", "session=abc; Path=/".parse().unwrap());
+//! headers.append("Set-Cookie", "theme=dark; SameSite=Strict".parse().unwrap());
+//!
+//! let mut policy = armor::cookies::new();
+//! policy.exempt("theme");
+//! let violations = policy.apply(&mut headers);
+//! assert!(violations.is_empty());
+//!
+//! let cookies: Vec<_> = headers.get_all("Set-Cookie").iter().collect();
+//! assert_eq!(cookies[0], "session=abc; Path=/; Secure; HttpOnly; SameSite=Lax");
+//! assert_eq!(cookies[1], "theme=dark; SameSite=Strict");
+//! ```
+
+use crate::RequestContext;
+use http::header::{HeaderMap, HeaderValue, SET_COOKIE};
+use std::fmt;
+
+/// Set the `SameSite` attribute
+///
+/// [MDN | SameSite](https://developer.mozilla.org/en-US/docs/Web/HTTP/Headers/Set-Cookie#samesitesamesite-value)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SameSite {
+    /// Set to "Strict"
+    Strict,
+    /// Set to "Lax"
+    Lax,
+    /// Set to "None", which requires `Secure`
+    None,
+}
+
+impl AsRef<str> for SameSite {
+    fn as_ref(&self) -> &str {
+        match *self {
+            SameSite::Strict => "Strict",
+            SameSite::Lax => "Lax",
+            SameSite::None => "None",
+        }
+    }
+}
+
+/// A problem with a cookie that can't be fixed by adding attributes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CookieViolation {
+    /// A `__Secure-` or `__Host-` cookie without the `Secure` attribute
+    PrefixRequiresSecure(String),
+    /// A `__Host-` cookie with a `Domain` attribute
+    HostPrefixWithDomain(String),
+    /// A `__Host-` cookie without `Path=/`
+    HostPrefixWithoutRootPath(String),
+    /// A `SameSite=None` cookie without the `Secure` attribute
+    SameSiteNoneWithoutSecure(String),
+    /// A `Set-Cookie` header that couldn't be parsed
+    Invalid(String),
+}
+
+impl CookieViolation {
+    /// The name of the cookie, or the raw header value if it couldn't be parsed.
+    pub fn cookie(&self) -> &str {
+        match *self {
+            CookieViolation::PrefixRequiresSecure(ref name)
+            | CookieViolation::HostPrefixWithDomain(ref name)
+            | CookieViolation::HostPrefixWithoutRootPath(ref name)
+            | CookieViolation::SameSiteNoneWithoutSecure(ref name)
+            | CookieViolation::Invalid(ref name) => name,
+        }
+    }
+}
+
+impl fmt::Display for CookieViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            CookieViolation::PrefixRequiresSecure(ref name) => {
+                write!(f, "cookie `{}` has a prefix that requires `Secure`", name)
+            }
+            CookieViolation::HostPrefixWithDomain(ref name) => {
+                write!(f, "`__Host-` cookie `{}` must not set `Domain`", name)
+            }
+            CookieViolation::HostPrefixWithoutRootPath(ref name) => {
+                write!(f, "`__Host-` cookie `{}` must set `Path=/`", name)
+            }
+            CookieViolation::SameSiteNoneWithoutSecure(ref name) => {
+                write!(f, "cookie `{}` sets `SameSite=None` without `Secure`", name)
+            }
+            CookieViolation::Invalid(ref value) => {
+                write!(f, "invalid `Set-Cookie` header `{}`", value)
+            }
+        }
+    }
+}
+
+/// Build the `Set-Cookie` hardening policy
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CookiePolicy {
+    secure: bool,
+    http_only: bool,
+    same_site: Option<SameSite>,
+    exempt: Vec<String>,
+}
+
+impl Default for CookiePolicy {
+    /// Enforces `Secure`, `HttpOnly` and `SameSite=Lax`.
+    fn default() -> Self {
+        Self {
+            secure: true,
+            http_only: true,
+            same_site: Some(SameSite::Lax),
+            exempt: Vec::new(),
+        }
+    }
+}
+
+impl CookiePolicy {
+    /// Create a new instance.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set whether to add `Secure` to cookies.
+    pub fn secure(&mut self, enforce: bool) -> &mut Self {
+        self.secure = enforce;
+        self
+    }
+
+    /// Set whether to add `HttpOnly` to cookies.
+    pub fn http_only(&mut self, enforce: bool) -> &mut Self {
+        self.http_only = enforce;
+        self
+    }
+
+    /// Set the `SameSite` value added to cookies that don't specify one.
+    pub fn same_site(&mut self, same_site: Option<SameSite>) -> &mut Self {
+        self.same_site = same_site;
+        self
+    }
+
+    /// Leave a cookie's attributes untouched, e.g. a token that must be readable from JavaScript.
+    ///
+    /// Exempt cookies are still checked for violations.
+    pub fn exempt<T: AsRef<str>>(&mut self, name: T) -> &mut Self {
+        self.exempt.push(name.as_ref().to_string());
+        self
+    }
+
+    /// Harden a single `Set-Cookie` value, returning the new value and any violations.
+    pub fn harden(&self, set_cookie: &str) -> (String, Vec<CookieViolation>) {
+        let mut cookie = match Cookie::parse(set_cookie) {
+            Some(cookie) => cookie,
+            None => {
+                let violation = CookieViolation::Invalid(set_cookie.to_string());
+                return (set_cookie.to_string(), vec![violation]);
+            }
+        };
+
+        let mut value = set_cookie.trim_end().trim_end_matches(';').to_string();
+        if !self.exempt.contains(&cookie.name) {
+            if self.secure && !cookie.secure {
+                value.push_str("; Secure");
+                cookie.secure = true;
+            }
+            if self.http_only && !cookie.http_only {
+                value.push_str("; HttpOnly");
+            }
+            if let (Some(same_site), None) = (self.same_site, &cookie.same_site) {
+                value.push_str("; SameSite=");
+                value.push_str(same_site.as_ref());
+            }
+        }
+        (value, cookie.violations())
+    }
+
+    /// Rewrite every `Set-Cookie` header, returning the violations that remain.
+    ///
+    /// Headers that aren't valid UTF-8 are left untouched and reported as invalid.
+    pub fn apply(&self, headers: &mut HeaderMap) -> Vec<CookieViolation> {
+        let values: Vec<HeaderValue> = headers.get_all(SET_COOKIE).iter().cloned().collect();
+        headers.remove(SET_COOKIE);
+
+        let mut violations = Vec::new();
+        for value in values {
+            let set_cookie = match value.to_str() {
+                Ok(set_cookie) => set_cookie,
+                Err(_) => {
+                    let raw = String::from_utf8_lossy(value.as_bytes()).into_owned();
+                    violations.push(CookieViolation::Invalid(raw));
+                    headers.append(SET_COOKIE, value);
+                    continue;
+                }
+            };
+            let (hardened, mut found) = self.harden(set_cookie);
+            violations.append(&mut found);
+            headers.append(SET_COOKIE, hardened.parse().unwrap_or(value));
+        }
+        violations
+    }
+
+    /// Rewrite every `Set-Cookie` header for the given request, returning the violations that
+    /// remain.
+    ///
+    /// Same as [`CookiePolicy::apply`], but `Secure` is only added for HTTPS requests to
+    /// non-local hosts, since browsers drop `Secure` cookies sent over plain HTTP. See
+    /// [`RequestContext::is_secure`].
+    ///
+    /// ## Examples
+    /// ```
+    /// let mut ctx = armor::RequestContext::new();
+    /// ctx.scheme("http").host("localhost:8080");
+    ///
+    /// let mut headers = http::HeaderMap::new();
+    /// headers.append("Set-Cookie", "session=abc".parse().unwrap());
+    /// armor::cookies::new().apply_for(&mut headers, &ctx);
+    /// assert_eq!(headers["Set-Cookie"], "session=abc; HttpOnly; SameSite=Lax");
+    /// ```
+    pub fn apply_for(&self, headers: &mut HeaderMap, ctx: &RequestContext) -> Vec<CookieViolation> {
+        if self.secure && !ctx.is_secure() {
+            self.clone().secure(false).apply(headers)
+        } else {
+            self.apply(headers)
+        }
+    }
+}
+
+/// Create a new instance.
+pub fn new() -> CookiePolicy {
+    CookiePolicy::new()
+}
+
+/// The attributes of a `Set-Cookie` header relevant to hardening.
+#[derive(Debug)]
+pub(crate) struct Cookie {
+    pub(crate) name: String,
+    pub(crate) secure: bool,
+    pub(crate) http_only: bool,
+    pub(crate) same_site: Option<String>,
+    pub(crate) domain: Option<String>,
+    pub(crate) path: Option<String>,
+}
+
+impl Cookie {
+    pub(crate) fn parse(set_cookie: &str) -> Option<Self> {
+        let mut parts = set_cookie.split(';');
+        let pair = parts.next()?;
+        let name = pair.split_once('=')?.0.trim();
+        if name.is_empty() {
+            return None;
+        }
+
+        let mut cookie = Self {
+            name: name.to_string(),
+            secure: false,
+            http_only: false,
+            same_site: None,
+            domain: None,
+            path: None,
+        };
+        for attr in parts {
+            let mut attr = attr.splitn(2, '=');
+            let key = attr.next().unwrap_or("").trim().to_ascii_lowercase();
+            let value = attr.next().map(|value| value.trim().to_string());
+            match key.as_str() {
+                "secure" => cookie.secure = true,
+                "httponly" => cookie.http_only = true,
+                "samesite" => cookie.same_site = value,
+                "domain" => cookie.domain = value,
+                "path" => cookie.path = value,
+                _ => {}
+            }
+        }
+        Some(cookie)
+    }
+
+    pub(crate) fn violations(&self) -> Vec<CookieViolation> {
+        let mut violations = Vec::new();
+        let name = || self.name.clone();
+        let host = self.name.starts_with("__Host-");
+        if (host || self.name.starts_with("__Secure-")) && !self.secure {
+            violations.push(CookieViolation::PrefixRequiresSecure(name()));
+        }
+        if host && self.domain.is_some() {
+            violations.push(CookieViolation::HostPrefixWithDomain(name()));
+        }
+        if host && self.path.as_deref() != Some("/") {
+            violations.push(CookieViolation::HostPrefixWithoutRootPath(name()));
+        }
+        let same_site_none = self
+            .same_site
+            .as_deref()
+            .is_some_and(|same_site| same_site.eq_ignore_ascii_case("none"));
+        if same_site_none && !self.secure {
+            violations.push(CookieViolation::SameSiteNoneWithoutSecure(name()));
+        }
+        violations
+    }
+}
@@ -0,0 +1,463 @@
+//! Apply Cross-Origin Resource Sharing (CORS) headers.
+//!
+//! A [`Cors`] policy decides which origins may read responses, and with which methods, headers
+//! and credentials. [`Cors::apply`] handles actual requests and [`Cors::preflight`] answers
+//! `OPTIONS` preflight requests. Both add the `Vary` header needed to keep caches from serving
+//! a response meant for one origin to another.
+//!
+//! [MDN | CORS](https://developer.mozilla.org/en-US/docs/Web/HTTP/CORS)
+//!
+//! # Examples
+//!
+//! ```
+//! use http::{HeaderMap, Method};
+//!
+//! let mut policy = armor::cors::new();
+//! policy
+//!     .allow_origin("https://example.com")
+//!     .allow_origin_suffix("https://*.example.com")
+//!     .unwrap()
+//!     .allow_method(Method::PUT)
+//!     .allow_header("Content-Type")
+//!     .unwrap()
+//!     .allow_credentials(true)
+//!     .max_age(600);
+//!
+//! let mut request = HeaderMap::new();
+//! request.insert("Origin", "https://api.example.com".parse().unwrap());
+//! request.insert("Access-Control-Request-Method", "PUT".parse().unwrap());
+//! request.insert("Access-Control-Request-Headers", "content-type".parse().unwrap());
+//!
+//! let mut response = HeaderMap::new();
+//! policy.preflight(&Method::OPTIONS, &request, &mut response).unwrap();
+//!
+//! assert_eq!(response["Access-Control-Allow-Origin"], "https://api.example.com");
+//! assert_eq!(response["Access-Control-Allow-Methods"], "GET, HEAD, POST, PUT");
+//! assert_eq!(response["Access-Control-Allow-Headers"], "content-type");
+//! assert_eq!(response["Access-Control-Allow-Credentials"], "true");
+//! assert_eq!(response["Access-Control-Max-Age"], "600");
+//! ```
+
+use crate::url::Url;
+use crate::ParseError;
+use http::header::{HeaderMap, HeaderName, HeaderValue, VARY};
+use http::Method;
+use regex::Regex;
+use std::error::Error;
+use std::fmt;
+use std::sync::Arc;
+
+/// Methods browsers may use cross-origin without a preflight.
+const SAFELISTED_METHODS: &[Method] = &[Method::GET, Method::HEAD, Method::POST];
+
+#[derive(Clone)]
+enum AllowOrigin {
+    Any,
+    Exact(String),
+    Suffix {
+        scheme: String,
+        domain: String,
+        port: Option<u16>,
+    },
+    Regex(Regex),
+    Predicate(Arc<dyn Fn(&str) -> bool + Send + Sync>),
+}
+
+impl AllowOrigin {
+    fn matches(&self, origin: &str) -> bool {
+        match *self {
+            AllowOrigin::Any => true,
+            AllowOrigin::Exact(ref allowed) => allowed.eq_ignore_ascii_case(origin),
+            AllowOrigin::Suffix {
+                ref scheme,
+                ref domain,
+                port,
+            } => Url::parse(origin).is_some_and(|origin| {
+                origin.scheme == *scheme
+                    && origin.path == "/"
+                    && origin.effective_port() == port
+                    && origin
+                        .host
+                        .is_some_and(|host| host.ends_with(&format!(".{}", domain)))
+            }),
+            AllowOrigin::Regex(ref regex) => regex.is_match(origin),
+            AllowOrigin::Predicate(ref predicate) => predicate(origin),
+        }
+    }
+}
+
+impl fmt::Debug for AllowOrigin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            AllowOrigin::Any => f.write_str("Any"),
+            AllowOrigin::Exact(ref origin) => f.debug_tuple("Exact").field(origin).finish(),
+            AllowOrigin::Suffix {
+                ref scheme,
+                ref domain,
+                port,
+            } => f
+                .debug_struct("Suffix")
+                .field("scheme", scheme)
+                .field("domain", domain)
+                .field("port", &port)
+                .finish(),
+            AllowOrigin::Regex(ref regex) => f.debug_tuple("Regex").field(regex).finish(),
+            AllowOrigin::Predicate(_) => f.write_str("Predicate(..)"),
+        }
+    }
+}
+
+/// A request was rejected by a [`Cors`] policy.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CorsError {
+    /// The policy allows any origin and credentials, which browsers refuse.
+    WildcardWithCredentials,
+    /// The `Origin` isn't allowed.
+    OriginNotAllowed(String),
+    /// The requested method isn't allowed.
+    MethodNotAllowed(String),
+    /// A requested header isn't allowed.
+    HeaderNotAllowed(String),
+    /// The request isn't a preflight request.
+    NotPreflight,
+}
+
+impl fmt::Display for CorsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            CorsError::WildcardWithCredentials => {
+                f.write_str("credentials can't be allowed for any origin")
+            }
+            CorsError::OriginNotAllowed(ref origin) => {
+                write!(f, "origin `{}` is not allowed", origin)
+            }
+            CorsError::MethodNotAllowed(ref method) => {
+                write!(f, "method `{}` is not allowed", method)
+            }
+            CorsError::HeaderNotAllowed(ref header) => {
+                write!(f, "header `{}` is not allowed", header)
+            }
+            CorsError::NotPreflight => f.write_str("not a CORS preflight request"),
+        }
+    }
+}
+
+impl Error for CorsError {}
+
+/// Build the CORS policy
+#[derive(Debug, Clone, Default)]
+pub struct Cors {
+    origins: Vec<AllowOrigin>,
+    methods: Vec<Method>,
+    headers: Vec<String>,
+    any_header: bool,
+    credentials: bool,
+    max_age: Option<u64>,
+    expose_headers: Vec<String>,
+}
+
+impl Cors {
+    /// Create a new instance that doesn't allow any origin.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allow requests from any origin, answering with `Access-Control-Allow-Origin: *`.
+    ///
+    /// Can't be combined with [`Cors::allow_credentials`].
+    pub fn allow_any_origin(&mut self) -> &mut Self {
+        self.origins.push(AllowOrigin::Any);
+        self
+    }
+
+    /// Allow an exact origin, e.g. `https://example.com`.
+    pub fn allow_origin<T: AsRef<str>>(&mut self, origin: T) -> &mut Self {
+        let origin = origin.as_ref().trim_end_matches('/').to_string();
+        self.origins.push(AllowOrigin::Exact(origin));
+        self
+    }
+
+    /// Allow every subdomain of a domain, e.g. `https://*.example.com`.
+    ///
+    /// The scheme and port must match too, so `http://api.example.com` isn't allowed by
+    /// `https://*.example.com`. The domain itself isn't allowed either.
+    pub fn allow_origin_suffix<T: AsRef<str>>(
+        &mut self,
+        pattern: T,
+    ) -> Result<&mut Self, ParseError> {
+        let pattern = pattern.as_ref().trim_end_matches('/');
+        let invalid = || {
+            ParseError::new(format!(
+                "invalid origin pattern `{}`",
+                pattern.escape_debug()
+            ))
+        };
+        if !pattern.contains("://*.") {
+            return Err(invalid());
+        }
+        let url = Url::parse(pattern)
+            .filter(|url| url.path == "/")
+            .ok_or_else(invalid)?;
+        let domain = url
+            .host
+            .as_deref()
+            .and_then(|host| host.strip_prefix("*."))
+            .filter(|domain| {
+                domain.split('.').all(|label| {
+                    !label.is_empty()
+                        && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+                })
+            })
+            .ok_or_else(invalid)?;
+        self.origins.push(AllowOrigin::Suffix {
+            domain: domain.to_string(),
+            port: url.effective_port(),
+            scheme: url.scheme,
+        });
+        Ok(self)
+    }
+
+    /// Allow every origin matching a regular expression.
+    ///
+    /// The expression must match the whole origin.
+    pub fn allow_origin_regex<T: AsRef<str>>(
+        &mut self,
+        pattern: T,
+    ) -> Result<&mut Self, ParseError> {
+        let anchored = format!("^(?:{})$", pattern.as_ref());
+        let regex = Regex::new(&anchored).map_err(|err| ParseError::new(err.to_string()))?;
+        self.origins.push(AllowOrigin::Regex(regex));
+        Ok(self)
+    }
+
+    /// Allow every origin for which a predicate returns `true`.
+    pub fn allow_origin_fn<F>(&mut self, predicate: F) -> &mut Self
+    where
+        F: Fn(&str) -> bool + Send + Sync + 'static,
+    {
+        self.origins
+            .push(AllowOrigin::Predicate(Arc::new(predicate)));
+        self
+    }
+
+    /// Allow a method in addition to the safelisted `GET`, `HEAD` and `POST`.
+    pub fn allow_method(&mut self, method: Method) -> &mut Self {
+        if !self.methods.contains(&method) {
+            self.methods.push(method);
+        }
+        self
+    }
+
+    /// Allow a request header.
+    ///
+    /// Browsers only list headers in a preflight when they aren't safelisted, e.g.
+    /// `Content-Type: application/json`, so every requested header must be allowed here.
+    pub fn allow_header<T: AsRef<str>>(&mut self, header: T) -> Result<&mut Self, ParseError> {
+        let name = header_name(header.as_ref())?;
+        self.headers.push(name.as_str().to_string());
+        Ok(self)
+    }
+
+    /// Allow any request header by echoing the requested headers.
+    pub fn allow_any_header(&mut self) -> &mut Self {
+        self.any_header = true;
+        self
+    }
+
+    /// Set whether to allow credentials such as cookies.
+    pub fn allow_credentials(&mut self, allow: bool) -> &mut Self {
+        self.credentials = allow;
+        self
+    }
+
+    /// Set how many seconds browsers may cache a preflight response.
+    pub fn max_age(&mut self, seconds: u64) -> &mut Self {
+        self.max_age = Some(seconds);
+        self
+    }
+
+    /// Expose a response header to scripts.
+    pub fn expose_header<T: AsRef<str>>(&mut self, header: T) -> Result<&mut Self, ParseError> {
+        let header = header.as_ref();
+        header_name(header)?;
+        self.expose_headers.push(header.to_string());
+        Ok(self)
+    }
+
+    /// Check that the policy can be sent to browsers.
+    pub fn validate(&self) -> Result<(), CorsError> {
+        let any = self
+            .origins
+            .iter()
+            .any(|origin| matches!(origin, AllowOrigin::Any));
+        if any && self.credentials {
+            Err(CorsError::WildcardWithCredentials)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Check whether an origin is allowed.
+    pub fn is_allowed(&self, origin: &str) -> bool {
+        self.origins.iter().any(|allowed| allowed.matches(origin))
+    }
+
+    /// Check whether a request is a CORS preflight request.
+    pub fn is_preflight(method: &Method, request: &HeaderMap) -> bool {
+        method == Method::OPTIONS
+            && request.contains_key("Origin")
+            && request.contains_key("Access-Control-Request-Method")
+    }
+
+    /// Sets the CORS headers for an actual (non-preflight) request.
+    ///
+    /// Requests without an `Origin` header aren't cross-origin and only get `Vary: Origin`.
+    pub fn apply(&self, request: &HeaderMap, response: &mut HeaderMap) -> Result<(), CorsError> {
+        self.validate()?;
+        let origin = self.set_allow_origin(request, response)?;
+        if origin.is_some() && !self.expose_headers.is_empty() {
+            let exposed = self.expose_headers.join(", ");
+            response.insert("Access-Control-Expose-Headers", exposed.parse().unwrap());
+        }
+        Ok(())
+    }
+
+    /// Sets the CORS headers for a preflight request.
+    ///
+    /// Inspects `Origin`, `Access-Control-Request-Method` and `Access-Control-Request-Headers`.
+    /// On error no `Access-Control-*` headers are set, and the caller should answer without
+    /// them, which makes the browser fail the request.
+    pub fn preflight(
+        &self,
+        method: &Method,
+        request: &HeaderMap,
+        response: &mut HeaderMap,
+    ) -> Result<(), CorsError> {
+        self.validate()?;
+        if !Self::is_preflight(method, request) {
+            return Err(CorsError::NotPreflight);
+        }
+        vary(response, "Origin");
+        vary(response, "Access-Control-Request-Method");
+        vary(response, "Access-Control-Request-Headers");
+
+        let origin = header_str(request, "Origin");
+        if !self.is_allowed(origin) {
+            return Err(CorsError::OriginNotAllowed(origin.to_string()));
+        }
+        let requested = header_str(request, "Access-Control-Request-Method");
+        let allowed_method = requested
+            .parse::<Method>()
+            .is_ok_and(|requested| self.allowed_methods().any(|method| *method == requested));
+        if !allowed_method {
+            return Err(CorsError::MethodNotAllowed(requested.to_string()));
+        }
+
+        let requested_headers: Vec<String> = header_str(request, "Access-Control-Request-Headers")
+            .split(',')
+            .map(|header| header.trim().to_ascii_lowercase())
+            .filter(|header| !header.is_empty())
+            .collect();
+        for header in &requested_headers {
+            let allowed = self.any_header || self.headers.contains(header);
+            if !allowed {
+                return Err(CorsError::HeaderNotAllowed(header.clone()));
+            }
+        }
+
+        self.set_allow_origin(request, response)?;
+        let methods: Vec<&str> = self.allowed_methods().map(Method::as_str).collect();
+        response.insert(
+            "Access-Control-Allow-Methods",
+            methods.join(", ").parse().unwrap(),
+        );
+        if !requested_headers.is_empty() {
+            response.insert(
+                "Access-Control-Allow-Headers",
+                requested_headers.join(", ").parse().unwrap(),
+            );
+        }
+        if let Some(max_age) = self.max_age {
+            response.insert("Access-Control-Max-Age", max_age.into());
+        }
+        Ok(())
+    }
+
+    fn allowed_methods(&self) -> impl Iterator<Item = &Method> {
+        SAFELISTED_METHODS.iter().chain(
+            self.methods
+                .iter()
+                .filter(|method| !SAFELISTED_METHODS.contains(method)),
+        )
+    }
+
+    /// Sets `Access-Control-Allow-Origin`, `Access-Control-Allow-Credentials` and `Vary`,
+    /// returning the allowed origin.
+    fn set_allow_origin(
+        &self,
+        request: &HeaderMap,
+        response: &mut HeaderMap,
+    ) -> Result<Option<String>, CorsError> {
+        let wildcard = !self.credentials
+            && self
+                .origins
+                .iter()
+                .all(|origin| matches!(origin, AllowOrigin::Any))
+            && !self.origins.is_empty();
+        if !wildcard {
+            vary(response, "Origin");
+        }
+        let origin = match request.get("Origin") {
+            Some(origin) => origin.to_str().unwrap_or("").to_string(),
+            None => return Ok(None),
+        };
+        if !self.is_allowed(&origin) {
+            return Err(CorsError::OriginNotAllowed(origin));
+        }
+
+        if wildcard {
+            response.insert("Access-Control-Allow-Origin", HeaderValue::from_static("*"));
+        } else {
+            let value = HeaderValue::from_str(&origin)
+                .map_err(|_| CorsError::OriginNotAllowed(origin.clone()))?;
+            response.insert("Access-Control-Allow-Origin", value);
+        }
+        if self.credentials {
+            response.insert(
+                "Access-Control-Allow-Credentials",
+                HeaderValue::from_static("true"),
+            );
+        }
+        Ok(Some(origin))
+    }
+}
+
+/// Create a new instance.
+pub fn new() -> Cors {
+    Cors::new()
+}
+
+fn header_name(header: &str) -> Result<HeaderName, ParseError> {
+    HeaderName::from_bytes(header.as_bytes())
+        .map_err(|_| ParseError::new(format!("invalid header name `{}`", header.escape_debug())))
+}
+
+fn header_str<'a>(headers: &'a HeaderMap, name: &str) -> &'a str {
+    headers
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or("")
+}
+
+/// Add a header name to `Vary`, unless it's already listed.
+fn vary(headers: &mut HeaderMap, name: &str) {
+    let listed = headers.get_all(VARY).iter().any(|value| {
+        value.to_str().unwrap_or("").split(',').any(|listed| {
+            let listed = listed.trim();
+            listed == "*" || listed.eq_ignore_ascii_case(name)
+        })
+    });
+    if !listed {
+        headers.append(VARY, name.parse().unwrap());
+    }
+}
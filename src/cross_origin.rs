@@ -0,0 +1,395 @@
+//! Apply cross-origin isolation headers.
+//!
+//! `Cross-Origin-Opener-Policy` (COOP) isolates a document from cross-origin windows,
+//! `Cross-Origin-Embedder-Policy` (COEP) restricts which cross-origin resources a document
+//! can load, and `Cross-Origin-Resource-Policy` (CORP) restricts who can load a resource.
+//! A document with both `Cross-Origin-Opener-Policy: same-origin` and
+//! `Cross-Origin-Embedder-Policy: require-corp` is cross-origin isolated, which enables
+//! `SharedArrayBuffer` and high-resolution timers.
+//!
+//! [read more](https://web.dev/articles/coop-coep)
+//!
+//! # Examples
+//!
+//! ```
+//! use armor::cross_origin::{self, EmbedderPolicy, OpenerPolicy};
+//!
+//! let mut headers = http::HeaderMap::new();
+//! cross_origin::opener_policy(OpenerPolicy::SameOrigin)
+//!     .report_to("coop")
+//!     .unwrap()
+//!     .apply(&mut headers);
+//! cross_origin::embedder_policy(EmbedderPolicy::Credentialless)
+//!     .report_only()
+//!     .apply(&mut headers);
+//!
+//! assert_eq!(headers["cross-origin-opener-policy"], r#"same-origin;report-to="coop""#);
+//! assert_eq!(headers["cross-origin-embedder-policy-report-only"], "credentialless");
+//! ```
+
+use crate::sfv::{self, BareItem, Item};
+use crate::ParseError;
+use http::HeaderMap;
+use std::fmt;
+use std::str::FromStr;
+
+/// Set the `Cross-Origin-Opener-Policy` value
+///
+/// [MDN | Cross-Origin-Opener-Policy](https://developer.mozilla.org/en-US/docs/Web/HTTP/Headers/Cross-Origin-Opener-Policy)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OpenerPolicy {
+    /// Set to "unsafe-none" the default
+    UnsafeNone,
+    /// Set to "same-origin-allow-popups"
+    SameOriginAllowPopups,
+    /// Set to "same-origin"
+    SameOrigin,
+    /// Set to "noopener-allow-popups"
+    NoopenerAllowPopups,
+}
+
+impl AsRef<str> for OpenerPolicy {
+    fn as_ref(&self) -> &str {
+        match *self {
+            OpenerPolicy::UnsafeNone => "unsafe-none",
+            OpenerPolicy::SameOriginAllowPopups => "same-origin-allow-popups",
+            OpenerPolicy::SameOrigin => "same-origin",
+            OpenerPolicy::NoopenerAllowPopups => "noopener-allow-popups",
+        }
+    }
+}
+
+impl FromStr for OpenerPolicy {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "unsafe-none" => Ok(OpenerPolicy::UnsafeNone),
+            "same-origin-allow-popups" => Ok(OpenerPolicy::SameOriginAllowPopups),
+            "same-origin" => Ok(OpenerPolicy::SameOrigin),
+            "noopener-allow-popups" => Ok(OpenerPolicy::NoopenerAllowPopups),
+            other => Err(ParseError::new(format!(
+                "unknown opener policy `{}`",
+                other
+            ))),
+        }
+    }
+}
+
+/// Set the `Cross-Origin-Embedder-Policy` value
+///
+/// [MDN | Cross-Origin-Embedder-Policy](https://developer.mozilla.org/en-US/docs/Web/HTTP/Headers/Cross-Origin-Embedder-Policy)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EmbedderPolicy {
+    /// Set to "unsafe-none" the default
+    UnsafeNone,
+    /// Set to "require-corp"
+    RequireCorp,
+    /// Set to "credentialless"
+    Credentialless,
+}
+
+impl AsRef<str> for EmbedderPolicy {
+    fn as_ref(&self) -> &str {
+        match *self {
+            EmbedderPolicy::UnsafeNone => "unsafe-none",
+            EmbedderPolicy::RequireCorp => "require-corp",
+            EmbedderPolicy::Credentialless => "credentialless",
+        }
+    }
+}
+
+impl FromStr for EmbedderPolicy {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "unsafe-none" => Ok(EmbedderPolicy::UnsafeNone),
+            "require-corp" => Ok(EmbedderPolicy::RequireCorp),
+            "credentialless" => Ok(EmbedderPolicy::Credentialless),
+            other => Err(ParseError::new(format!(
+                "unknown embedder policy `{}`",
+                other
+            ))),
+        }
+    }
+}
+
+/// Set the `Cross-Origin-Resource-Policy` value
+///
+/// [MDN | Cross-Origin-Resource-Policy](https://developer.mozilla.org/en-US/docs/Web/HTTP/Headers/Cross-Origin-Resource-Policy)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResourcePolicy {
+    /// Set to "same-site"
+    SameSite,
+    /// Set to "same-origin"
+    SameOrigin,
+    /// Set to "cross-origin"
+    CrossOrigin,
+}
+
+impl AsRef<str> for ResourcePolicy {
+    fn as_ref(&self) -> &str {
+        match *self {
+            ResourcePolicy::SameSite => "same-site",
+            ResourcePolicy::SameOrigin => "same-origin",
+            ResourcePolicy::CrossOrigin => "cross-origin",
+        }
+    }
+}
+
+impl FromStr for ResourcePolicy {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "same-site" => Ok(ResourcePolicy::SameSite),
+            "same-origin" => Ok(ResourcePolicy::SameOrigin),
+            "cross-origin" => Ok(ResourcePolicy::CrossOrigin),
+            other => Err(ParseError::new(format!(
+                "unknown resource policy `{}`",
+                other
+            ))),
+        }
+    }
+}
+
+/// A policy value with an optional `report-to` parameter, shared by COOP and COEP.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Reporting<P> {
+    policy: P,
+    report_to: Option<String>,
+    report_only_flag: bool,
+}
+
+impl<P: AsRef<str> + FromStr<Err = ParseError>> Reporting<P> {
+    fn new(policy: P) -> Self {
+        Self {
+            policy,
+            report_to: None,
+            report_only_flag: false,
+        }
+    }
+
+    fn parse(value: &str) -> Result<Self, ParseError> {
+        let item = sfv::parse_item(value)?;
+        let policy = match item.bare_item.as_token() {
+            Some(token) => token.parse()?,
+            None => return Err(ParseError::new("expected a token")),
+        };
+        let report_to = sfv::get(&item.params, "report-to")
+            .and_then(BareItem::as_string)
+            .map(String::from);
+        Ok(Self {
+            policy,
+            report_to,
+            report_only_flag: false,
+        })
+    }
+
+    fn report_to(&mut self, group: &str) -> Result<(), ParseError> {
+        // The group is serialized as a Structured Fields string: printable ASCII only.
+        if group.is_empty() || !group.bytes().all(|b| (0x20..=0x7e).contains(&b)) {
+            return Err(ParseError::new(format!(
+                "invalid report-to group `{}`",
+                group.escape_debug()
+            )));
+        }
+        self.report_to = Some(group.to_string());
+        Ok(())
+    }
+
+    fn value(&self) -> String {
+        let mut item = Item::new(BareItem::Token(self.policy.as_ref().to_string()));
+        if let Some(ref group) = self.report_to {
+            item = item.param("report-to", BareItem::String(group.clone()));
+        }
+        sfv::serialize_item(&item).expect("policies are tokens and groups are validated")
+    }
+
+    fn apply(&self, headers: &mut HeaderMap, name: &'static str, report_only: &'static str) {
+        let val = self.value().parse().unwrap();
+        if !self.report_only_flag {
+            headers.insert(name, val);
+        } else {
+            headers.insert(report_only, val);
+        }
+    }
+}
+
+/// Build the Cross-Origin-Opener-Policy
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CrossOriginOpenerPolicy {
+    inner: Reporting<OpenerPolicy>,
+}
+
+impl CrossOriginOpenerPolicy {
+    /// Create a new instance.
+    pub fn new(policy: OpenerPolicy) -> Self {
+        Self {
+            inner: Reporting::new(policy),
+        }
+    }
+
+    /// Parse a `Cross-Origin-Opener-Policy` header value.
+    pub fn parse(value: &str) -> Result<Self, ParseError> {
+        Reporting::parse(value).map(|inner| Self { inner })
+    }
+
+    /// The policy value.
+    pub fn policy(&self) -> OpenerPolicy {
+        self.inner.policy
+    }
+
+    /// Send violation reports to the named `Reporting-Endpoints` group.
+    ///
+    /// Fails if the group name contains characters other than printable ASCII.
+    pub fn report_to<T: AsRef<str>>(&mut self, group: T) -> Result<&mut Self, ParseError> {
+        self.inner.report_to(group.as_ref())?;
+        Ok(self)
+    }
+
+    /// Change the header to `Cross-Origin-Opener-Policy-Report-Only`
+    pub fn report_only(&mut self) -> &mut Self {
+        self.inner.report_only_flag = true;
+        self
+    }
+
+    /// Create and retrieve the policy value
+    pub fn value(&self) -> String {
+        self.inner.value()
+    }
+
+    /// Sets the `Cross-Origin-Opener-Policy` HTTP header.
+    pub fn apply(&self, headers: &mut HeaderMap) {
+        self.inner.apply(
+            headers,
+            "Cross-Origin-Opener-Policy",
+            "Cross-Origin-Opener-Policy-Report-Only",
+        );
+    }
+}
+
+impl fmt::Display for CrossOriginOpenerPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.value())
+    }
+}
+
+/// Build the Cross-Origin-Embedder-Policy
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CrossOriginEmbedderPolicy {
+    inner: Reporting<EmbedderPolicy>,
+}
+
+impl CrossOriginEmbedderPolicy {
+    /// Create a new instance.
+    pub fn new(policy: EmbedderPolicy) -> Self {
+        Self {
+            inner: Reporting::new(policy),
+        }
+    }
+
+    /// Parse a `Cross-Origin-Embedder-Policy` header value.
+    pub fn parse(value: &str) -> Result<Self, ParseError> {
+        Reporting::parse(value).map(|inner| Self { inner })
+    }
+
+    /// The policy value.
+    pub fn policy(&self) -> EmbedderPolicy {
+        self.inner.policy
+    }
+
+    /// Send violation reports to the named `Reporting-Endpoints` group.
+    ///
+    /// Fails if the group name contains characters other than printable ASCII.
+    pub fn report_to<T: AsRef<str>>(&mut self, group: T) -> Result<&mut Self, ParseError> {
+        self.inner.report_to(group.as_ref())?;
+        Ok(self)
+    }
+
+    /// Change the header to `Cross-Origin-Embedder-Policy-Report-Only`
+    pub fn report_only(&mut self) -> &mut Self {
+        self.inner.report_only_flag = true;
+        self
+    }
+
+    /// Create and retrieve the policy value
+    pub fn value(&self) -> String {
+        self.inner.value()
+    }
+
+    /// Sets the `Cross-Origin-Embedder-Policy` HTTP header.
+    pub fn apply(&self, headers: &mut HeaderMap) {
+        self.inner.apply(
+            headers,
+            "Cross-Origin-Embedder-Policy",
+            "Cross-Origin-Embedder-Policy-Report-Only",
+        );
+    }
+}
+
+impl fmt::Display for CrossOriginEmbedderPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.value())
+    }
+}
+
+/// Build the Cross-Origin-Resource-Policy
+///
+/// Unlike COOP and COEP, CORP has no report-only mode or reporting parameters.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CrossOriginResourcePolicy {
+    policy: ResourcePolicy,
+}
+
+impl CrossOriginResourcePolicy {
+    /// Create a new instance.
+    pub fn new(policy: ResourcePolicy) -> Self {
+        Self { policy }
+    }
+
+    /// Parse a `Cross-Origin-Resource-Policy` header value.
+    pub fn parse(value: &str) -> Result<Self, ParseError> {
+        value.parse().map(Self::new)
+    }
+
+    /// The policy value.
+    pub fn policy(&self) -> ResourcePolicy {
+        self.policy
+    }
+
+    /// Create and retrieve the policy value
+    pub fn value(&self) -> String {
+        self.policy.as_ref().to_string()
+    }
+
+    /// Sets the `Cross-Origin-Resource-Policy` HTTP header.
+    pub fn apply(&self, headers: &mut HeaderMap) {
+        headers.insert(
+            "Cross-Origin-Resource-Policy",
+            self.value().parse().unwrap(),
+        );
+    }
+}
+
+impl fmt::Display for CrossOriginResourcePolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.value())
+    }
+}
+
+/// Create a new `Cross-Origin-Opener-Policy`.
+pub fn opener_policy(policy: OpenerPolicy) -> CrossOriginOpenerPolicy {
+    CrossOriginOpenerPolicy::new(policy)
+}
+
+/// Create a new `Cross-Origin-Embedder-Policy`.
+pub fn embedder_policy(policy: EmbedderPolicy) -> CrossOriginEmbedderPolicy {
+    CrossOriginEmbedderPolicy::new(policy)
+}
+
+/// Create a new `Cross-Origin-Resource-Policy`.
+pub fn resource_policy(policy: ResourcePolicy) -> CrossOriginResourcePolicy {
+    CrossOriginResourcePolicy::new(policy)
+}
@@ -8,7 +8,8 @@
 //! # Examples
 //!
 //! ```
-//! let mut policy = armor::csp::new()
+//! let mut policy = armor::csp::new();
+//! policy
 //!     .default_src(armor::csp::Source::SameOrigin)
 //!     .default_src("areweasyncyet.rs")
 //!     .script_src(armor::csp::Source::SameOrigin)
@@ -22,11 +23,130 @@
 //! assert_eq!(headers["content-security-policy"], "base-uri 'none'; default-src 'self' areweasyncyet.rs; object-src 'none'; script-src 'self'; upgrade-insecure-requests");
 //! ```
 
+use crate::url::{self, Url};
+use crate::{ParseError, Severity};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
 use http::HeaderMap;
 use serde::Serialize;
+use sha2::{Digest, Sha256, Sha384, Sha512};
 use std::collections::HashMap;
 use std::fmt;
 
+/// Directives defined by CSP Level 3.
+const DIRECTIVES: &[&str] = &[
+    "base-uri",
+    "child-src",
+    "connect-src",
+    "default-src",
+    "fenced-frame-src",
+    "font-src",
+    "form-action",
+    "frame-ancestors",
+    "frame-src",
+    "img-src",
+    "manifest-src",
+    "media-src",
+    "object-src",
+    "report-to",
+    "report-uri",
+    "require-trusted-types-for",
+    "sandbox",
+    "script-src",
+    "script-src-attr",
+    "script-src-elem",
+    "style-src",
+    "style-src-attr",
+    "style-src-elem",
+    "trusted-types",
+    "upgrade-insecure-requests",
+    "webrtc",
+    "worker-src",
+];
+
+/// What each directive controls, for [`describe_directive`].
+const DESCRIPTIONS: &[(&str, &str)] = &[
+    ("base-uri", "URLs allowed in a `<base>` element"),
+    (
+        "block-all-mixed-content",
+        "blocks HTTP resources on HTTPS pages (deprecated)",
+    ),
+    (
+        "child-src",
+        "sources of workers and nested browsing contexts",
+    ),
+    (
+        "connect-src",
+        "URLs loaded by fetch, XHR, WebSocket and EventSource",
+    ),
+    (
+        "default-src",
+        "fallback for fetch directives that aren't set",
+    ),
+    ("fenced-frame-src", "sources of `<fencedframe>` elements"),
+    ("font-src", "sources of fonts loaded with `@font-face`"),
+    ("form-action", "URLs forms may submit to"),
+    (
+        "frame-ancestors",
+        "pages that may embed this page in a frame",
+    ),
+    ("frame-src", "sources of `<frame>` and `<iframe>` elements"),
+    ("img-src", "sources of images and favicons"),
+    ("manifest-src", "sources of web app manifests"),
+    (
+        "media-src",
+        "sources of `<audio>`, `<video>` and `<track>` elements",
+    ),
+    ("object-src", "sources of `<object>` and `<embed>` plugins"),
+    ("plugin-types", "MIME types of allowed plugins (deprecated)"),
+    (
+        "prefetch-src",
+        "sources that may be prefetched (deprecated)",
+    ),
+    (
+        "referrer",
+        "referrer policy (deprecated, use `Referrer-Policy`)",
+    ),
+    ("reflected-xss", "XSS auditor mode (deprecated)"),
+    ("report-to", "reporting group violations are sent to"),
+    (
+        "report-uri",
+        "URL violations are sent to (superseded by `report-to`)",
+    ),
+    (
+        "require-sri-for",
+        "requires Subresource Integrity (deprecated)",
+    ),
+    (
+        "require-trusted-types-for",
+        "requires Trusted Types for DOM XSS sinks",
+    ),
+    ("sandbox", "applies iframe sandbox restrictions to the page"),
+    ("script-src", "sources of scripts"),
+    ("script-src-attr", "inline event handlers like `onclick`"),
+    ("script-src-elem", "`<script>` elements"),
+    ("style-src", "sources of stylesheets"),
+    ("style-src-attr", "inline `style` attributes"),
+    (
+        "style-src-elem",
+        "`<style>` and stylesheet `<link>` elements",
+    ),
+    ("trusted-types", "allowed Trusted Types policy names"),
+    ("upgrade-insecure-requests", "loads HTTP URLs over HTTPS"),
+    ("webrtc", "whether WebRTC connections are allowed"),
+    ("worker-src", "sources of workers"),
+];
+
+/// Directives that browsers no longer support.
+const DEPRECATED_DIRECTIVES: &[&str] = &[
+    "block-all-mixed-content",
+    "plugin-types",
+    "prefetch-src",
+    "referrer",
+    "reflected-xss",
+    "require-sri-for",
+];
+
 /// Define source value
 ///
 /// [read more](https://content-security-policy.com)
@@ -99,7 +219,7 @@ impl AsRef<str> for Source {
 /// Define `report-to` directive value
 ///
 /// [MDN | report-to](https://developer.mozilla.org/en-US/docs/Web/HTTP/Headers/Content-Security-Policy/report-to)
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Debug, Clone)]
 pub struct ReportTo {
     #[serde(skip_serializing_if = "Option::is_none")]
     group: Option<String>,
@@ -109,16 +229,79 @@ pub struct ReportTo {
     include_subdomains: Option<bool>,
 }
 
+impl ReportTo {
+    /// Create a new instance.
+    pub fn new(max_age: i32, endpoints: Vec<ReportToEndpoint>) -> Self {
+        Self {
+            group: None,
+            max_age,
+            endpoints,
+            include_subdomains: None,
+        }
+    }
+
+    /// Set the group name. Defaults to `"default"`.
+    pub fn group<T: AsRef<str>>(&mut self, group: T) -> &mut Self {
+        self.group = Some(group.as_ref().to_string());
+        self
+    }
+
+    /// Set whether the group applies to subdomains.
+    pub fn include_subdomains(&mut self, include: bool) -> &mut Self {
+        self.include_subdomains = Some(include);
+        self
+    }
+
+    /// The group name.
+    pub fn group_name(&self) -> &str {
+        self.group.as_ref().map_or("default", String::as_str)
+    }
+
+    /// Create and retrieve the `Report-To` header value
+    pub fn value(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+
+    /// Adds the group to the `Report-To` header, unless it's already there.
+    ///
+    /// ## Examples
+    /// ```
+    /// use armor::csp::{ReportTo, ReportToEndpoint};
+    ///
+    /// let mut group = ReportTo::new(86400, vec![ReportToEndpoint::new("https://example.com/reports")]);
+    /// group.group("nel");
+    ///
+    /// let mut headers = http::HeaderMap::new();
+    /// group.apply(&mut headers);
+    /// assert_eq!(headers["Report-To"], r#"{"group":"nel","max_age":86400,"endpoints":[{"url":"https://example.com/reports"}]}"#);
+    /// ```
+    pub fn apply(&self, headers: &mut HeaderMap) {
+        let val: http::HeaderValue = self.value().parse().unwrap();
+        if !headers.get_all("Report-To").iter().any(|v| *v == val) {
+            headers.append("Report-To", val);
+        }
+    }
+}
+
 /// Define `endpoints` for `report-to` directive value
 ///
 /// [MDN | report-to](https://developer.mozilla.org/en-US/docs/Web/HTTP/Headers/Content-Security-Policy/report-to)
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Debug, Clone)]
 pub struct ReportToEndpoint {
     url: String,
 }
 
+impl ReportToEndpoint {
+    /// Create a new instance.
+    pub fn new<T: AsRef<str>>(url: T) -> Self {
+        Self {
+            url: url.as_ref().to_string(),
+        }
+    }
+}
+
 /// Build the Content-Security-Policy
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct ContentSecurityPolicy {
     policy: Vec<String>,
     report_only_flag: bool,
@@ -147,13 +330,199 @@ impl ContentSecurityPolicy {
         }
     }
 
-    fn insert_directive<T: AsRef<str>>(&mut self, directive: &str, source: T) {
+    /// Parse a serialized policy, e.g. the value of a `Content-Security-Policy` header.
+    ///
+    /// Directive names are lowercased, and repeated directives are ignored like browsers do.
+    ///
+    /// ## Examples
+    /// ```
+    /// let policy = armor::csp::ContentSecurityPolicy::parse("Script-Src 'self' cdn.example; upgrade-insecure-requests");
+    /// assert_eq!(policy.directive("script-src"), Some(&["'self'".to_string(), "cdn.example".to_string()][..]));
+    /// assert!(policy.has_directive("upgrade-insecure-requests"));
+    /// assert_eq!(policy.value(), "script-src 'self' cdn.example; upgrade-insecure-requests");
+    /// ```
+    pub fn parse(value: &str) -> Self {
+        let mut policy = Self::new();
+        for directive in value.split(';') {
+            let mut tokens = directive.split_ascii_whitespace();
+            let name = match tokens.next() {
+                Some(name) => name.to_ascii_lowercase(),
+                None => continue,
+            };
+            if policy.has_directive(&name) {
+                continue;
+            }
+            let sources: Vec<&str> = tokens.collect();
+            if sources.is_empty() {
+                policy.policy.push(name);
+            } else {
+                for source in sources {
+                    policy.insert_directive(&name, source);
+                }
+            }
+        }
+        policy
+    }
+
+    /// Parse every enforced policy: each `Content-Security-Policy` header, and each
+    /// comma-separated policy within one. When no policy is enforced, the
+    /// `Content-Security-Policy-Report-Only` policies are returned instead.
+    ///
+    /// Browsers apply every policy, so a load is only allowed when all of them allow it.
+    ///
+    /// ## Examples
+    /// ```
+    /// let mut headers = http::HeaderMap::new();
+    /// headers.append("Content-Security-Policy", "default-src 'self'".parse().unwrap());
+    /// headers.append("Content-Security-Policy", "img-src *, script-src 'none'".parse().unwrap());
+    ///
+    /// let policies = armor::csp::ContentSecurityPolicy::from_headers(&headers);
+    /// assert_eq!(policies.len(), 3);
+    /// let page = "https://example.com/";
+    /// assert!(!policies.iter().all(|policy| policy.allows("script-src", "https://example.com/app.js", page)));
+    /// ```
+    pub fn from_headers(headers: &HeaderMap) -> Vec<Self> {
+        let parse = |name| -> Vec<Self> {
+            headers
+                .get_all(name)
+                .iter()
+                .filter_map(|value| value.to_str().ok())
+                .flat_map(|value| value.split(','))
+                .filter(|policy| !policy.trim().is_empty())
+                .map(Self::parse)
+                .collect()
+        };
+        let enforced = parse("Content-Security-Policy");
+        if !enforced.is_empty() {
+            return enforced;
+        }
+        let mut policies = parse("Content-Security-Policy-Report-Only");
+        for policy in &mut policies {
+            policy.report_only();
+        }
+        policies
+    }
+
+    pub(crate) fn insert_directive<T: AsRef<str>>(&mut self, directive: &str, source: T) {
         let directive = String::from(directive);
-        let directives = self.directives.entry(directive).or_insert_with(Vec::new);
+        let directives = self.directives.entry(directive).or_default();
         let source: String = source.as_ref().to_string();
         directives.push(source);
     }
 
+    /// Get the sources of a directive, if the policy defines it.
+    pub fn directive(&self, directive: &str) -> Option<&[String]> {
+        self.directives.get(directive).map(Vec::as_slice)
+    }
+
+    /// Check whether the policy defines a directive, including directives without sources.
+    pub fn has_directive(&self, directive: &str) -> bool {
+        self.directives.contains_key(directive)
+            || self
+                .policy
+                .iter()
+                .any(|policy| policy.split_whitespace().next() == Some(directive))
+    }
+
+    /// The names of the directives the policy defines, sorted.
+    pub fn directive_names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self
+            .policy
+            .iter()
+            .filter_map(|policy| policy.split_whitespace().next())
+            .chain(self.directives.keys().map(String::as_str))
+            .collect();
+        names.sort_unstable();
+        names.dedup();
+        names
+    }
+
+    /// Get the sources that apply to a fetch directive, following the CSP Level 3 fallback
+    /// list: `script-src-elem` falls back to `script-src`, `worker-src` to `child-src` and
+    /// `script-src`, and every fetch directive eventually to `default-src`.
+    pub fn effective_sources(&self, directive: &str) -> Option<&[String]> {
+        let fallbacks: &[&str] = match directive {
+            "script-src-elem" | "script-src-attr" => &["script-src"],
+            "style-src-elem" | "style-src-attr" => &["style-src"],
+            "worker-src" => &["child-src", "script-src"],
+            "frame-src" => &["child-src"],
+            _ => &[],
+        };
+        std::iter::once(directive)
+            .chain(fallbacks.iter().copied())
+            .chain(std::iter::once("default-src"))
+            .find_map(|name| self.directive(name))
+    }
+
+    /// Whether the policy allows a document at `document_url` to load `url` under a fetch
+    /// directive like `img-src`.
+    ///
+    /// URLs are matched against the host, scheme, `*` and `'self'` sources the directive
+    /// applies. Scripts under `'strict-dynamic'` can't be decided from their URL alone, since
+    /// trust is passed on by the scripts that load them, so they're treated as allowed.
+    ///
+    /// [read more](https://www.w3.org/TR/CSP3/#match-url-to-source-list)
+    ///
+    /// ## Examples
+    /// ```
+    /// let policy = armor::csp::ContentSecurityPolicy::parse(
+    ///     "default-src 'self'; img-src 'self' https://*.cdn.example data:",
+    /// );
+    /// let page = "https://example.com/";
+    /// assert!(policy.allows("img-src", "https://img.cdn.example/logo.png", page));
+    /// assert!(policy.allows("img-src", "data:image/png;base64,AAAA", page));
+    /// assert!(!policy.allows("img-src", "http://img.cdn.example/logo.png", page));
+    /// assert!(policy.allows("script-src", "https://example.com/app.js", page));
+    /// assert!(!policy.allows("script-src", "https://cdn.example/app.js", page));
+    /// ```
+    pub fn allows(&self, directive: &str, url: &str, document_url: &str) -> bool {
+        self.allows_request(directive, url, document_url, false)
+    }
+
+    /// Like [`allows`](Self::allows), for a request that may follow a redirect. The path of a
+    /// source isn't matched after a redirect, so it doesn't leak where the redirect went.
+    pub(crate) fn allows_request(
+        &self,
+        directive: &str,
+        url: &str,
+        document_url: &str,
+        redirected: bool,
+    ) -> bool {
+        let directive = directive.to_ascii_lowercase();
+        let sources = match self.effective_sources(&directive) {
+            Some(sources) => sources,
+            None => return true,
+        };
+        let is_script = directive.starts_with("script-src") || directive == "worker-src";
+        if is_script
+            && sources
+                .iter()
+                .any(|source| source.eq_ignore_ascii_case("'strict-dynamic'"))
+        {
+            return true;
+        }
+        let (url, document) = match (Url::parse(url), Url::parse(document_url)) {
+            (Some(url), Some(document)) => (url, document),
+            _ => return false,
+        };
+        sources
+            .iter()
+            .any(|source| source_matches(source, &url, &document, redirected))
+    }
+
+    /// Whether the policy is sent as `Content-Security-Policy-Report-Only`.
+    pub fn is_report_only(&self) -> bool {
+        self.report_only_flag
+    }
+
+    /// Remove a directive from the policy.
+    pub fn remove_directive(&mut self, directive: &str) -> &mut Self {
+        self.directives.remove(directive);
+        self.policy
+            .retain(|policy| policy.split_whitespace().next() != Some(directive));
+        self
+    }
+
     /// Defines the Content-Security-Policy `base-uri` directive
     ///
     /// [MDN | base-uri](https://developer.mozilla.org/en-US/docs/Web/HTTP/Headers/Content-Security-Policy/base-uri)
@@ -301,6 +670,14 @@ impl ContentSecurityPolicy {
         self
     }
 
+    /// Defines the Content-Security-Policy `script-src-attr` directive
+    ///
+    /// [MDN | script-src-attr](https://developer.mozilla.org/en-US/docs/Web/HTTP/Headers/Content-Security-Policy/script-src-attr)
+    pub fn script_src_attr<T: AsRef<str>>(&mut self, source: T) -> &mut Self {
+        self.insert_directive("script-src-attr", source);
+        self
+    }
+
     /// Defines the Content-Security-Policy `style-src` directive
     ///
     /// [MDN | style-src](https://developer.mozilla.org/en-US/docs/Web/HTTP/Headers/Content-Security-Policy/style-src)
@@ -332,18 +709,207 @@ impl ContentSecurityPolicy {
         self
     }
 
+    /// The sources of a directive, or an empty list for directives without sources or that
+    /// aren't set.
+    fn sources(&self, directive: &str) -> &[String] {
+        self.directive(directive).unwrap_or(&[])
+    }
+
+    /// List the changes from this policy to another.
+    ///
+    /// ## Examples
+    /// ```
+    /// use armor::csp::{ContentSecurityPolicy, PolicyChange};
+    ///
+    /// let old = ContentSecurityPolicy::parse("script-src 'self' cdn.example; img-src *");
+    /// let new = ContentSecurityPolicy::parse("script-src 'self' 'nonce-abc'; object-src 'none'");
+    /// let changes: Vec<String> = old.diff(&new).iter().map(|c| c.to_string()).collect();
+    /// assert_eq!(changes, [
+    ///     "- img-src *",
+    ///     "+ object-src 'none'",
+    ///     "- script-src cdn.example",
+    ///     "+ script-src 'nonce-abc'",
+    /// ]);
+    /// ```
+    pub fn diff(&self, other: &Self) -> Vec<PolicyChange> {
+        let mut names = self.directive_names();
+        names.extend(other.directive_names());
+        names.sort_unstable();
+        names.dedup();
+
+        let mut changes = Vec::new();
+        for name in names {
+            let (old, new) = (self.sources(name), other.sources(name));
+            match (self.has_directive(name), other.has_directive(name)) {
+                (true, false) => changes.push(PolicyChange::RemovedDirective {
+                    directive: name.to_string(),
+                    sources: old.to_vec(),
+                }),
+                (false, true) => changes.push(PolicyChange::AddedDirective {
+                    directive: name.to_string(),
+                    sources: new.to_vec(),
+                }),
+                _ => {
+                    for source in old.iter().filter(|source| !new.contains(source)) {
+                        changes.push(PolicyChange::RemovedSource {
+                            directive: name.to_string(),
+                            source: source.clone(),
+                        });
+                    }
+                    for source in new.iter().filter(|source| !old.contains(source)) {
+                        changes.push(PolicyChange::AddedSource {
+                            directive: name.to_string(),
+                            source: source.clone(),
+                        });
+                    }
+                }
+            }
+        }
+        changes
+    }
+
+    /// Check the policy for common weaknesses.
+    ///
+    /// ## Examples
+    /// ```
+    /// use armor::Severity;
+    ///
+    /// let policy = armor::csp::ContentSecurityPolicy::parse("script-src 'self' 'unsafe-inline'; object-src 'none'; base-uri 'none'");
+    /// let lints = policy.lint();
+    /// assert_eq!(lints.len(), 1);
+    /// assert_eq!(lints[0].severity, Severity::High);
+    /// assert_eq!(lints[0].directive.as_deref(), Some("script-src"));
+    /// ```
+    pub fn lint(&self) -> Vec<Lint> {
+        let mut lints = Vec::new();
+        let mut push = |severity, directive: Option<&str>, message: String| {
+            lints.push(Lint {
+                severity,
+                directive: directive.map(String::from),
+                message,
+            })
+        };
+
+        let script = if self.directives.contains_key("script-src") {
+            "script-src"
+        } else {
+            "default-src"
+        };
+        match self.directive(script) {
+            None => push(
+                Severity::High,
+                None,
+                "neither `script-src` nor `default-src` is set, so scripts can load from anywhere"
+                    .to_string(),
+            ),
+            Some(sources) => {
+                let has = |keyword: &str| {
+                    sources
+                        .iter()
+                        .any(|source| source.eq_ignore_ascii_case(keyword))
+                };
+                let strict_dynamic = has(Source::StrictDynamic.as_ref());
+                if has(Source::UnsafeInline.as_ref())
+                    && !sources.iter().any(|s| is_nonce_or_hash(s))
+                {
+                    push(
+                        Severity::High,
+                        Some(script),
+                        "`'unsafe-inline'` allows injected inline scripts to run".to_string(),
+                    );
+                }
+                if has(Source::UnsafeEval.as_ref()) {
+                    push(
+                        Severity::Medium,
+                        Some(script),
+                        "`'unsafe-eval'` allows strings to be executed as code".to_string(),
+                    );
+                }
+                let permissive = sources.iter().filter(|source| is_permissive(source));
+                for source in permissive.filter(|_| !strict_dynamic) {
+                    push(
+                        Severity::High,
+                        Some(script),
+                        format!("`{}` allows scripts from any host", source),
+                    );
+                }
+            }
+        }
+
+        let object_none = self
+            .effective_sources("object-src")
+            .is_some_and(|sources| sources == [Source::None.as_ref()]);
+        if !object_none {
+            push(
+                Severity::Medium,
+                Some("object-src"),
+                "`object-src` should be `'none'` to block plugin content".to_string(),
+            );
+        }
+        if !self.has_directive("base-uri") {
+            push(
+                Severity::Medium,
+                Some("base-uri"),
+                "`base-uri` is missing, so an injected `<base>` tag can redirect relative URLs"
+                    .to_string(),
+            );
+        }
+        let style_inline = self.effective_sources("style-src").is_some_and(|sources| {
+            sources
+                .iter()
+                .any(|source| source.eq_ignore_ascii_case(Source::UnsafeInline.as_ref()))
+                && !sources.iter().any(|source| is_nonce_or_hash(source))
+        });
+        if style_inline {
+            push(
+                Severity::Low,
+                Some("style-src"),
+                "`'unsafe-inline'` allows injected styles".to_string(),
+            );
+        }
+
+        for name in self.directive_names() {
+            if DEPRECATED_DIRECTIVES.contains(&name) {
+                push(
+                    Severity::Info,
+                    Some(name),
+                    format!("`{}` is deprecated and ignored by browsers", name),
+                );
+            } else if !DIRECTIVES.contains(&name) {
+                push(
+                    Severity::Low,
+                    Some(name),
+                    format!("unknown directive `{}`", name),
+                );
+            }
+            let insecure = self
+                .directive(name)
+                .unwrap_or(&[])
+                .iter()
+                .filter(|source| source.to_ascii_lowercase().starts_with("http://"));
+            for source in insecure {
+                push(
+                    Severity::Medium,
+                    Some(name),
+                    format!("`{}` is loaded over plain HTTP", source),
+                );
+            }
+        }
+        lints
+    }
+
     /// Create and retrieve the policy value
-    fn value(&mut self) -> String {
+    pub fn value(&self) -> String {
+        let mut policy = self.policy.clone();
         for (directive, sources) in &self.directives {
-            let policy = format!("{} {}", directive, sources.join(" "));
-            self.policy.push(policy);
-            self.policy.sort();
+            policy.push(format!("{} {}", directive, sources.join(" ")));
         }
-        self.policy.join("; ")
+        policy.sort();
+        policy.join("; ")
     }
 
     /// Sets the `Content-Security-Policy` (CSP) HTTP header to prevent cross-site injections
-    pub fn apply(&mut self, headers: &mut HeaderMap) {
+    pub fn apply(&self, headers: &mut HeaderMap) {
         let val = self.value().parse().unwrap();
         if !self.report_only_flag {
             headers.insert("Content-Security-Policy", val);
@@ -357,3 +923,582 @@ impl ContentSecurityPolicy {
 pub fn new() -> ContentSecurityPolicy {
     ContentSecurityPolicy::new()
 }
+
+/// Lint policies that are applied together, e.g. from [`ContentSecurityPolicy::from_headers`].
+///
+/// A load must be allowed by every policy, so a weakness is only reported when every policy
+/// has it.
+///
+/// ## Examples
+/// ```
+/// use armor::csp::{self, ContentSecurityPolicy};
+///
+/// let weak = ContentSecurityPolicy::parse("script-src 'self' 'unsafe-inline'");
+/// let strict = ContentSecurityPolicy::parse("script-src 'self'; object-src 'none'; base-uri 'none'");
+/// assert!(!weak.lint().is_empty());
+/// assert!(csp::lint_all(&[weak, strict]).is_empty());
+/// ```
+pub fn lint_all(policies: &[ContentSecurityPolicy]) -> Vec<Lint> {
+    let mut policies = policies.iter();
+    let mut lints = match policies.next() {
+        Some(policy) => policy.lint(),
+        None => return Vec::new(),
+    };
+    for policy in policies {
+        let other = policy.lint();
+        lints.retain(|lint| other.contains(lint));
+    }
+    lints
+}
+
+/// A weakness found by [`ContentSecurityPolicy::lint`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Lint {
+    /// How serious the weakness is.
+    pub severity: Severity,
+    /// The directive the weakness was found in, if any.
+    pub directive: Option<String>,
+    /// A description of the weakness.
+    pub message: String,
+}
+
+impl fmt::Display for Lint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.severity, self.message)
+    }
+}
+
+fn is_nonce_or_hash(source: &str) -> bool {
+    let source = source.to_ascii_lowercase();
+    ["'nonce-", "'sha256-", "'sha384-", "'sha512-"]
+        .iter()
+        .any(|prefix| source.starts_with(prefix))
+}
+
+/// Whether a source allows loading from any host.
+fn is_permissive(source: &str) -> bool {
+    let source = source.to_ascii_lowercase();
+    ["*", "http:", "https:", "data:", "blob:", "filesystem:"].contains(&source.as_str())
+}
+
+/// A difference between two policies, found by [`ContentSecurityPolicy::diff`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PolicyChange {
+    /// A directive was added.
+    AddedDirective {
+        /// The directive name.
+        directive: String,
+        /// The directive's sources.
+        sources: Vec<String>,
+    },
+    /// A directive was removed.
+    RemovedDirective {
+        /// The directive name.
+        directive: String,
+        /// The directive's sources.
+        sources: Vec<String>,
+    },
+    /// A source was added to a directive.
+    AddedSource {
+        /// The directive name.
+        directive: String,
+        /// The added source.
+        source: String,
+    },
+    /// A source was removed from a directive.
+    RemovedSource {
+        /// The directive name.
+        directive: String,
+        /// The removed source.
+        source: String,
+    },
+}
+
+impl fmt::Display for PolicyChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (sign, directive, sources) = match *self {
+            PolicyChange::AddedDirective {
+                ref directive,
+                ref sources,
+            } => ('+', directive, sources.join(" ")),
+            PolicyChange::RemovedDirective {
+                ref directive,
+                ref sources,
+            } => ('-', directive, sources.join(" ")),
+            PolicyChange::AddedSource {
+                ref directive,
+                ref source,
+            } => ('+', directive, source.clone()),
+            PolicyChange::RemovedSource {
+                ref directive,
+                ref source,
+            } => ('-', directive, source.clone()),
+        };
+        write!(f, "{} {}", sign, directive)?;
+        if !sources.is_empty() {
+            write!(f, " {}", sources)?;
+        }
+        Ok(())
+    }
+}
+
+/// Whether a string is a single source expression: `*`, a quoted keyword, nonce or hash, a
+/// scheme like `https:`, or a host like `https://*.example.com:8443/path`.
+///
+/// [read more](https://www.w3.org/TR/CSP3/#grammardef-serialized-source-list)
+pub(crate) fn is_source_expression(source: &str) -> bool {
+    let is_scheme = |scheme: &str| {
+        scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+            && scheme
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c))
+    };
+    if source == "*" {
+        return true;
+    }
+    if let Some(quoted) = source
+        .strip_prefix('\'')
+        .and_then(|source| source.strip_suffix('\''))
+    {
+        return !quoted.is_empty()
+            && quoted
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "+-/=_".contains(c));
+    }
+    if let Some(scheme) = source.strip_suffix(':') {
+        return is_scheme(scheme);
+    }
+
+    let rest = match source.split_once("://") {
+        Some((scheme, rest)) if is_scheme(scheme) => rest,
+        Some(_) => return false,
+        None => source,
+    };
+    let (authority, path) = match rest.find('/') {
+        Some(index) => rest.split_at(index),
+        None => (rest, ""),
+    };
+    let (host, port) = match authority.split_once(':') {
+        Some((host, port)) => (host, Some(port)),
+        None => (authority, None),
+    };
+    let labels = host.strip_prefix("*.").unwrap_or(host);
+    let valid_host = host == "*"
+        || (!labels.is_empty()
+            && labels.split('.').all(|label| {
+                !label.is_empty() && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+            }));
+    let valid_port = port.is_none_or(|port| {
+        port == "*" || (!port.is_empty() && port.chars().all(|c| c.is_ascii_digit()))
+    });
+    let valid_path = path
+        .chars()
+        .all(|c| c.is_ascii_graphic() && c != ';' && c != ',');
+    valid_host && valid_port && valid_path
+}
+
+/// Whether a source expression matches a URL loaded by a document. Paths are ignored for
+/// `redirected` requests.
+pub(crate) fn source_matches(source: &str, url: &Url, document: &Url, redirected: bool) -> bool {
+    let source = source.to_ascii_lowercase();
+    match source.as_str() {
+        "*" => {
+            return matches!(url.scheme.as_str(), "http" | "https" | "ws" | "wss")
+                || url.scheme == document.scheme
+        }
+        "'self'" => {
+            return url.host.is_some()
+                && url.host == document.host
+                && scheme_matches(&document.scheme, &url.scheme)
+                && if url.scheme == document.scheme {
+                    url.effective_port() == document.effective_port()
+                } else {
+                    url.effective_port() == url::default_port(&url.scheme)
+                }
+        }
+        _ if source.starts_with('\'') => return false,
+        _ => {}
+    }
+    if let Some(scheme) = source.strip_suffix(':') {
+        return scheme_matches(scheme, &url.scheme);
+    }
+
+    let (scheme, rest) = match source.split_once("://") {
+        Some((scheme, rest)) => (Some(scheme), rest),
+        None => (None, source.as_str()),
+    };
+    match scheme {
+        Some(scheme) if !scheme_matches(scheme, &url.scheme) => return false,
+        None if !scheme_matches(&document.scheme, &url.scheme) => return false,
+        _ => {}
+    }
+    let (authority, path) = match rest.find('/') {
+        Some(index) => rest.split_at(index),
+        None => (rest, ""),
+    };
+    let (host, port) = match url::split_port(authority) {
+        Some(parts) => parts,
+        None => return false,
+    };
+    let url_host = match url.host {
+        Some(ref url_host) => url_host,
+        None => return false,
+    };
+    let host_matches = match host.strip_prefix("*.") {
+        Some(domain) => url_host.ends_with(&format!(".{}", domain)),
+        None => host == "*" || host == url_host,
+    };
+    let url_port = url.effective_port();
+    let port_matches = match port {
+        Some("*") => true,
+        Some(port) => {
+            let port = port.parse().ok();
+            port == url_port || (port == Some(80) && url_port == Some(443))
+        }
+        None => {
+            let default = url::default_port(scheme.unwrap_or(&url.scheme));
+            url_port == url::default_port(&url.scheme) || url_port == default
+        }
+    };
+    let path_matches = match path {
+        _ if redirected => true,
+        "" | "/" => true,
+        path if path.ends_with('/') => url.path.starts_with(path),
+        path => url.path == path,
+    };
+    host_matches && port_matches && path_matches
+}
+
+/// Whether a source's scheme matches a URL's scheme, allowing upgrades to secure schemes.
+fn scheme_matches(source: &str, url: &str) -> bool {
+    match (source, url) {
+        (source, url) if source == url => true,
+        ("http", "https") | ("ws", "wss") | ("ws", "http") | ("ws", "https") => true,
+        ("wss", "https") | ("http", "ws") | ("http", "wss") | ("https", "wss") => true,
+        _ => false,
+    }
+}
+
+/// Describe what a directive controls, if it's known.
+///
+/// ## Examples
+/// ```
+/// assert_eq!(armor::csp::describe_directive("img-src"), Some("sources of images and favicons"));
+/// assert_eq!(armor::csp::describe_directive("bogus-src"), None);
+/// ```
+pub fn describe_directive(directive: &str) -> Option<&'static str> {
+    let directive = directive.to_ascii_lowercase();
+    DESCRIPTIONS
+        .iter()
+        .find(|(name, _)| *name == directive)
+        .map(|(_, description)| *description)
+}
+
+/// Describe what a source expression allows.
+///
+/// ## Examples
+/// ```
+/// assert_eq!(armor::csp::describe_source("'self'"), "the page's own origin");
+/// assert_eq!(armor::csp::describe_source("*.example.com"), "any subdomain of example.com");
+/// ```
+pub fn describe_source(source: &str) -> String {
+    let lower = source.to_ascii_lowercase();
+    let keyword = match lower.as_str() {
+        "'self'" => Some("the page's own origin"),
+        "'none'" => Some("nothing"),
+        "'unsafe-inline'" => Some("inline code, unless a nonce or hash is also listed"),
+        "'unsafe-eval'" => Some("`eval()` and similar string-to-code functions"),
+        "'wasm-unsafe-eval'" => Some("compiling WebAssembly"),
+        "'unsafe-hashes'" => Some("inline event handlers matching a listed hash"),
+        "'strict-dynamic'" => Some("scripts loaded by already trusted scripts; hosts are ignored"),
+        "'report-sample'" => Some("includes a sample of the violating code in reports"),
+        "'inline-speculation-rules'" => Some("inline speculation rules"),
+        "*" => Some("any URL except `data:`, `blob:` and `filesystem:`"),
+        _ => None,
+    };
+    if let Some(keyword) = keyword {
+        return keyword.to_string();
+    }
+    if lower.starts_with("'nonce-") {
+        return "elements with a matching `nonce` attribute".to_string();
+    }
+    for algorithm in &["sha256", "sha384", "sha512"] {
+        if lower.starts_with(&format!("'{}-", algorithm)) {
+            return format!("inline code with a matching {} hash", algorithm);
+        }
+    }
+    if lower.ends_with(':') && !lower.contains('/') {
+        return format!("any `{}` URL", lower);
+    }
+    let host = lower.split("://").last().unwrap_or(&lower);
+    match host.strip_prefix("*.") {
+        Some(domain) => format!("any subdomain of {}", domain),
+        None if lower.contains("://") => format!("{} only", lower),
+        None => format!("{} over the page's scheme or a more secure one", host),
+    }
+}
+
+/// A hash algorithm for hash sources.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HashAlgorithm {
+    /// SHA-256
+    Sha256,
+    /// SHA-384
+    Sha384,
+    /// SHA-512
+    Sha512,
+}
+
+impl AsRef<str> for HashAlgorithm {
+    fn as_ref(&self) -> &str {
+        match *self {
+            HashAlgorithm::Sha256 => "sha256",
+            HashAlgorithm::Sha384 => "sha384",
+            HashAlgorithm::Sha512 => "sha512",
+        }
+    }
+}
+
+impl std::str::FromStr for HashAlgorithm {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().replace('-', "").as_str() {
+            "sha256" => Ok(HashAlgorithm::Sha256),
+            "sha384" => Ok(HashAlgorithm::Sha384),
+            "sha512" => Ok(HashAlgorithm::Sha512),
+            _ => Err(ParseError::new(format!("unknown hash algorithm `{}`", s))),
+        }
+    }
+}
+
+/// Create a hash source that allows an inline script or style with the given content.
+///
+/// ## Examples
+/// ```
+/// use armor::csp::{self, HashAlgorithm};
+///
+/// let source = csp::hash_source(HashAlgorithm::Sha256, "alert('Hello, world.');");
+/// assert_eq!(source, "'sha256-qznLcsROx4GACP2dm0UCKCzCG+HiZ1guq6ZZDob/Tng='");
+///
+/// let mut policy = csp::new();
+/// policy.script_src(source);
+/// ```
+pub fn hash_source<T: AsRef<[u8]>>(algorithm: HashAlgorithm, content: T) -> String {
+    let content = content.as_ref();
+    let digest = match algorithm {
+        HashAlgorithm::Sha256 => Sha256::digest(content).to_vec(),
+        HashAlgorithm::Sha384 => Sha384::digest(content).to_vec(),
+        HashAlgorithm::Sha512 => Sha512::digest(content).to_vec(),
+    };
+    format!("'{}-{}'", algorithm.as_ref(), STANDARD.encode(digest))
+}
+
+/// Parse the policy of the first `<meta http-equiv="Content-Security-Policy">` element in an
+/// HTML document.
+///
+/// ## Examples
+/// ```
+/// let html = r#"<head><meta http-equiv="content-security-policy" content="default-src 'self'"></head>"#;
+/// let policy = armor::csp::from_html(html).unwrap();
+/// assert_eq!(policy.value(), "default-src 'self'");
+/// ```
+pub fn from_html(html: &str) -> Option<ContentSecurityPolicy> {
+    html_tags(html, "meta").find_map(|(attributes, _)| {
+        let attribute = |name: &str| {
+            attributes
+                .iter()
+                .find(|(key, _)| key.eq_ignore_ascii_case(name))
+                .map(|(_, value)| value.as_str())
+        };
+        let http_equiv = attribute("http-equiv")?;
+        if http_equiv.eq_ignore_ascii_case("Content-Security-Policy") {
+            attribute("content").map(ContentSecurityPolicy::parse)
+        } else {
+            None
+        }
+    })
+}
+
+/// Hash every inline `<script>` and `<style>` element in an HTML document, returning the
+/// element name and hash source of each in document order.
+///
+/// ## Examples
+/// ```
+/// use armor::csp::{self, HashAlgorithm};
+///
+/// let html = "<script src=app.js></script><script>alert('Hello, world.');</script>";
+/// let hashes = csp::inline_hashes(html, HashAlgorithm::Sha256);
+/// assert_eq!(hashes, [("script", "'sha256-qznLcsROx4GACP2dm0UCKCzCG+HiZ1guq6ZZDob/Tng='".to_string())]);
+/// ```
+pub fn inline_hashes(html: &str, algorithm: HashAlgorithm) -> Vec<(&'static str, String)> {
+    let mut hashes: Vec<(usize, &'static str, String)> = Vec::new();
+    for element in &["script", "style"] {
+        for (attributes, content) in html_tags(html, element) {
+            let external = attributes
+                .iter()
+                .any(|(key, _)| key.eq_ignore_ascii_case("src"));
+            if let (false, Some((offset, content))) = (external, content) {
+                hashes.push((offset, element, hash_source(algorithm, content)));
+            }
+        }
+    }
+    hashes.sort_by_key(|(offset, _, _)| *offset);
+    hashes
+        .into_iter()
+        .map(|(_, element, hash)| (element, hash))
+        .collect()
+}
+
+type Attributes = Vec<(String, String)>;
+
+/// Find the elements with a name in an HTML document, returning their attributes and, for
+/// elements with a closing tag, the offset and raw text of their content.
+fn html_tags<'a>(
+    html: &'a str,
+    name: &'a str,
+) -> impl Iterator<Item = (Attributes, Option<(usize, &'a str)>)> + 'a {
+    let lower = html.to_ascii_lowercase();
+    let open = format!("<{}", name);
+    let close = format!("</{}", name);
+    let mut position = 0;
+    std::iter::from_fn(move || loop {
+        let start = position + lower[position..].find(&open)?;
+        let after_name = start + open.len();
+        position = after_name;
+        let boundary = lower[after_name..].chars().next()?;
+        if !(boundary.is_ascii_whitespace() || boundary == '>' || boundary == '/') {
+            continue;
+        }
+        let (attributes, end) = parse_attributes(&html[after_name..]);
+        let content_start = after_name + end;
+        position = content_start;
+        let content = lower[content_start..].find(&close).map(|length| {
+            position = content_start + length;
+            (content_start, &html[content_start..content_start + length])
+        });
+        return Some((attributes, content));
+    })
+}
+
+/// Decode the character references in an attribute value, like `&#39;` or `&quot;`.
+///
+/// Numeric references and the named references a policy is likely to contain are decoded;
+/// anything else is kept as written.
+fn decode_references(value: &str) -> String {
+    const NAMED: &[(&str, char)] = &[
+        ("amp", '&'),
+        ("apos", '\''),
+        ("ast", '*'),
+        ("colon", ':'),
+        ("commat", '@'),
+        ("equals", '='),
+        ("gt", '>'),
+        ("lt", '<'),
+        ("nbsp", '\u{a0}'),
+        ("num", '#'),
+        ("period", '.'),
+        ("plus", '+'),
+        ("quot", '"'),
+        ("semi", ';'),
+        ("sol", '/'),
+    ];
+
+    let mut decoded = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let reference = &rest[1..];
+        let (character, length) = if let Some(number) = reference.strip_prefix('#') {
+            let (digits, radix, prefix) = match number.strip_prefix(['x', 'X']) {
+                Some(hex) => (hex, 16, 2),
+                None => (number, 10, 1),
+            };
+            let end = digits
+                .find(|c: char| !c.is_digit(radix))
+                .unwrap_or(digits.len());
+            if end == 0 {
+                (None, 0)
+            } else {
+                let character = u32::from_str_radix(&digits[..end], radix)
+                    .ok()
+                    .filter(|code| *code != 0)
+                    .and_then(char::from_u32)
+                    .unwrap_or('\u{fffd}');
+                let semicolon = digits[end..].starts_with(';') as usize;
+                (Some(character), 1 + prefix + end + semicolon)
+            }
+        } else {
+            NAMED
+                .iter()
+                .find(|(name, _)| {
+                    reference.starts_with(name) && reference[name.len()..].starts_with(';')
+                })
+                .map_or((None, 0), |(name, character)| {
+                    (Some(*character), name.len() + 2)
+                })
+        };
+        match character {
+            Some(character) => {
+                decoded.push(character);
+                rest = &rest[length..];
+            }
+            None => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+/// Parse the attributes of a start tag, returning them and the offset after the closing `>`.
+fn parse_attributes(tag: &str) -> (Attributes, usize) {
+    let bytes = tag.as_bytes();
+    let mut attributes = Vec::new();
+    let mut index = 0;
+    loop {
+        while index < bytes.len() && (bytes[index].is_ascii_whitespace() || bytes[index] == b'/') {
+            index += 1;
+        }
+        if index >= bytes.len() {
+            return (attributes, index);
+        }
+        if bytes[index] == b'>' {
+            return (attributes, index + 1);
+        }
+        let name_start = index;
+        while index < bytes.len()
+            && !bytes[index].is_ascii_whitespace()
+            && !b"=>/".contains(&bytes[index])
+        {
+            index += 1;
+        }
+        let name = tag[name_start..index].to_string();
+        let mut value = String::new();
+        if index < bytes.len() && bytes[index] == b'=' {
+            index += 1;
+            let quote = bytes
+                .get(index)
+                .copied()
+                .filter(|byte| *byte == b'"' || *byte == b'\'');
+            let value_start = index + quote.map_or(0, |_| 1);
+            index = value_start;
+            while index < bytes.len()
+                && match quote {
+                    Some(quote) => bytes[index] != quote,
+                    None => !bytes[index].is_ascii_whitespace() && bytes[index] != b'>',
+                }
+            {
+                index += 1;
+            }
+            value = decode_references(&tag[value_start..index]);
+            if quote.is_some() && index < bytes.len() {
+                index += 1;
+            }
+        }
+        attributes.push((name, value));
+    }
+}
@@ -0,0 +1,442 @@
+//! Protect against Cross-Site Request Forgery (CSRF).
+//!
+//! A [`CsrfProtection`] checks requests with unsafe methods in up to three layers:
+//!
+//! 1. An optional [Fetch Metadata](crate::fetch_metadata) policy rejects cross-site requests
+//!    from browsers that send `Sec-Fetch-*` headers.
+//! 2. The `Origin` header, or the origin of the `Referer` header when `Origin` is missing, must
+//!    be the request's own host or one of the allowed origins.
+//! 3. Optionally, a signed double-submit token must be sent both in a cookie and in a request
+//!    header. Other sites can't read the cookie, so they can't copy it into the header. The
+//!    signature covers the user's session, so a token issued for one session can't be
+//!    planted in another.
+//!
+//! The token cookie must be readable from JavaScript, so use
+//! [`CsrfProtection::configure_cookies`] to exempt it from `HttpOnly` when hardening cookies.
+//!
+//! [OWASP | CSRF Prevention](https://cheatsheetseries.owasp.org/cheatsheets/Cross-Site_Request_Forgery_Prevention_Cheat_Sheet.html)
+//!
+//! # Examples
+//!
+//! ```
+//! use armor::csrf::CsrfError;
+//!
+//! let mut csrf = armor::csrf::new();
+//! csrf.allow_origin("https://app.example.com")
+//!     .fetch_metadata(Some(armor::fetch_metadata::new()));
+//!
+//! let req = http::Request::post("/transfer")
+//!     .header("Host", "example.com")
+//!     .header("Origin", "https://app.example.com")
+//!     .body(())
+//!     .unwrap();
+//! assert_eq!(csrf.check_request(&req, ""), Ok(()));
+//!
+//! let req = http::Request::post("/transfer")
+//!     .header("Host", "example.com")
+//!     .header("Origin", "https://evil.com")
+//!     .body(())
+//!     .unwrap();
+//! assert_eq!(csrf.check_request(&req, ""), Err(CsrfError::OriginNotAllowed("https://evil.com".into())));
+//! ```
+
+use crate::cookies::{CookiePolicy, SameSite};
+use crate::fetch_metadata::{DenyReason, ResourceIsolationPolicy};
+use crate::url::Url;
+use crate::{ParseError, RequestContext};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use http::header::{HeaderValue, COOKIE, ORIGIN, REFERER, SET_COOKIE};
+use http::{HeaderMap, Method, Request, Uri};
+use sha2::Sha256;
+use std::error::Error;
+use std::fmt;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Why a request was rejected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CsrfError {
+    /// The Fetch Metadata policy denied the request.
+    FetchMetadata(DenyReason),
+    /// The request has neither an `Origin` nor a `Referer` header.
+    MissingOrigin,
+    /// The request's origin isn't allowed. An opaque origin is reported as `null`.
+    OriginNotAllowed(String),
+    /// The token cookie or header is missing.
+    MissingToken,
+    /// The token cookie and header don't match, or the token's signature is invalid.
+    InvalidToken,
+}
+
+impl fmt::Display for CsrfError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            CsrfError::FetchMetadata(ref reason) => reason.fmt(f),
+            CsrfError::MissingOrigin => f.write_str("request has no `Origin` or `Referer`"),
+            CsrfError::OriginNotAllowed(ref origin) => {
+                write!(f, "origin `{}` is not allowed", origin)
+            }
+            CsrfError::MissingToken => f.write_str("CSRF token is missing"),
+            CsrfError::InvalidToken => f.write_str("CSRF token is invalid"),
+        }
+    }
+}
+
+impl Error for CsrfError {}
+
+#[derive(Clone)]
+struct DoubleSubmit {
+    secret: Vec<u8>,
+    cookie_name: String,
+    header_name: String,
+}
+
+impl fmt::Debug for DoubleSubmit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DoubleSubmit")
+            .field("secret", &"..")
+            .field("cookie_name", &self.cookie_name)
+            .field("header_name", &self.header_name)
+            .finish()
+    }
+}
+
+impl DoubleSubmit {
+    /// The MAC over the session and nonce. The session is length-prefixed so its bytes can't
+    /// be shifted into the nonce.
+    fn mac(&self, session: &str, nonce: &[u8]) -> HmacSha256 {
+        let mut mac = HmacSha256::new_from_slice(&self.secret).unwrap();
+        mac.update(&(session.len() as u64).to_be_bytes());
+        mac.update(session.as_bytes());
+        mac.update(nonce);
+        mac
+    }
+
+    fn sign(&self, session: &str, nonce: &[u8]) -> Vec<u8> {
+        self.mac(session, nonce).finalize().into_bytes().to_vec()
+    }
+
+    fn verify(&self, session: &str, token: &str) -> bool {
+        let (nonce, signature) = match token.split_once('.') {
+            Some(parts) => parts,
+            None => return false,
+        };
+        let (nonce, signature) = match (
+            URL_SAFE_NO_PAD.decode(nonce),
+            URL_SAFE_NO_PAD.decode(signature),
+        ) {
+            (Ok(nonce), Ok(signature)) => (nonce, signature),
+            _ => return false,
+        };
+        self.mac(session, &nonce).verify_slice(&signature).is_ok()
+    }
+}
+
+/// Build the CSRF protection
+#[derive(Debug, Clone)]
+pub struct CsrfProtection {
+    origins: Vec<String>,
+    allow_missing_origin: bool,
+    scheme: String,
+    trust_proxy: bool,
+    fetch_metadata: Option<ResourceIsolationPolicy>,
+    double_submit: Option<DoubleSubmit>,
+}
+
+impl Default for CsrfProtection {
+    /// Only allows requests from the request's own host, and requires an origin.
+    fn default() -> Self {
+        Self {
+            origins: Vec::new(),
+            allow_missing_origin: false,
+            scheme: "https".to_string(),
+            trust_proxy: false,
+            fetch_metadata: None,
+            double_submit: None,
+        }
+    }
+}
+
+impl CsrfProtection {
+    /// Create a new instance.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allow requests from an origin other than the request's own host, e.g.
+    /// `https://app.example.com`.
+    pub fn allow_origin<T: AsRef<str>>(&mut self, origin: T) -> &mut Self {
+        let origin = origin.as_ref().trim_end_matches('/').to_ascii_lowercase();
+        self.origins.push(origin);
+        self
+    }
+
+    /// Set whether to allow requests without `Origin` and `Referer` headers.
+    ///
+    /// Browsers send `Origin` with every cross-origin request with an unsafe method, so only
+    /// non-browser clients and privacy extensions omit both. Disabled by default.
+    pub fn allow_missing_origin(&mut self, allow: bool) -> &mut Self {
+        self.allow_missing_origin = allow;
+        self
+    }
+
+    /// Set the scheme requests are received over when neither the request URI nor a trusted
+    /// proxy says. Defaults to `https`, so `http://` origins only match the request's own host
+    /// after setting this to `http`.
+    pub fn scheme<T: AsRef<str>>(&mut self, scheme: T) -> &mut Self {
+        self.scheme = scheme.as_ref().to_ascii_lowercase();
+        self
+    }
+
+    /// Compare origins against the `Forwarded` or `X-Forwarded-Host` header instead of `Host`.
+    ///
+    /// Only enable this behind a proxy that overwrites those headers.
+    pub fn trust_proxy(&mut self, trust: bool) -> &mut Self {
+        self.trust_proxy = trust;
+        self
+    }
+
+    /// Set the Fetch Metadata policy checked before the origin.
+    pub fn fetch_metadata(&mut self, policy: Option<ResourceIsolationPolicy>) -> &mut Self {
+        self.fetch_metadata = policy;
+        self
+    }
+
+    /// Require a double-submit token signed with a secret.
+    ///
+    /// The token is read from the `__Host-csrf` cookie and the `X-CSRF-Token` header.
+    pub fn double_submit<T: AsRef<[u8]>>(&mut self, secret: T) -> &mut Self {
+        self.double_submit = Some(DoubleSubmit {
+            secret: secret.as_ref().to_vec(),
+            cookie_name: "__Host-csrf".to_string(),
+            header_name: "X-CSRF-Token".to_string(),
+        });
+        self
+    }
+
+    /// Set the name of the token cookie. Has no effect without [`CsrfProtection::double_submit`].
+    ///
+    /// Returns an error when `name` isn't a valid cookie name.
+    pub fn cookie_name<T: AsRef<str>>(&mut self, name: T) -> Result<&mut Self, ParseError> {
+        let name = name.as_ref();
+        let valid = !name.is_empty()
+            && name
+                .bytes()
+                .all(|c| c.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&c));
+        if !valid {
+            return Err(ParseError::new(format!(
+                "invalid cookie name `{}`",
+                name.escape_debug()
+            )));
+        }
+        if let Some(ref mut double_submit) = self.double_submit {
+            double_submit.cookie_name = name.to_string();
+        }
+        Ok(self)
+    }
+
+    /// Set the name of the token header. Has no effect without [`CsrfProtection::double_submit`].
+    pub fn header_name<T: AsRef<str>>(&mut self, name: T) -> &mut Self {
+        if let Some(ref mut double_submit) = self.double_submit {
+            double_submit.header_name = name.as_ref().to_string();
+        }
+        self
+    }
+
+    /// Generate a new token signed for a session, or `None` without
+    /// [`CsrfProtection::double_submit`].
+    ///
+    /// `session` identifies the user's session, e.g. a session ID, and must be passed again
+    /// when checking requests. Issue a new token when the session changes, e.g. after login.
+    pub fn generate_token(&self, session: &str) -> Option<String> {
+        let double_submit = self.double_submit.as_ref()?;
+        let mut nonce = [0; 32];
+        getrandom::getrandom(&mut nonce).expect("failed to generate random CSRF token");
+        let signature = double_submit.sign(session, &nonce);
+        Some(format!(
+            "{}.{}",
+            URL_SAFE_NO_PAD.encode(nonce),
+            URL_SAFE_NO_PAD.encode(signature)
+        ))
+    }
+
+    /// Send a token in the token cookie. Has no effect without
+    /// [`CsrfProtection::double_submit`].
+    ///
+    /// The cookie is `Secure` and `SameSite=Strict`, but not `HttpOnly`, so scripts can copy it
+    /// into the token header. Returns an error when `token` isn't a valid cookie value, which
+    /// tokens from [`CsrfProtection::generate_token`] always are.
+    pub fn set_token_cookie(&self, headers: &mut HeaderMap, token: &str) -> Result<(), ParseError> {
+        let valid = token
+            .bytes()
+            .all(|c| c.is_ascii_graphic() && !b"\",;\\".contains(&c));
+        if !valid {
+            return Err(ParseError::new(format!(
+                "invalid token `{}`",
+                token.escape_debug()
+            )));
+        }
+        if let Some(ref double_submit) = self.double_submit {
+            let cookie = format!(
+                "{}={}; Path=/; Secure; SameSite={}",
+                double_submit.cookie_name,
+                token,
+                SameSite::Strict.as_ref()
+            );
+            let value = HeaderValue::from_str(&cookie)
+                .map_err(|_| ParseError::new("invalid token cookie"))?;
+            headers.append(SET_COOKIE, value);
+        }
+        Ok(())
+    }
+
+    /// Exempt the token cookie from a cookie hardening policy, so it stays readable from
+    /// JavaScript.
+    pub fn configure_cookies(&self, policy: &mut CookiePolicy) {
+        if let Some(ref double_submit) = self.double_submit {
+            policy.exempt(&double_submit.cookie_name);
+        }
+    }
+
+    /// Check a request made in a session. See [`CsrfProtection::check`].
+    pub fn check_request<B>(&self, req: &Request<B>, session: &str) -> Result<(), CsrfError> {
+        self.check(req.method(), req.uri(), req.headers(), session)
+    }
+
+    /// Check a request made in a session from its method, URI and headers.
+    ///
+    /// `session` is the one the double-submit token was generated for; it's ignored without
+    /// [`CsrfProtection::double_submit`]. Requests with safe methods (`GET`, `HEAD`, `OPTIONS`
+    /// and `TRACE`) are always allowed. Requests from explicitly allowed origins, and requests
+    /// to paths exempt from the Fetch Metadata policy, skip that policy but not the other
+    /// checks.
+    pub fn check(
+        &self,
+        method: &Method,
+        uri: &Uri,
+        headers: &HeaderMap,
+        session: &str,
+    ) -> Result<(), CsrfError> {
+        if is_safe(method) {
+            return Ok(());
+        }
+        let origin = request_origin(headers);
+        let allowed = origin
+            .as_ref()
+            .is_some_and(|origin| self.origins.contains(origin));
+        if let Some(ref policy) = self.fetch_metadata {
+            if !allowed && !policy.is_exempt(uri.path()) {
+                policy
+                    .check(method, uri, headers)
+                    .map_err(CsrfError::FetchMetadata)?;
+            }
+        }
+
+        match origin {
+            Some(_) if allowed => {}
+            Some(origin) => self.check_same_origin(origin, uri, headers)?,
+            None if self.allow_missing_origin => {}
+            None => return Err(CsrfError::MissingOrigin),
+        }
+        match self.double_submit {
+            Some(ref double_submit) => check_token(double_submit, headers, session),
+            None => Ok(()),
+        }
+    }
+
+    fn check_same_origin(
+        &self,
+        origin: String,
+        uri: &Uri,
+        headers: &HeaderMap,
+    ) -> Result<(), CsrfError> {
+        let mut ctx = RequestContext::from_parts(uri, headers);
+        ctx.trust_proxy(self.trust_proxy);
+        let scheme = ctx.effective_scheme().unwrap_or(self.scheme.as_str());
+        let own = ctx
+            .effective_host()
+            .and_then(|host| Url::parse(&format!("{}://{}", scheme, host)));
+        let same_origin = match (Url::parse(&origin), own) {
+            (Some(origin), Some(own)) => {
+                origin.host.is_some()
+                    && origin.scheme == own.scheme
+                    && origin.host == own.host
+                    && origin.effective_port() == own.effective_port()
+            }
+            _ => false,
+        };
+        if same_origin {
+            Ok(())
+        } else {
+            Err(CsrfError::OriginNotAllowed(origin))
+        }
+    }
+}
+
+/// Create a new instance.
+pub fn new() -> CsrfProtection {
+    CsrfProtection::new()
+}
+
+fn is_safe(method: &Method) -> bool {
+    [Method::GET, Method::HEAD, Method::OPTIONS, Method::TRACE].contains(method)
+}
+
+/// The lowercase origin from the `Origin` header, or derived from the `Referer` header.
+fn request_origin(headers: &HeaderMap) -> Option<String> {
+    let header = |name| {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+    };
+    if let Some(origin) = header(ORIGIN) {
+        return Some(origin.trim_end_matches('/').to_ascii_lowercase());
+    }
+    let referer: Uri = header(REFERER)?.parse().ok()?;
+    match (referer.scheme_str(), referer.authority_part()) {
+        (Some(scheme), Some(authority)) => {
+            Some(format!("{}://{}", scheme, authority).to_ascii_lowercase())
+        }
+        _ => Some("null".to_string()),
+    }
+}
+
+fn check_token(
+    double_submit: &DoubleSubmit,
+    headers: &HeaderMap,
+    session: &str,
+) -> Result<(), CsrfError> {
+    let header = headers
+        .get(double_submit.header_name.as_str())
+        .and_then(|value| value.to_str().ok())
+        .map(str::trim);
+    let cookie = headers
+        .get_all(COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(';'))
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|(name, _)| *name == double_submit.cookie_name)
+        .map(|(_, value)| value.trim());
+
+    match (header, cookie) {
+        (Some(header), Some(cookie)) if !header.is_empty() && !cookie.is_empty() => {
+            if constant_time_eq(header.as_bytes(), cookie.as_bytes())
+                && double_submit.verify(session, cookie)
+            {
+                Ok(())
+            } else {
+                Err(CsrfError::InvalidToken)
+            }
+        }
+        _ => Err(CsrfError::MissingToken),
+    }
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
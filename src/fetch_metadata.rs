@@ -0,0 +1,247 @@
+//! Reject cross-site requests using Fetch Metadata request headers.
+//!
+//! Browsers describe the context of every request with the `Sec-Fetch-Site`, `Sec-Fetch-Mode`
+//! and `Sec-Fetch-Dest` headers. A [`ResourceIsolationPolicy`] uses them to reject cross-site
+//! requests before they're handled, which blocks CSRF, XSSI and cross-site leaks. It allows:
+//!
+//! - requests from browsers that don't send Fetch Metadata,
+//! - same-origin and same-site requests, and requests the user started directly,
+//! - top-level navigations with a safe method, which keeps links to the site working,
+//! - requests to exempt paths, e.g. endpoints meant to be loaded cross-site.
+//!
+//! Everything else is denied with a [`DenyReason`].
+//!
+//! [web.dev | Fetch Metadata](https://web.dev/articles/fetch-metadata)
+//!
+//! # Examples
+//!
+//! ```
+//! use armor::fetch_metadata::DenyReason;
+//!
+//! let mut policy = armor::fetch_metadata::new();
+//! policy.exempt_path("/embed");
+//!
+//! let req = http::Request::post("/account/delete")
+//!     .header("Sec-Fetch-Site", "cross-site")
+//!     .header("Sec-Fetch-Mode", "no-cors")
+//!     .header("Sec-Fetch-Dest", "image")
+//!     .body(())
+//!     .unwrap();
+//! assert_eq!(policy.check_request(&req), Err(DenyReason::CrossSiteRequest));
+//!
+//! let req = http::Request::get("/embed/widget")
+//!     .header("Sec-Fetch-Site", "cross-site")
+//!     .body(())
+//!     .unwrap();
+//! assert_eq!(policy.check_request(&req), Ok(()));
+//! ```
+
+use http::{HeaderMap, Method, Request, Uri};
+use std::error::Error;
+use std::fmt;
+
+/// Why a request was denied.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DenyReason {
+    /// A cross-site request that isn't a navigation, e.g. a `fetch()` or an `<img>`.
+    CrossSiteRequest,
+    /// A cross-site navigation with an unsafe method, e.g. a form `POST`.
+    UnsafeNavigation,
+    /// A cross-site navigation loading the page into an `<object>` or `<embed>`.
+    EmbedNavigation,
+    /// A same-site request, when same-site requests aren't allowed.
+    SameSiteRequest,
+}
+
+impl DenyReason {
+    /// A short, stable code for logs and metrics.
+    pub fn code(&self) -> &'static str {
+        match *self {
+            DenyReason::CrossSiteRequest => "cross-site-request",
+            DenyReason::UnsafeNavigation => "unsafe-navigation",
+            DenyReason::EmbedNavigation => "embed-navigation",
+            DenyReason::SameSiteRequest => "same-site-request",
+        }
+    }
+}
+
+impl AsRef<str> for DenyReason {
+    fn as_ref(&self) -> &str {
+        self.code()
+    }
+}
+
+impl fmt::Display for DenyReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let message = match *self {
+            DenyReason::CrossSiteRequest => "cross-site request denied",
+            DenyReason::UnsafeNavigation => "cross-site navigation with an unsafe method denied",
+            DenyReason::EmbedNavigation => "cross-site navigation into an object or embed denied",
+            DenyReason::SameSiteRequest => "same-site request denied",
+        };
+        f.write_str(message)
+    }
+}
+
+impl Error for DenyReason {}
+
+/// Build the resource isolation policy
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResourceIsolationPolicy {
+    allow_same_site: bool,
+    exempt_paths: Vec<Vec<String>>,
+}
+
+impl Default for ResourceIsolationPolicy {
+    /// Allows same-site requests and has no exempt paths.
+    fn default() -> Self {
+        Self {
+            allow_same_site: true,
+            exempt_paths: Vec::new(),
+        }
+    }
+}
+
+impl ResourceIsolationPolicy {
+    /// Create a new instance.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set whether to allow requests from other origins on the same site, e.g. from a
+    /// sibling subdomain.
+    pub fn allow_same_site(&mut self, allow: bool) -> &mut Self {
+        self.allow_same_site = allow;
+        self
+    }
+
+    /// Allow every request to a path and the paths below it.
+    ///
+    /// `/embed` exempts `/embed` and `/embed/widget`, but not `/embedded`. Paths are compared
+    /// after decoding percent-encoded bytes and resolving `.` and `..` segments, so
+    /// `/embed/../admin` isn't exempt.
+    pub fn exempt_path<T: AsRef<str>>(&mut self, path: T) -> &mut Self {
+        let path = path.as_ref().trim_end_matches('/');
+        let path = format!("/{}", path.trim_start_matches('/'));
+        if let Some(segments) = segments(&path) {
+            self.exempt_paths.push(segments);
+        }
+        self
+    }
+
+    /// Check whether a path is exempt from the policy.
+    ///
+    /// ## Examples
+    /// ```
+    /// let mut policy = armor::fetch_metadata::new();
+    /// policy.exempt_path("/public");
+    ///
+    /// assert!(policy.is_exempt("/public/logo.png"));
+    /// assert!(!policy.is_exempt("/public/../admin"));
+    /// assert!(!policy.is_exempt("/public%2f..%2fadmin"));
+    /// assert!(!policy.is_exempt("//public"));
+    /// ```
+    pub fn is_exempt(&self, path: &str) -> bool {
+        let path = match segments(path) {
+            Some(path) => path,
+            None => return false,
+        };
+        self.exempt_paths
+            .iter()
+            .any(|exempt| path.starts_with(exempt))
+    }
+
+    /// Check a request.
+    pub fn check_request<B>(&self, req: &Request<B>) -> Result<(), DenyReason> {
+        self.check(req.method(), req.uri(), req.headers())
+    }
+
+    /// Check a request from its method, URI and headers.
+    pub fn check(&self, method: &Method, uri: &Uri, headers: &HeaderMap) -> Result<(), DenyReason> {
+        if self.is_exempt(uri.path()) {
+            return Ok(());
+        }
+        let header = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(|value| value.trim().to_ascii_lowercase())
+        };
+
+        let site = match header("Sec-Fetch-Site") {
+            Some(site) => site,
+            None => return Ok(()),
+        };
+        match site.as_str() {
+            "same-origin" | "none" => return Ok(()),
+            "same-site" if self.allow_same_site => return Ok(()),
+            "same-site" => return Err(DenyReason::SameSiteRequest),
+            _ => {}
+        }
+
+        if header("Sec-Fetch-Mode").as_deref() != Some("navigate") {
+            return Err(DenyReason::CrossSiteRequest);
+        }
+        if method != Method::GET && method != Method::HEAD {
+            return Err(DenyReason::UnsafeNavigation);
+        }
+        match header("Sec-Fetch-Dest").as_deref() {
+            Some("object") | Some("embed") => Err(DenyReason::EmbedNavigation),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Create a new instance.
+pub fn new() -> ResourceIsolationPolicy {
+    ResourceIsolationPolicy::new()
+}
+
+/// Split an absolute path into segments the way a server resolves it before routing: decode
+/// percent-encoded bytes, treat `\` as a separator and resolve `.` and `..` segments.
+///
+/// Empty segments are kept, so `//embed` doesn't match `/embed`. Returns `None` for paths that
+/// aren't absolute or don't decode to UTF-8.
+fn segments(path: &str) -> Option<Vec<String>> {
+    let path = path.strip_prefix('/')?;
+    let path = String::from_utf8(percent_decode(path)).ok()?;
+    let mut segments: Vec<String> = Vec::new();
+    if path.is_empty() {
+        return Some(segments);
+    }
+    for segment in path.split(['/', '\\']) {
+        match segment {
+            "." => {}
+            ".." => {
+                segments.pop();
+            }
+            segment => segments.push(segment.to_string()),
+        }
+    }
+    Some(segments)
+}
+
+/// Decode `%XX` escapes, keeping malformed ones as they are.
+fn percent_decode(input: &str) -> Vec<u8> {
+    let bytes = input.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        let escape = bytes
+            .get(index + 1..index + 3)
+            .filter(|hex| bytes[index] == b'%' && hex.iter().all(u8::is_ascii_hexdigit))
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escape {
+            Some(byte) => {
+                decoded.push(byte);
+                index += 3;
+            }
+            None => {
+                decoded.push(bytes[index]);
+                index += 1;
+            }
+        }
+    }
+    decoded
+}
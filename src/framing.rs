@@ -0,0 +1,249 @@
+//! Keep `X-Frame-Options` and CSP `frame-ancestors` consistent.
+//!
+//! Browsers that support CSP ignore `X-Frame-Options` when a policy has a `frame-ancestors`
+//! directive, while older browsers only understand `X-Frame-Options`. When the two disagree,
+//! a page can be framed by a different set of sites depending on the browser. A
+//! [`FramingPolicy`] derives both headers from a single list of allowed ancestors, and
+//! [`check`] detects conflicts between headers that were set by hand.
+//!
+//! [MDN | frame-ancestors](https://developer.mozilla.org/en-US/docs/Web/HTTP/Headers/Content-Security-Policy/frame-ancestors)
+//!
+//! # Examples
+//!
+//! ```
+//! use armor::framing::FramingPolicy;
+//!
+//! let mut policy = FramingPolicy::same_origin();
+//! policy.allow("https://partner.example").unwrap();
+//!
+//! let mut headers = http::HeaderMap::new();
+//! policy.apply(&mut headers);
+//!
+//! // `X-Frame-Options` can't express a list of origins, so it's omitted.
+//! assert_eq!(headers.get("X-Frame-Options"), None);
+//! assert_eq!(headers["Content-Security-Policy"], "frame-ancestors 'self' https://partner.example");
+//! ```
+
+use crate::csp::{self, ContentSecurityPolicy, Source};
+use crate::{FrameOptions, ParseError};
+use http::HeaderMap;
+use std::error::Error;
+use std::fmt;
+
+/// Define which sites may embed the page in a frame.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FramingPolicy {
+    ancestors: Vec<String>,
+}
+
+impl Default for FramingPolicy {
+    /// Only allows the page to be framed by pages from the same origin.
+    fn default() -> Self {
+        Self::same_origin()
+    }
+}
+
+impl FramingPolicy {
+    /// Don't allow the page to be framed at all.
+    pub fn deny() -> Self {
+        Self {
+            ancestors: Vec::new(),
+        }
+    }
+
+    /// Only allow the page to be framed by pages from the same origin.
+    pub fn same_origin() -> Self {
+        Self {
+            ancestors: vec![Source::SameOrigin.as_ref().to_string()],
+        }
+    }
+
+    /// Allow the page to be framed by a source, e.g. an origin, a scheme like `https:` or
+    /// `'self'`.
+    ///
+    /// Returns an error when `source` isn't a single source expression `frame-ancestors`
+    /// accepts, so it can't add other directives to the policies it's merged into.
+    pub fn allow<T: AsRef<str>>(&mut self, source: T) -> Result<&mut Self, ParseError> {
+        let source = source.as_ref();
+        let keyword = source.starts_with('\'')
+            && source != Source::SameOrigin.as_ref()
+            && source != Source::None.as_ref();
+        if keyword || !csp::is_source_expression(source) {
+            return Err(ParseError::new(format!(
+                "invalid frame-ancestors source `{}`",
+                source.escape_debug()
+            )));
+        }
+        let source = source.to_string();
+        if source != Source::None.as_ref() && !self.ancestors.contains(&source) {
+            self.ancestors.push(source);
+        }
+        Ok(self)
+    }
+
+    /// The allowed ancestors. An empty list means framing is denied.
+    pub fn ancestors(&self) -> &[String] {
+        &self.ancestors
+    }
+
+    /// The `X-Frame-Options` equivalent of this policy, if it can be expressed.
+    ///
+    /// Only denying framing entirely or allowing the same origin can be expressed; the
+    /// obsolete `allow-from` value isn't supported by any current browser.
+    pub fn frame_options(&self) -> Option<FrameOptions> {
+        match self.ancestors.as_slice() {
+            [] => Some(FrameOptions::Deny),
+            [source] if source == Source::SameOrigin.as_ref() => Some(FrameOptions::SameOrigin),
+            _ => None,
+        }
+    }
+
+    /// Set the `frame-ancestors` directive of a `Content-Security-Policy`, replacing any
+    /// existing value.
+    pub fn apply_to_csp(&self, policy: &mut ContentSecurityPolicy) {
+        policy.remove_directive("frame-ancestors");
+        if self.ancestors.is_empty() {
+            policy.frame_ancestors(Source::None);
+        }
+        for source in &self.ancestors {
+            policy.frame_ancestors(source);
+        }
+    }
+
+    /// Sets `X-Frame-Options` and the `frame-ancestors` directive of every enforced
+    /// `Content-Security-Policy` header.
+    ///
+    /// Other directives of existing policies are kept. When there is no enforced policy, one
+    /// containing only the `frame-ancestors` directive is added. When `X-Frame-Options` can't
+    /// express the policy an existing header is removed.
+    ///
+    /// ## Examples
+    /// ```
+    /// let mut headers = http::HeaderMap::new();
+    /// headers.insert("Content-Security-Policy", "default-src 'self'".parse().unwrap());
+    /// armor::framing::FramingPolicy::deny().apply(&mut headers);
+    ///
+    /// assert_eq!(headers["X-Frame-Options"], "deny");
+    /// assert_eq!(headers["Content-Security-Policy"], "default-src 'self'; frame-ancestors 'none'");
+    /// ```
+    pub fn apply(&self, headers: &mut HeaderMap) {
+        let existing: Vec<_> = headers
+            .get_all("Content-Security-Policy")
+            .iter()
+            .cloned()
+            .collect();
+        headers.remove("Content-Security-Policy");
+        let mut merged = false;
+        for value in existing {
+            let value = match value.to_str() {
+                Ok(policy) => {
+                    let mut policy = ContentSecurityPolicy::parse(policy);
+                    self.apply_to_csp(&mut policy);
+                    merged = true;
+                    policy.value().parse().unwrap()
+                }
+                // Keep values that can't be parsed as they are.
+                Err(_) => value,
+            };
+            headers.append("Content-Security-Policy", value);
+        }
+        if !merged {
+            let mut policy = csp::new();
+            self.apply_to_csp(&mut policy);
+            headers.append("Content-Security-Policy", policy.value().parse().unwrap());
+        }
+        match self.frame_options() {
+            Some(guard) => crate::frameguard(headers, Some(guard)),
+            None => {
+                headers.remove("X-Frame-Options");
+            }
+        }
+    }
+}
+
+/// `X-Frame-Options` and CSP `frame-ancestors` allow different sets of sites to frame a page.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FramingConflict {
+    frame_options: String,
+    frame_ancestors: Vec<String>,
+}
+
+impl FramingConflict {
+    /// The `X-Frame-Options` value.
+    pub fn frame_options(&self) -> &str {
+        &self.frame_options
+    }
+
+    /// The `frame-ancestors` sources.
+    pub fn frame_ancestors(&self) -> &[String] {
+        &self.frame_ancestors
+    }
+}
+
+impl fmt::Display for FramingConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "`X-Frame-Options: {}` doesn't match `frame-ancestors {}`",
+            self.frame_options,
+            self.frame_ancestors.join(" ")
+        )
+    }
+}
+
+impl Error for FramingConflict {}
+
+/// Check that `X-Frame-Options` and the `frame-ancestors` directive of any enforced
+/// `Content-Security-Policy` allow the same sites.
+///
+/// Headers that only set one of the two are consistent.
+///
+/// ## Examples
+/// ```
+/// let mut headers = http::HeaderMap::new();
+/// armor::frameguard(&mut headers, Some(armor::FrameOptions::SameOrigin));
+/// assert!(armor::framing::check(&headers).is_ok());
+///
+/// let mut policy = armor::csp::new();
+/// policy.frame_ancestors("https://partner.example");
+/// policy.apply(&mut headers);
+/// assert!(armor::framing::check(&headers).is_err());
+/// ```
+pub fn check(headers: &HeaderMap) -> Result<(), FramingConflict> {
+    let frame_options = match headers.get("X-Frame-Options") {
+        Some(value) => value.to_str().unwrap_or("").trim().to_ascii_lowercase(),
+        None => return Ok(()),
+    };
+    for value in headers.get_all("Content-Security-Policy") {
+        let value = value.to_str().unwrap_or("");
+        let ancestors = match frame_ancestors(value) {
+            Some(ancestors) => ancestors,
+            None => continue,
+        };
+        let consistent = match frame_options.as_str() {
+            "deny" => ancestors.is_empty() || ancestors == ["'none'"],
+            "sameorigin" => ancestors == ["'self'"],
+            _ => false,
+        };
+        if !consistent {
+            return Err(FramingConflict {
+                frame_options,
+                frame_ancestors: ancestors,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Extract the first `frame-ancestors` directive from a serialized policy.
+fn frame_ancestors(policy: &str) -> Option<Vec<String>> {
+    policy.split(';').find_map(|directive| {
+        let mut tokens = directive.split_whitespace();
+        match tokens.next() {
+            Some(name) if name.eq_ignore_ascii_case("frame-ancestors") => {
+                Some(tokens.map(str::to_ascii_lowercase).collect())
+            }
+            _ => None,
+        }
+    })
+}
@@ -0,0 +1,899 @@
+//! Analyze browser captures in the HTTP Archive (HAR) format.
+//!
+//! A HAR file records every request a browser made while loading one or more pages, along with
+//! the response headers. [`analyze`] audits the security headers of each page's documents and
+//! replays its subresource loads against the page's Content Security Policy, all offline.
+//!
+//! [read more](https://w3c.github.io/web-performance/specs/HAR/Overview.html)
+//!
+//! ## Examples
+//! ```
+//! let har: armor::har::Har = r#"{"log": {
+//!     "pages": [{"id": "page_1", "title": "Home"}],
+//!     "entries": [
+//!         {"pageref": "page_1",
+//!          "request": {"method": "GET", "url": "https://example.com/"},
+//!          "response": {"status": 200, "headers": [
+//!              {"name": "Content-Type", "value": "text/html"},
+//!              {"name": "Content-Security-Policy", "value": "default-src 'self'"}
+//!          ]}},
+//!         {"pageref": "page_1",
+//!          "request": {"method": "GET", "url": "https://cdn.example/app.js"},
+//!          "response": {"status": 200, "headers": [
+//!              {"name": "Content-Type", "value": "text/javascript"}
+//!          ]}}
+//!     ]
+//! }}"#.parse().unwrap();
+//!
+//! let reports = armor::har::analyze(&har);
+//! assert_eq!(reports[0].title, "Home");
+//! assert_eq!(reports[0].blocked[0].url, "https://cdn.example/app.js");
+//! assert_eq!(reports[0].blocked[0].directive, "script-src");
+//! ```
+
+use crate::csp::{self, ContentSecurityPolicy};
+use crate::url::Url;
+use crate::{AuditReport, RequestContext};
+use http::header::{HeaderName, HeaderValue, CONTENT_TYPE, LOCATION};
+use http::HeaderMap;
+use serde::Deserialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+/// An error returned when a HAR file can't be read.
+#[derive(Debug)]
+pub enum HarError {
+    /// The file isn't valid JSON or doesn't follow the HAR structure.
+    Json(serde_json::Error),
+}
+
+impl fmt::Display for HarError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            HarError::Json(ref err) => write!(f, "invalid HAR file: {}", err),
+        }
+    }
+}
+
+impl Error for HarError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            HarError::Json(ref err) => Some(err),
+        }
+    }
+}
+
+/// What a request loaded, as far as CSP is concerned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ResourceKind {
+    /// A top-level document.
+    Document,
+    /// A document loaded in a frame.
+    Frame,
+    /// A script.
+    Script,
+    /// A stylesheet.
+    Style,
+    /// An image.
+    Image,
+    /// A font.
+    Font,
+    /// Audio, video or text tracks.
+    Media,
+    /// A fetch, XHR, WebSocket or EventSource connection.
+    Connect,
+    /// A worker script.
+    Worker,
+    /// A web app manifest.
+    Manifest,
+    /// Anything else.
+    Other,
+}
+
+impl ResourceKind {
+    /// The fetch directive that governs the resource, if any.
+    pub fn directive(self) -> Option<&'static str> {
+        match self {
+            ResourceKind::Document | ResourceKind::Other => None,
+            ResourceKind::Frame => Some("frame-src"),
+            ResourceKind::Script => Some("script-src"),
+            ResourceKind::Style => Some("style-src"),
+            ResourceKind::Image => Some("img-src"),
+            ResourceKind::Font => Some("font-src"),
+            ResourceKind::Media => Some("media-src"),
+            ResourceKind::Connect => Some("connect-src"),
+            ResourceKind::Worker => Some("worker-src"),
+            ResourceKind::Manifest => Some("manifest-src"),
+        }
+    }
+
+    /// Classify a resource by the `_resourceType` browsers record, falling back to its
+    /// content type and URL.
+    fn classify(resource_type: Option<&str>, mime_type: &str, url: &str) -> Self {
+        let kind = match resource_type.map(str::to_ascii_lowercase).as_deref() {
+            Some("document") => ResourceKind::Document,
+            Some("stylesheet") => ResourceKind::Style,
+            Some("script") => ResourceKind::Script,
+            Some("image") | Some("imageset") => ResourceKind::Image,
+            Some("font") => ResourceKind::Font,
+            Some("media") | Some("texttrack") => ResourceKind::Media,
+            Some("xhr") | Some("fetch") | Some("eventsource") | Some("websocket")
+            | Some("ping") | Some("beacon") => ResourceKind::Connect,
+            Some("worker") | Some("sharedworker") | Some("serviceworker") => ResourceKind::Worker,
+            Some("manifest") => ResourceKind::Manifest,
+            _ => ResourceKind::Other,
+        };
+        if kind != ResourceKind::Other {
+            return kind;
+        }
+        if url.starts_with("ws:") || url.starts_with("wss:") {
+            return ResourceKind::Connect;
+        }
+        let mime_type = mime_type.split(';').next().unwrap_or("").trim();
+        let mime_type = mime_type.to_ascii_lowercase();
+        match mime_type.as_str() {
+            "text/html" | "application/xhtml+xml" => ResourceKind::Document,
+            "text/css" => ResourceKind::Style,
+            "application/manifest+json" => ResourceKind::Manifest,
+            "application/json" | "text/event-stream" => ResourceKind::Connect,
+            "text/vtt" => ResourceKind::Media,
+            mime if mime.contains("javascript") || mime.contains("ecmascript") => {
+                ResourceKind::Script
+            }
+            mime if mime.starts_with("image/") => ResourceKind::Image,
+            mime if mime.starts_with("font/") || mime.contains("font-") => ResourceKind::Font,
+            mime if mime.starts_with("audio/") || mime.starts_with("video/") => ResourceKind::Media,
+            _ => ResourceKind::Other,
+        }
+    }
+}
+
+impl AsRef<str> for ResourceKind {
+    fn as_ref(&self) -> &str {
+        match *self {
+            ResourceKind::Document => "document",
+            ResourceKind::Frame => "frame",
+            ResourceKind::Script => "script",
+            ResourceKind::Style => "style",
+            ResourceKind::Image => "image",
+            ResourceKind::Font => "font",
+            ResourceKind::Media => "media",
+            ResourceKind::Connect => "connect",
+            ResourceKind::Worker => "worker",
+            ResourceKind::Manifest => "manifest",
+            ResourceKind::Other => "other",
+        }
+    }
+}
+
+impl fmt::Display for ResourceKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_ref())
+    }
+}
+
+#[derive(Deserialize)]
+struct RawHar {
+    log: RawLog,
+}
+
+#[derive(Deserialize)]
+struct RawLog {
+    #[serde(default)]
+    pages: Vec<RawPage>,
+    entries: Vec<RawEntry>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct RawPage {
+    id: String,
+    #[serde(default)]
+    title: String,
+}
+
+#[derive(Deserialize)]
+struct RawEntry {
+    pageref: Option<String>,
+    request: RawRequest,
+    response: RawResponse,
+    #[serde(rename = "_resourceType")]
+    resource_type: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct RawRequest {
+    #[serde(default)]
+    method: String,
+    url: String,
+}
+
+#[derive(Deserialize)]
+struct RawResponse {
+    #[serde(default)]
+    status: u16,
+    #[serde(default)]
+    headers: Vec<RawHeader>,
+    #[serde(default)]
+    content: RawContent,
+    #[serde(default, rename = "redirectURL")]
+    redirect_url: String,
+}
+
+#[derive(Deserialize)]
+struct RawHeader {
+    name: String,
+    value: String,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawContent {
+    mime_type: Option<String>,
+}
+
+/// A request recorded in a HAR file.
+#[derive(Debug, Clone)]
+pub struct Entry {
+    page: Option<String>,
+    method: String,
+    url: String,
+    status: u16,
+    headers: HeaderMap,
+    kind: ResourceKind,
+    redirect_url: Option<String>,
+    redirected: bool,
+}
+
+impl Entry {
+    /// The id of the page the request belongs to.
+    pub fn page(&self) -> Option<&str> {
+        self.page.as_deref()
+    }
+
+    /// The request method.
+    pub fn method(&self) -> &str {
+        &self.method
+    }
+
+    /// The request URL.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// The response status, `0` for requests that failed or were blocked.
+    pub fn status(&self) -> u16 {
+        self.status
+    }
+
+    /// The response headers. Headers with invalid names or values are skipped.
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    /// What the request loaded.
+    pub fn kind(&self) -> ResourceKind {
+        self.kind
+    }
+
+    /// Where a redirect response points, from the entry's `redirectURL` or its `Location`
+    /// header, resolved against the request URL.
+    pub fn redirect_url(&self) -> Option<&str> {
+        self.redirect_url.as_deref()
+    }
+
+    /// Whether the request follows a redirect, that is an earlier entry redirected to its URL.
+    pub fn is_redirected(&self) -> bool {
+        self.redirected
+    }
+
+    fn is_redirect(&self) -> bool {
+        (300..400).contains(&self.status)
+    }
+}
+
+impl From<RawEntry> for Entry {
+    fn from(raw: RawEntry) -> Self {
+        let mut headers = HeaderMap::new();
+        for header in &raw.response.headers {
+            let name = HeaderName::from_bytes(header.name.as_bytes());
+            let value = HeaderValue::from_str(&header.value);
+            if let (Ok(name), Ok(value)) = (name, value) {
+                headers.append(name, value);
+            }
+        }
+        let mime_type = raw.response.content.mime_type.or_else(|| {
+            headers
+                .get(CONTENT_TYPE)
+                .and_then(|value| value.to_str().ok())
+                .map(String::from)
+        });
+        let kind = ResourceKind::classify(
+            raw.resource_type.as_deref(),
+            mime_type.as_deref().unwrap_or(""),
+            &raw.request.url,
+        );
+        let status = raw.response.status;
+        let url = raw.request.url;
+        let redirect_url = Some(raw.response.redirect_url)
+            .filter(|location| !location.is_empty())
+            .or_else(|| {
+                headers
+                    .get(LOCATION)
+                    .and_then(|value| value.to_str().ok())
+                    .map(String::from)
+            })
+            .filter(|_| (300..400).contains(&status))
+            .map(|location| resolve(&url, &location));
+        Self {
+            page: raw.pageref,
+            method: raw.request.method,
+            url,
+            status,
+            headers,
+            kind,
+            redirect_url,
+            redirected: false,
+        }
+    }
+}
+
+/// Resolve a `Location` against the URL of the request it answered.
+fn resolve(base: &str, location: &str) -> String {
+    let has_scheme = location.split_once(':').is_some_and(|(scheme, _)| {
+        scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+            && scheme
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c))
+    });
+    if has_scheme {
+        return location.to_string();
+    }
+    let (scheme, rest) = match base.split_once("://") {
+        Some(parts) => parts,
+        None => return location.to_string(),
+    };
+    if location.starts_with("//") {
+        return format!("{}:{}", scheme, location);
+    }
+    let authority = rest.find(['/', '?', '#']).unwrap_or(rest.len());
+    let origin = &base[..scheme.len() + 3 + authority];
+    if location.starts_with('/') {
+        return format!("{}{}", origin, location);
+    }
+    let path = &rest[authority..];
+    let path = &path[..path.find(['?', '#']).unwrap_or(path.len())];
+    let directory = &path[..path.rfind('/').map_or(0, |index| index + 1)];
+    match location.chars().next() {
+        Some('?') | Some('#') | None => format!("{}{}{}", origin, path, location),
+        _ if directory.is_empty() => format!("{}/{}", origin, location),
+        _ => format!("{}{}{}", origin, directory, location),
+    }
+}
+
+/// A parsed HAR file.
+#[derive(Debug, Clone)]
+pub struct Har {
+    pages: Vec<RawPage>,
+    entries: Vec<Entry>,
+}
+
+impl Har {
+    /// Parse a HAR file.
+    pub fn parse(json: &str) -> Result<Self, HarError> {
+        let raw: RawHar = serde_json::from_str(json).map_err(HarError::Json)?;
+        let typed: Vec<bool> = raw
+            .log
+            .entries
+            .iter()
+            .map(|entry| entry.resource_type.is_some())
+            .collect();
+        let mut entries: Vec<Entry> = raw.log.entries.into_iter().map(Entry::from).collect();
+        let mut followed = vec![None; entries.len()];
+        for index in 0..entries.len() {
+            let target = match entries[index].redirect_url.clone() {
+                Some(target) => target,
+                None => continue,
+            };
+            let next = entries[index + 1..]
+                .iter()
+                .position(|entry| !entry.redirected && entry.url == target)
+                .map(|offset| index + 1 + offset);
+            if let Some(next) = next {
+                entries[next].redirected = true;
+                followed[index] = Some(next);
+            }
+        }
+        // A redirect's content type says nothing about the request, so without a recorded
+        // resource type it loads whatever the request it leads to loads.
+        for index in (0..entries.len()).rev() {
+            if let (false, Some(next)) = (typed[index], followed[index]) {
+                entries[index].kind = entries[next].kind;
+            }
+        }
+        Ok(Self {
+            pages: raw.log.pages,
+            entries,
+        })
+    }
+
+    /// Every recorded request, in order.
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries
+    }
+
+    /// The recorded pages with their requests, in order. Requests without a page reference are
+    /// grouped in a page with an empty id, which is the only page of captures that don't record
+    /// pages.
+    pub fn pages(&self) -> Vec<Page<'_>> {
+        let mut pages: Vec<Page<'_>> = self
+            .pages
+            .iter()
+            .map(|page| Page {
+                id: &page.id,
+                title: &page.title,
+                entries: Vec::new(),
+            })
+            .collect();
+        for entry in &self.entries {
+            let id = entry.page().unwrap_or("");
+            match pages.iter_mut().find(|page| page.id == id) {
+                Some(page) => page.entries.push(entry),
+                None => pages.push(Page {
+                    id,
+                    title: "",
+                    entries: vec![entry],
+                }),
+            }
+        }
+        pages.retain(|page| !page.entries.is_empty());
+        pages
+    }
+}
+
+impl FromStr for Har {
+    type Err = HarError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+/// A page recorded in a HAR file.
+#[derive(Debug, Clone)]
+pub struct Page<'a> {
+    id: &'a str,
+    title: &'a str,
+    entries: Vec<&'a Entry>,
+}
+
+impl<'a> Page<'a> {
+    /// The page id.
+    pub fn id(&self) -> &'a str {
+        self.id
+    }
+
+    /// The page title.
+    pub fn title(&self) -> &'a str {
+        self.title
+    }
+
+    /// The requests made by the page, in order.
+    pub fn entries(&self) -> &[&'a Entry] {
+        &self.entries
+    }
+
+    /// The page's main document: the first document that isn't a redirect.
+    pub fn document(&self) -> Option<&'a Entry> {
+        self.entries
+            .iter()
+            .copied()
+            .find(|entry| entry.kind == ResourceKind::Document && !entry.is_redirect())
+    }
+
+    /// The resources the document loaded, with documents loaded after the main document
+    /// counted as frames. Redirects are included, since the policy checks the request that
+    /// was redirected as well as the one that follows it, see [`Entry::is_redirected`]. The
+    /// redirects that led to the main document are not.
+    pub fn resources(&self) -> Vec<(ResourceKind, &'a Entry)> {
+        let document = self.document();
+        let position = document.and_then(|document| {
+            self.entries
+                .iter()
+                .position(|entry| std::ptr::eq(*entry, document))
+        });
+        self.entries
+            .iter()
+            .copied()
+            .enumerate()
+            .filter(|(index, entry)| match position {
+                // Earlier documents are the redirects to the main document.
+                Some(position) => *index > position || entry.kind != ResourceKind::Document,
+                None => true,
+            })
+            .map(|(_, entry)| entry)
+            .map(|entry| match entry.kind {
+                ResourceKind::Document => (ResourceKind::Frame, entry),
+                kind => (kind, entry),
+            })
+            .collect()
+    }
+}
+
+/// A subresource load the page's policies would block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockedLoad {
+    /// The URL of the resource.
+    pub url: String,
+    /// What the resource is.
+    pub kind: ResourceKind,
+    /// The directive that blocks it.
+    pub directive: &'static str,
+}
+
+/// The result of analyzing one page of a HAR file.
+#[derive(Debug, Clone)]
+pub struct PageReport {
+    /// The page id.
+    pub id: String,
+    /// The page title.
+    pub title: String,
+    /// The URL of the main document, if the page has one.
+    pub url: Option<String>,
+    /// The policies of the main document. See [`ContentSecurityPolicy::from_headers`].
+    pub policies: Vec<ContentSecurityPolicy>,
+    /// The audit of every document response, the main document first.
+    pub documents: Vec<(String, AuditReport)>,
+    /// The subresource loads any of the policies blocks, or only reports if they're
+    /// report-only.
+    pub blocked: Vec<BlockedLoad>,
+}
+
+/// Audit the documents of every page in a HAR file and list the subresource loads blocked by
+/// each page's Content Security Policy.
+///
+/// Each document is audited with a [`RequestContext`] built from its URL. Subresources are
+/// checked against every policy of the page's main document, including those loaded by frames,
+/// since HAR files don't record which frame made a request.
+pub fn analyze(har: &Har) -> Vec<PageReport> {
+    har.pages().iter().map(analyze_page).collect()
+}
+
+fn analyze_page(page: &Page<'_>) -> PageReport {
+    let document = page.document();
+    let resources = page.resources();
+    let policies = document
+        .map(|document| ContentSecurityPolicy::from_headers(&document.headers))
+        .unwrap_or_default();
+
+    let documents = document
+        .into_iter()
+        .chain(
+            resources
+                .iter()
+                .filter(|(kind, _)| *kind == ResourceKind::Frame)
+                .map(|(_, entry)| *entry),
+        )
+        .map(|entry| {
+            (
+                entry.url.clone(),
+                crate::audit(&entry.headers, &context(&entry.url)),
+            )
+        })
+        .collect();
+
+    let blocked = match document {
+        Some(document) => resources
+            .iter()
+            .filter_map(|(kind, entry)| {
+                let directive = kind.directive()?;
+                let allowed = policies.iter().all(|policy| {
+                    policy.allows_request(directive, &entry.url, &document.url, entry.redirected)
+                });
+                if allowed {
+                    None
+                } else {
+                    Some(BlockedLoad {
+                        url: entry.url.clone(),
+                        kind: *kind,
+                        directive,
+                    })
+                }
+            })
+            .collect(),
+        None => Vec::new(),
+    };
+
+    PageReport {
+        id: page.id.to_string(),
+        title: page.title.to_string(),
+        url: document.map(|document| document.url.clone()),
+        policies,
+        documents,
+        blocked,
+    }
+}
+
+/// The context a document was requested in.
+fn context(url: &str) -> RequestContext {
+    let mut ctx = RequestContext::new();
+    if let Some(url) = Url::parse(url) {
+        ctx.scheme(&url.scheme);
+        if let Some(ref host) = url.host {
+            ctx.host(host);
+        }
+    }
+    ctx
+}
+
+/// Fetch directives a generated policy lists sources for.
+const GENERATED_DIRECTIVES: &[&str] = &[
+    "connect-src",
+    "font-src",
+    "frame-src",
+    "img-src",
+    "manifest-src",
+    "media-src",
+    "script-src",
+    "style-src",
+    "worker-src",
+];
+
+/// Derive a starter Content Security Policy from the resources pages actually loaded.
+///
+/// The generated policy starts from `default-src 'none'` and lists, for each fetch directive,
+/// the origins the pages loaded from: `'self'` for the page's own origin, `data:` and `blob:`
+/// for those URLs, and the origin of every other URL. When enough subdomains of one domain
+/// serve a directive, they're collapsed into a wildcard source like `https://*.example.com`.
+/// `object-src 'none'` and `base-uri 'none'` are always added.
+///
+/// Wildcards are never formed over a domain shared by unrelated sites, like `github.io`,
+/// `cloudfront.net` or `co.uk`, and can be turned off for a directive with
+/// [`PolicyGenerator::wildcards`]. Redirects count as loads of the directive that started
+/// them, so both the redirecting origin and its target are listed.
+///
+/// HAR files don't show inline scripts and styles, so pages that use them still need nonces
+/// or hashes, see [`csp::hash_source`].
+///
+/// ## Examples
+/// ```
+/// let har: armor::har::Har = r#"{"log": {"entries": [
+///     {"request": {"url": "https://example.com/"}, "_resourceType": "document",
+///      "response": {"status": 200, "headers": []}},
+///     {"request": {"url": "https://example.com/app.js"}, "_resourceType": "script",
+///      "response": {"status": 200, "headers": []}},
+///     {"request": {"url": "https://a.img.example/1.png"}, "_resourceType": "image",
+///      "response": {"status": 200, "headers": []}},
+///     {"request": {"url": "https://b.img.example/2.png"}, "_resourceType": "image",
+///      "response": {"status": 200, "headers": []}}
+/// ]}}"#.parse().unwrap();
+///
+/// let policy = armor::har::PolicyGenerator::new()
+///     .subdomain_threshold(2)
+///     .generate(&har.pages());
+/// assert_eq!(
+///     policy.value(),
+///     "base-uri 'none'; default-src 'none'; img-src https://*.img.example; object-src 'none'; script-src 'self'",
+/// );
+/// ```
+#[derive(Debug, Clone)]
+pub struct PolicyGenerator {
+    subdomain_threshold: usize,
+    exact_directives: BTreeSet<String>,
+}
+
+impl Default for PolicyGenerator {
+    fn default() -> Self {
+        Self {
+            subdomain_threshold: 3,
+            exact_directives: BTreeSet::new(),
+        }
+    }
+}
+
+/// Domains whose subdomains belong to unrelated sites: public suffixes with more than one
+/// label, and hosting platforms that give every customer a subdomain. A wildcard over one of
+/// these would allow anyone's content.
+const SHARED_DOMAINS: &[&str] = &[
+    "ac.uk",
+    "amazonaws.com",
+    "appspot.com",
+    "azureedge.net",
+    "azurewebsites.net",
+    "blob.core.windows.net",
+    "blogspot.com",
+    "cloudfront.net",
+    "cloudfunctions.net",
+    "co.in",
+    "co.jp",
+    "co.kr",
+    "co.nz",
+    "co.uk",
+    "co.za",
+    "com.au",
+    "com.br",
+    "com.cn",
+    "com.mx",
+    "com.tr",
+    "elasticbeanstalk.com",
+    "fastly.net",
+    "firebaseapp.com",
+    "fly.dev",
+    "github.io",
+    "githubusercontent.com",
+    "gitlab.io",
+    "glitch.me",
+    "gov.uk",
+    "herokuapp.com",
+    "myshopify.com",
+    "net.au",
+    "netlify.app",
+    "ngrok.io",
+    "onrender.com",
+    "org.au",
+    "org.uk",
+    "pages.dev",
+    "readthedocs.io",
+    "s3.amazonaws.com",
+    "vercel.app",
+    "web.app",
+    "wordpress.com",
+    "workers.dev",
+];
+
+/// A host source, before it's serialized.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct HostSource {
+    scheme: String,
+    host: String,
+    port: Option<u16>,
+}
+
+impl HostSource {
+    /// The domain a wildcard would cover: the host without its first label, if that still
+    /// has at least two labels and isn't shared between sites, see [`SHARED_DOMAINS`].
+    fn parent(&self) -> Option<&str> {
+        let is_ip = self.host.starts_with('[') || self.host.parse::<std::net::Ipv4Addr>().is_ok();
+        let (_, parent) = self.host.split_once('.')?;
+        let is_shared = SHARED_DOMAINS
+            .iter()
+            .any(|domain| parent.eq_ignore_ascii_case(domain));
+        if is_ip || is_shared || !parent.contains('.') {
+            return None;
+        }
+        Some(parent)
+    }
+
+    fn serialize(&self, host: &str) -> String {
+        match self.port {
+            Some(port) => format!("{}://{}:{}", self.scheme, host, port),
+            None => format!("{}://{}", self.scheme, host),
+        }
+    }
+}
+
+impl PolicyGenerator {
+    /// Create a new instance.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Collapse subdomains into a wildcard source once at least this many subdomains of the
+    /// same domain, scheme and port serve a directive. Defaults to 3, values below 2 are
+    /// treated as 2.
+    pub fn subdomain_threshold(&mut self, threshold: usize) -> &mut Self {
+        self.subdomain_threshold = threshold.max(2);
+        self
+    }
+
+    /// Whether subdomains may be collapsed into a wildcard source in `directive`. Defaults to
+    /// `true` for every directive; turn it off where a wildcard would allow too much, like
+    /// `script-src` on a domain that also serves user content.
+    pub fn wildcards(&mut self, directive: &str, enabled: bool) -> &mut Self {
+        let directive = directive.to_ascii_lowercase();
+        if enabled {
+            self.exact_directives.remove(&directive);
+        } else {
+            self.exact_directives.insert(directive);
+        }
+        self
+    }
+
+    /// Generate a policy allowing every resource the pages loaded. `'self'` refers to the
+    /// origin of each page's own document.
+    pub fn generate(&self, pages: &[Page<'_>]) -> ContentSecurityPolicy {
+        let mut keywords: BTreeMap<&str, BTreeSet<String>> = BTreeMap::new();
+        let mut hosts: BTreeMap<&str, BTreeSet<HostSource>> = BTreeMap::new();
+        for page in pages {
+            let document = page
+                .document()
+                .and_then(|document| Url::parse(&document.url));
+            for (kind, entry) in page.resources() {
+                let directive = match kind.directive() {
+                    Some(directive) if GENERATED_DIRECTIVES.contains(&directive) => directive,
+                    _ => continue,
+                };
+                let url = match Url::parse(&entry.url) {
+                    Some(url) => url,
+                    None => continue,
+                };
+                let is_self = document
+                    .as_ref()
+                    .is_some_and(|document| csp::source_matches("'self'", &url, document, false));
+                if is_self {
+                    keywords
+                        .entry(directive)
+                        .or_default()
+                        .insert("'self'".into());
+                    continue;
+                }
+                match (url.scheme.as_str(), url.host.as_ref()) {
+                    ("http", Some(host))
+                    | ("https", Some(host))
+                    | ("ws", Some(host))
+                    | ("wss", Some(host)) => {
+                        hosts.entry(directive).or_default().insert(HostSource {
+                            scheme: url.scheme.clone(),
+                            host: host.clone(),
+                            port: url.explicit_port(),
+                        });
+                    }
+                    ("data", None) | ("blob", _) | ("mediastream", None) => {
+                        let source = format!("{}:", url.scheme);
+                        keywords.entry(directive).or_default().insert(source);
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        let mut policy = ContentSecurityPolicy::new();
+        policy
+            .default_src("'none'")
+            .object_src("'none'")
+            .base_uri("'none'");
+        for directive in GENERATED_DIRECTIVES {
+            // Keywords sort before schemes, and both before hosts.
+            let mut sources: Vec<String> = keywords
+                .remove(directive)
+                .map(|keywords| keywords.into_iter().collect())
+                .unwrap_or_default();
+            if let Some(hosts) = hosts.remove(directive) {
+                if self.exact_directives.contains(*directive) {
+                    sources.extend(hosts.iter().map(|host| host.serialize(&host.host)));
+                } else {
+                    sources.extend(self.collapse(hosts));
+                }
+            }
+            for source in sources {
+                policy.insert_directive(directive, source);
+            }
+        }
+        policy
+    }
+
+    /// Serialize host sources, replacing subdomains with a wildcard above the threshold.
+    fn collapse(&self, hosts: BTreeSet<HostSource>) -> Vec<String> {
+        let mut groups: BTreeMap<(&str, &str, Option<u16>), usize> = BTreeMap::new();
+        for host in &hosts {
+            if let Some(parent) = host.parent() {
+                *groups.entry((&host.scheme, parent, host.port)).or_default() += 1;
+            }
+        }
+        let mut sources = BTreeSet::new();
+        for host in &hosts {
+            let source = match host.parent() {
+                Some(parent)
+                    if groups[&(host.scheme.as_str(), parent, host.port)]
+                        >= self.subdomain_threshold =>
+                {
+                    host.serialize(&format!("*.{}", parent))
+                }
+                _ => host.serialize(&host.host),
+            };
+            sources.insert(source);
+        }
+        sources.into_iter().collect()
+    }
+}
@@ -0,0 +1,335 @@
+//! Validate the `Host` a request was sent to.
+//!
+//! Applications often build absolute URLs, like password reset links or redirects, from the
+//! `Host` header. Clients control that header, so an unchecked value lets an attacker point
+//! those URLs at their own site or poison shared caches. A [`HostAllowlist`] checks the host
+//! from the request URI (the HTTP/2 `:authority`) or `Host` header against a list of allowed
+//! patterns, optionally honoring forwarded hosts set by a trusted proxy.
+//!
+//! [PortSwigger | HTTP Host header attacks](https://portswigger.net/web-security/host-header)
+//!
+//! # Examples
+//!
+//! ```
+//! use armor::host::HostRejection;
+//!
+//! let mut allowlist = armor::host::new();
+//! allowlist.allow("example.com").allow("*.example.com").allow("localhost:*");
+//!
+//! let req = http::Request::get("/").header("Host", "API.example.com").body(()).unwrap();
+//! assert_eq!(allowlist.check_request(&req), Ok("api.example.com".to_string()));
+//!
+//! let req = http::Request::get("/").header("Host", "evil.com").body(()).unwrap();
+//! assert_eq!(allowlist.check_request(&req), Err(HostRejection::NotAllowed("evil.com".into())));
+//! ```
+
+use crate::{ParseError, RequestContext};
+use http::header::HOST;
+use http::{HeaderMap, Request, Uri};
+use ipnet::IpNet;
+use std::error::Error;
+use std::fmt;
+use std::net::IpAddr;
+
+/// Why a request's host was rejected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HostRejection {
+    /// The request has no host.
+    Missing,
+    /// The host isn't a valid hostname, IP address or port.
+    Invalid(String),
+    /// The `:authority` and `Host` header name different hosts.
+    Mismatch {
+        /// The host from the request URI.
+        authority: String,
+        /// The `Host` header.
+        host: String,
+    },
+    /// The host isn't on the allowlist.
+    NotAllowed(String),
+}
+
+impl fmt::Display for HostRejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            HostRejection::Missing => f.write_str("request has no host"),
+            HostRejection::Invalid(ref host) => write!(f, "invalid host `{}`", host),
+            HostRejection::Mismatch {
+                ref authority,
+                ref host,
+            } => write!(
+                f,
+                "authority `{}` doesn't match `Host: {}`",
+                authority, host
+            ),
+            HostRejection::NotAllowed(ref host) => write!(f, "host `{}` is not allowed", host),
+        }
+    }
+}
+
+impl Error for HostRejection {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Port {
+    None,
+    Any,
+    Exact(u16),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Pattern {
+    host: String,
+    wildcard: bool,
+    port: Port,
+}
+
+impl Pattern {
+    fn matches(&self, host: &str, port: Option<u16>) -> bool {
+        let host_matches = if self.wildcard {
+            host.len() > self.host.len() + 1
+                && host.ends_with(&self.host)
+                && host[..host.len() - self.host.len()].ends_with('.')
+        } else {
+            host == self.host
+        };
+        let port_matches = match self.port {
+            Port::None => port.is_none(),
+            Port::Any => true,
+            Port::Exact(allowed) => port == Some(allowed),
+        };
+        host_matches && port_matches
+    }
+}
+
+/// Build the host allowlist
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HostAllowlist {
+    patterns: Vec<Pattern>,
+    trusted_proxies: Vec<IpNet>,
+}
+
+impl HostAllowlist {
+    /// Create a new instance that doesn't allow any host.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allow a host pattern.
+    ///
+    /// - `example.com` only allows that host without a port.
+    /// - `*.example.com` allows every subdomain of `example.com`, but not `example.com` itself.
+    /// - `example.com:8080` allows a single port, and `example.com:*` any port.
+    pub fn allow<T: AsRef<str>>(&mut self, pattern: T) -> &mut Self {
+        let pattern = pattern.as_ref().trim().to_ascii_lowercase();
+        let (host, port) = match pattern.rsplit_once(':') {
+            Some((host, "*")) => (host, Port::Any),
+            Some((host, port)) if !host.contains(':') || host.ends_with(']') => {
+                match port.parse() {
+                    Ok(port) => (host, Port::Exact(port)),
+                    Err(_) => (pattern.as_str(), Port::None),
+                }
+            }
+            _ => (pattern.as_str(), Port::None),
+        };
+        let (host, wildcard) = match host.strip_prefix("*.") {
+            Some(host) => (host, true),
+            None => (host, false),
+        };
+        self.patterns.push(Pattern {
+            host: normalize(host.trim_start_matches('[').trim_end_matches(']')),
+            wildcard,
+            port,
+        });
+        self
+    }
+
+    /// Validate the `Forwarded` or `X-Forwarded-Host` header instead of `Host` for requests
+    /// from a proxy address or network, e.g. `10.0.0.0/8` or `::1`.
+    ///
+    /// Forwarded hosts are only honored by [`HostAllowlist::check_from`] and
+    /// [`HostAllowlist::check_request_from`], when the peer is one of these proxies.
+    ///
+    /// ## Examples
+    /// ```
+    /// let mut allowlist = armor::host::new();
+    /// allowlist.allow("example.com").trust_proxy("10.0.0.0/8").unwrap();
+    ///
+    /// let req = http::Request::get("/")
+    ///     .header("Host", "internal:8080")
+    ///     .header("X-Forwarded-Host", "example.com")
+    ///     .body(())
+    ///     .unwrap();
+    /// assert!(allowlist.check_request_from(&req, "10.1.2.3".parse().unwrap()).is_ok());
+    /// assert!(allowlist.check_request_from(&req, "203.0.113.7".parse().unwrap()).is_err());
+    /// ```
+    pub fn trust_proxy<T: AsRef<str>>(&mut self, cidr: T) -> Result<&mut Self, ParseError> {
+        self.trusted_proxies.push(parse_cidr(cidr.as_ref())?);
+        Ok(self)
+    }
+
+    /// Whether an address is a trusted proxy.
+    pub fn is_trusted(&self, peer: IpAddr) -> bool {
+        is_trusted(&self.trusted_proxies, peer)
+    }
+
+    /// Check a request, returning the normalized host (with port, if any) on success.
+    ///
+    /// Forwarded hosts are ignored, since the peer isn't known.
+    pub fn check_request<B>(&self, req: &Request<B>) -> Result<String, HostRejection> {
+        self.check_host_of(req.uri(), req.headers(), false)
+    }
+
+    /// Check a request received from `peer`, honoring forwarded hosts if it's a trusted proxy.
+    pub fn check_request_from<B>(
+        &self,
+        req: &Request<B>,
+        peer: IpAddr,
+    ) -> Result<String, HostRejection> {
+        self.check_from(req.uri(), req.headers(), peer)
+    }
+
+    /// Check a request from its URI and headers, ignoring forwarded hosts.
+    pub fn check(&self, uri: &Uri, headers: &HeaderMap) -> Result<String, HostRejection> {
+        self.check_host_of(uri, headers, false)
+    }
+
+    /// Check a request received from `peer` from its URI and headers, honoring forwarded hosts
+    /// if it's a trusted proxy.
+    pub fn check_from(
+        &self,
+        uri: &Uri,
+        headers: &HeaderMap,
+        peer: IpAddr,
+    ) -> Result<String, HostRejection> {
+        self.check_host_of(uri, headers, self.is_trusted(peer))
+    }
+
+    fn check_host_of(
+        &self,
+        uri: &Uri,
+        headers: &HeaderMap,
+        trust_proxy: bool,
+    ) -> Result<String, HostRejection> {
+        let authority = uri.authority_part().map(|authority| authority.as_str());
+        let host = match headers.get(HOST) {
+            Some(value) => match value.to_str() {
+                Ok(value) => Some(value.trim()),
+                Err(_) => return Err(HostRejection::Invalid(lossy(value.as_bytes()))),
+            },
+            None => None,
+        };
+        if let (Some(authority), Some(host)) = (authority, host) {
+            if !authority.eq_ignore_ascii_case(host) {
+                return Err(HostRejection::Mismatch {
+                    authority: authority.to_string(),
+                    host: host.to_string(),
+                });
+            }
+        }
+
+        let mut ctx = RequestContext::from_parts(uri, headers);
+        ctx.trust_proxy(trust_proxy);
+        let host = ctx.effective_host().ok_or(HostRejection::Missing)?;
+        self.check_host(host)
+    }
+
+    /// Check a single host value, e.g. `example.com:8080`.
+    pub fn check_host(&self, host: &str) -> Result<String, HostRejection> {
+        let invalid = || HostRejection::Invalid(host.to_string());
+        let (name, port) = split_host(host).ok_or_else(invalid)?;
+        let name = normalize(name);
+        if !is_valid_name(&name) {
+            return Err(invalid());
+        }
+
+        let normalized = match (port, name.contains(':')) {
+            (Some(port), true) => format!("[{}]:{}", name, port),
+            (Some(port), false) => format!("{}:{}", name, port),
+            (None, true) => format!("[{}]", name),
+            (None, false) => name.clone(),
+        };
+        if self
+            .patterns
+            .iter()
+            .any(|pattern| pattern.matches(&name, port))
+        {
+            Ok(normalized)
+        } else {
+            Err(HostRejection::NotAllowed(normalized))
+        }
+    }
+}
+
+/// Create a new instance.
+pub fn new() -> HostAllowlist {
+    HostAllowlist::new()
+}
+
+/// Split a host into its name and port, unwrapping bracketed IPv6 literals.
+fn split_host(host: &str) -> Option<(&str, Option<u16>)> {
+    let (name, port) = if let Some(rest) = host.strip_prefix('[') {
+        let end = rest.find(']')?;
+        let port = &rest[end + 1..];
+        let port = match port.strip_prefix(':') {
+            Some(port) => Some(port),
+            None if port.is_empty() => None,
+            None => return None,
+        };
+        (&rest[..end], port)
+    } else {
+        match host.split_once(':') {
+            Some((name, port)) => (name, Some(port)),
+            None => (host, None),
+        }
+    };
+    let port = match port {
+        Some(port) => Some(port.parse().ok()?),
+        None => None,
+    };
+    if name.is_empty() {
+        return None;
+    }
+    Some((name, port))
+}
+
+fn normalize(host: &str) -> String {
+    host.trim_end_matches('.').to_ascii_lowercase()
+}
+
+fn is_valid_name(name: &str) -> bool {
+    if name.contains(':') {
+        return name.parse::<std::net::Ipv6Addr>().is_ok();
+    }
+    name.split('.').all(|label| {
+        !label.is_empty()
+            && label.len() <= 63
+            && label
+                .bytes()
+                .all(|byte| byte.is_ascii_alphanumeric() || byte == b'-' || byte == b'_')
+    })
+}
+
+fn lossy(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(bytes).into_owned()
+}
+
+/// Parse a network like `10.0.0.0/8`, or a single address like `::1`.
+pub(crate) fn parse_cidr(cidr: &str) -> Result<IpNet, ParseError> {
+    let cidr = cidr.trim();
+    match cidr.parse::<IpNet>() {
+        Ok(net) => Ok(net),
+        Err(_) => cidr
+            .parse::<IpAddr>()
+            .map(IpNet::from)
+            .map_err(|_| ParseError::new(format!("invalid CIDR `{}`", cidr))),
+    }
+}
+
+/// Whether an address, or the IPv4 address mapped into it, is in one of the networks.
+pub(crate) fn is_trusted(networks: &[IpNet], peer: IpAddr) -> bool {
+    let peer = match peer {
+        IpAddr::V6(v6) => v6.to_ipv4_mapped().map(IpAddr::V4).unwrap_or(peer),
+        IpAddr::V4(_) => peer,
+    };
+    networks.iter().any(|net| net.contains(&peer))
+}
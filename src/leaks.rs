@@ -0,0 +1,136 @@
+//! Strip response headers that leak implementation details.
+//!
+//! Headers like `Server`, `X-Powered-By` and `X-AspNet-Version` tell attackers which software
+//! and versions to target, and a detailed `Server-Timing` header exposes backend timings. A
+//! [`LeakStripper`] removes a curated list of such headers, or replaces them with a generic
+//! value. Headers can be allowed again, e.g. to keep `Server-Timing` in staging.
+//!
+//! [OWASP | Fingerprint Web Server](https://owasp.org/www-project-web-security-testing-guide/latest/4-Web_Application_Security_Testing/01-Information_Gathering/02-Fingerprint_Web_Server)
+//!
+//! # Examples
+//!
+//! ```
+//! let mut stripper = armor::leaks::new();
+//! stripper.replace("Server", "webserver").unwrap();
+//!
+//! let mut headers = http::HeaderMap::new();
+//! headers.insert("Server", "nginx/1.25.3".parse().unwrap());
+//! headers.insert("X-AspNet-Version", "4.0.30319".parse().unwrap());
+//! stripper.apply(&mut headers);
+//!
+//! assert_eq!(headers["Server"], "webserver");
+//! assert_eq!(headers.get("X-AspNet-Version"), None);
+//! ```
+
+use crate::ParseError;
+use http::header::{HeaderName, HeaderValue};
+use http::HeaderMap;
+
+/// Headers stripped by default.
+pub(crate) const LEAKY_HEADERS: &[&str] = &[
+    "server",
+    "server-timing",
+    "via",
+    "x-aspnet-version",
+    "x-aspnetmvc-version",
+    "x-generator",
+    "x-powered-by",
+    "x-runtime",
+];
+
+/// Build the list of headers to strip
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LeakStripper {
+    headers: Vec<HeaderName>,
+    replacements: Vec<(HeaderName, HeaderValue)>,
+}
+
+impl Default for LeakStripper {
+    /// Strips `Server`, `Server-Timing`, `Via`, `X-AspNet-Version`, `X-AspNetMvc-Version`,
+    /// `X-Generator`, `X-Powered-By` and `X-Runtime`.
+    fn default() -> Self {
+        Self {
+            headers: LEAKY_HEADERS
+                .iter()
+                .map(|name| HeaderName::from_static(name))
+                .collect(),
+            replacements: Vec::new(),
+        }
+    }
+}
+
+impl LeakStripper {
+    /// Create a new instance.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Strip an additional header.
+    pub fn strip<T: AsRef<str>>(&mut self, header: T) -> Result<&mut Self, ParseError> {
+        let name = header_name(header.as_ref())?;
+        if !self.headers.contains(&name) {
+            self.headers.push(name);
+        }
+        Ok(self)
+    }
+
+    /// Keep a header, e.g. `Server-Timing` in development.
+    pub fn allow<T: AsRef<str>>(&mut self, header: T) -> &mut Self {
+        let header = header.as_ref();
+        self.headers
+            .retain(|name| !name.as_str().eq_ignore_ascii_case(header));
+        self.replacements
+            .retain(|(name, _)| !name.as_str().eq_ignore_ascii_case(header));
+        self
+    }
+
+    /// Replace a header's value instead of removing it, e.g. a generic `Server`.
+    ///
+    /// Headers that aren't present aren't added.
+    pub fn replace<T, V>(&mut self, header: T, value: V) -> Result<&mut Self, ParseError>
+    where
+        T: AsRef<str>,
+        V: AsRef<str>,
+    {
+        let name = header_name(header.as_ref())?;
+        let value = HeaderValue::from_str(value.as_ref())
+            .map_err(|_| ParseError::new(format!("invalid value for `{}`", name)))?;
+        if !self.headers.contains(&name) {
+            self.headers.push(name.clone());
+        }
+        self.replacements.retain(|(replaced, _)| *replaced != name);
+        self.replacements.push((name, value));
+        Ok(self)
+    }
+
+    /// The headers that are stripped or replaced.
+    pub fn headers(&self) -> impl Iterator<Item = &str> {
+        self.headers.iter().map(HeaderName::as_str)
+    }
+
+    /// Strip or replace the configured headers.
+    pub fn apply(&self, headers: &mut HeaderMap) {
+        for name in &self.headers {
+            if headers.remove(name).is_none() {
+                continue;
+            }
+            let replacement = self
+                .replacements
+                .iter()
+                .find(|(replaced, _)| replaced == name);
+            if let Some((_, value)) = replacement {
+                headers.insert(name.clone(), value.clone());
+            }
+        }
+    }
+}
+
+/// Create a new instance.
+pub fn new() -> LeakStripper {
+    LeakStripper::new()
+}
+
+fn header_name(name: &str) -> Result<HeaderName, ParseError> {
+    HeaderName::from_bytes(name.as_bytes())
+        .map_err(|_| ParseError::new(format!("invalid header name `{}`", name)))
+}
@@ -16,10 +16,37 @@
 #![cfg_attr(test, deny(warnings))]
 
 use http::HeaderMap;
+pub mod cookies;
+pub mod cors;
+pub mod cross_origin;
 pub mod csp;
+pub mod csrf;
+pub mod fetch_metadata;
+pub mod framing;
+pub mod har;
+pub mod host;
+pub mod leaks;
+pub mod nel;
+pub mod permissions;
+pub mod sanitize;
+pub mod sfv;
+
+mod audit;
+mod context;
+mod error;
+mod preset;
+mod url;
+
+pub use audit::{audit, AuditReport, Category, Finding, Grade, Severity};
+pub use context::RequestContext;
+pub use error::ParseError;
+pub use preset::{Armor, Preset};
 
 /// Apply all protections.
 ///
+/// Uses the [`Preset::Helmet2019`] defaults so existing users keep the same headers. Use
+/// [`Armor`] for current defaults or to configure individual protections.
+///
 /// ## Examples
 /// ```
 /// let mut headers = http::HeaderMap::new();
@@ -28,31 +55,53 @@ pub mod csp;
 /// assert_eq!(headers["X-XSS-Protection"], "1; mode=block");
 /// ```
 pub fn armor(headers: &mut HeaderMap) {
-    dns_prefetch_control(headers);
-    dont_sniff_mimetype(headers);
-    frameguard(headers, None);
-    hide_powered_by(headers);
-    hsts(headers);
-    xss_filter(headers);
+    Armor::preset(Preset::Helmet2019).apply(headers);
+}
+
+/// Apply all protections that make sense for the given request.
+///
+/// Same as [`armor`], but `Strict-Transport-Security` is only set for HTTPS requests to
+/// non-local hosts. See [`hsts_for`].
+///
+/// ## Examples
+/// ```
+/// let mut ctx = armor::RequestContext::new();
+/// ctx.scheme("http").host("localhost:8080");
+///
+/// let mut headers = http::HeaderMap::new();
+/// armor::armor_for(&mut headers, &ctx);
+/// assert_eq!(headers["X-Content-Type-Options"], "nosniff");
+/// assert_eq!(headers.get("Strict-Transport-Security"), None);
+/// ```
+pub fn armor_for(headers: &mut HeaderMap, ctx: &RequestContext) {
+    Armor::preset(Preset::Helmet2019).apply_for(headers, ctx);
 }
 
-/// Disable browsers’ DNS prefetching by setting the `X-DNS-Prefetch-Control` header.
+/// Control browsers’ DNS prefetching by setting the `X-DNS-Prefetch-Control` header.
+///
+/// Prefetching makes links load slightly faster, but resolves the hostnames of every link on the
+/// page, which leaks them to the DNS resolver even if they're never visited. Pass `false` to
+/// disable it, which is what [`Preset::Modern`] does.
 ///
 /// [read more](https://helmetjs.github.io/docs/dns-prefetch-control/)
 ///
 /// ## Examples
 /// ```
 /// let mut headers = http::HeaderMap::new();
-/// armor::dns_prefetch_control(&mut headers);
+/// armor::dns_prefetch_control(&mut headers, false);
+/// assert_eq!(headers["X-DNS-Prefetch-Control"], "off");
+///
+/// armor::dns_prefetch_control(&mut headers, true);
 /// assert_eq!(headers["X-DNS-Prefetch-Control"], "on");
 /// ```
 #[inline]
-pub fn dns_prefetch_control(headers: &mut HeaderMap) {
-    headers.insert("X-DNS-Prefetch-Control", "on".parse().unwrap());
+pub fn dns_prefetch_control(headers: &mut HeaderMap, allow: bool) {
+    let value = if allow { "on" } else { "off" };
+    headers.insert("X-DNS-Prefetch-Control", value.parse().unwrap());
 }
 
 /// Set the frameguard level.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum FrameOptions {
     /// Set to `sameorigin`
     SameOrigin,
@@ -62,6 +111,8 @@ pub enum FrameOptions {
 
 /// Mitigates clickjacking attacks by setting the `X-Frame-Options` header.
 ///
+/// Use [`framing::FramingPolicy`] to keep it consistent with a CSP `frame-ancestors` directive.
+///
 /// [read more](https://helmetjs.github.io/docs/frameguard/)
 ///
 /// ## Examples
@@ -79,9 +130,81 @@ pub fn frameguard(headers: &mut HeaderMap, guard: Option<FrameOptions>) {
     headers.insert("X-Frame-Options", kind.parse().unwrap());
 }
 
+/// Prevents old versions of Internet Explorer from executing downloads in the site’s context
+/// by setting the `X-Download-Options` header.
+///
+/// [read more](https://helmetjs.github.io/docs/ienoopen/)
+///
+/// ## Examples
+/// ```
+/// let mut headers = http::HeaderMap::new();
+/// armor::ie_no_open(&mut headers);
+/// assert_eq!(headers["X-Download-Options"], "noopen");
+/// ```
+#[inline]
+pub fn ie_no_open(headers: &mut HeaderMap) {
+    headers.insert("X-Download-Options", "noopen".parse().unwrap());
+}
+
+/// Asks browsers to isolate the document in its own origin-keyed agent cluster by setting the
+/// `Origin-Agent-Cluster` header.
+///
+/// [MDN | Origin-Agent-Cluster](https://developer.mozilla.org/en-US/docs/Web/HTTP/Headers/Origin-Agent-Cluster)
+///
+/// ## Examples
+/// ```
+/// let mut headers = http::HeaderMap::new();
+/// armor::origin_agent_cluster(&mut headers);
+/// assert_eq!(headers["Origin-Agent-Cluster"], "?1");
+/// ```
+#[inline]
+pub fn origin_agent_cluster(headers: &mut HeaderMap) {
+    headers.insert("Origin-Agent-Cluster", "?1".parse().unwrap());
+}
+
+/// Set the `X-Permitted-Cross-Domain-Policies` level.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CrossDomainOptions {
+    /// Set to "none" the default
+    None,
+    /// Set to "master-only"
+    MasterOnly,
+    /// Set to "by-content-type"
+    ByContentType,
+    /// Set to "all"
+    All,
+}
+
+/// Restricts Adobe Flash and Acrobat from loading cross-domain data by setting the
+/// `X-Permitted-Cross-Domain-Policies` header.
+///
+/// [read more](https://helmetjs.github.io/docs/crossdomain/)
+///
+/// ## Examples
+/// ```
+/// let mut headers = http::HeaderMap::new();
+/// armor::permitted_cross_domain_policies(&mut headers, None);
+/// assert_eq!(headers["X-Permitted-Cross-Domain-Policies"], "none");
+/// ```
+#[inline]
+pub fn permitted_cross_domain_policies(
+    headers: &mut HeaderMap,
+    policy: Option<CrossDomainOptions>,
+) {
+    let policy = match policy {
+        None | Some(CrossDomainOptions::None) => "none",
+        Some(CrossDomainOptions::MasterOnly) => "master-only",
+        Some(CrossDomainOptions::ByContentType) => "by-content-type",
+        Some(CrossDomainOptions::All) => "all",
+    };
+    headers.insert("X-Permitted-Cross-Domain-Policies", policy.parse().unwrap());
+}
+
 /// Removes the `X-Powered-By` header to make it slightly harder for attackers to see what
 /// potentially-vulnerable technology powers your site.
 ///
+/// Use [`leaks`] to strip other headers like `Server` and `X-AspNet-Version` too.
+///
 /// [read more](https://helmetjs.github.io/docs/hide-powered-by/)
 ///
 /// ## Examples
@@ -96,6 +219,72 @@ pub fn hide_powered_by(headers: &mut HeaderMap) {
     headers.remove("X-Powered-By");
 }
 
+/// Configure the `Strict-Transport-Security` header.
+///
+/// Defaults to 60 days without `includeSubDomains` or `preload`.
+///
+/// [MDN | Strict-Transport-Security](https://developer.mozilla.org/en-US/docs/Web/HTTP/Headers/Strict-Transport-Security)
+///
+/// ## Examples
+/// ```
+/// let options = armor::HstsOptions {
+///     max_age: 15552000,
+///     include_subdomains: true,
+///     preload: false,
+/// };
+/// let mut headers = http::HeaderMap::new();
+/// options.apply(&mut headers);
+/// assert_eq!(headers["Strict-Transport-Security"], "max-age=15552000; includeSubDomains");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HstsOptions {
+    /// How long browsers should remember to only use HTTPS, in seconds
+    pub max_age: u64,
+    /// Set the `includeSubDomains` directive
+    pub include_subdomains: bool,
+    /// Set the `preload` directive
+    pub preload: bool,
+}
+
+impl Default for HstsOptions {
+    fn default() -> Self {
+        Self {
+            max_age: 5_184_000,
+            include_subdomains: false,
+            preload: false,
+        }
+    }
+}
+
+impl HstsOptions {
+    /// Create and retrieve the header value
+    pub fn value(&self) -> String {
+        let mut value = format!("max-age={}", self.max_age);
+        if self.include_subdomains {
+            value.push_str("; includeSubDomains");
+        }
+        if self.preload {
+            value.push_str("; preload");
+        }
+        value
+    }
+
+    /// Sets the `Strict-Transport-Security` header.
+    pub fn apply(&self, headers: &mut HeaderMap) {
+        headers.insert("Strict-Transport-Security", self.value().parse().unwrap());
+    }
+
+    /// Sets the `Strict-Transport-Security` header, but only for HTTPS requests to non-local
+    /// hosts. See [`hsts_for`].
+    pub fn apply_for(&self, headers: &mut HeaderMap, ctx: &RequestContext) {
+        if ctx.is_secure() {
+            self.apply(headers);
+        } else {
+            headers.remove("Strict-Transport-Security");
+        }
+    }
+}
+
 /// Sets the `Strict-Transport-Security` header to keep your users on `HTTPS`.
 ///
 /// Note that the header won’t tell users on HTTP to switch to HTTPS, it will tell HTTPS users to
@@ -111,8 +300,31 @@ pub fn hide_powered_by(headers: &mut HeaderMap) {
 /// ```
 #[inline]
 pub fn hsts(headers: &mut HeaderMap) {
-    let val = "max-age=5184000".parse().unwrap();
-    headers.insert("Strict-Transport-Security", val);
+    HstsOptions::default().apply(headers);
+}
+
+/// Sets the `Strict-Transport-Security` header, but only for HTTPS requests to non-local hosts.
+///
+/// Browsers ignore the header when it's received over plain HTTP, and setting it on
+/// `localhost` forces HTTPS for every project served from that host. In those cases an
+/// existing `Strict-Transport-Security` header is removed instead.
+///
+/// ## Examples
+/// ```
+/// let mut ctx = armor::RequestContext::new();
+/// ctx.scheme("https").host("example.com");
+///
+/// let mut headers = http::HeaderMap::new();
+/// armor::hsts_for(&mut headers, &ctx);
+/// assert_eq!(headers["Strict-Transport-Security"], "max-age=5184000");
+///
+/// ctx.host("127.0.0.1:8443");
+/// armor::hsts_for(&mut headers, &ctx);
+/// assert_eq!(headers.get("Strict-Transport-Security"), None);
+/// ```
+#[inline]
+pub fn hsts_for(headers: &mut HeaderMap, ctx: &RequestContext) {
+    HstsOptions::default().apply_for(headers, ctx);
 }
 
 /// Prevent browsers from trying to guess (“sniff”) the MIME type, which can have security
@@ -131,23 +343,178 @@ pub fn dont_sniff_mimetype(headers: &mut HeaderMap) {
     headers.insert("X-Content-Type-Options", "nosniff".parse().unwrap());
 }
 
-/// Sets the `X-XSS-Protection` header to prevent reflected XSS attacks.
+/// Set the `X-XSS-Protection` mode.
+///
+/// Browsers have removed their XSS auditors, and the auditor itself could be abused to leak
+/// information from a page, so `Disabled` is recommended. Use a `Content-Security-Policy` to
+/// prevent XSS instead.
+///
+/// [MDN | X-XSS-Protection](https://developer.mozilla.org/en-US/docs/Web/HTTP/Headers/X-XSS-Protection)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum XssOptions {
+    /// Set to "0" the default
+    Disabled,
+    /// Set to "1"
+    Enabled,
+    /// Set to "1; mode=block"
+    Block,
+    /// Set to "1; report=<uri>"
+    Report(ReportUri),
+    /// Set to "1; mode=block; report=<uri>"
+    BlockReport(ReportUri),
+}
+
+impl XssOptions {
+    /// Create and retrieve the header value
+    pub fn value(&self) -> String {
+        match *self {
+            XssOptions::Disabled => "0".to_string(),
+            XssOptions::Enabled => "1".to_string(),
+            XssOptions::Block => "1; mode=block".to_string(),
+            XssOptions::Report(ref uri) => format!("1; report={}", uri),
+            XssOptions::BlockReport(ref uri) => format!("1; mode=block; report={}", uri),
+        }
+    }
+
+    /// Warn about modes that enable the XSS auditor.
+    ///
+    /// ## Examples
+    /// ```
+    /// assert!(armor::XssOptions::Block.warning().is_some());
+    /// assert_eq!(armor::XssOptions::Disabled.warning(), None);
+    /// ```
+    pub fn warning(&self) -> Option<&'static str> {
+        match *self {
+            XssOptions::Disabled => None,
+            XssOptions::Block | XssOptions::BlockReport(_) => Some(
+                "`1; mode=block` is not recommended: the blocking XSS auditor can be abused to \
+                 leak information about the page; use `0` and a Content-Security-Policy instead",
+            ),
+            XssOptions::Enabled | XssOptions::Report(_) => Some(
+                "enabling the XSS auditor is not recommended: it can be abused to remove \
+                 legitimate scripts and leak information; use `0` and a Content-Security-Policy instead",
+            ),
+        }
+    }
+}
+
+impl std::str::FromStr for XssOptions {
+    type Err = ParseError;
+
+    /// Parse an `X-XSS-Protection` header value.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut directives = s.split(';').map(str::trim);
+        match directives.next() {
+            Some("0") => return Ok(XssOptions::Disabled),
+            Some("1") => {}
+            _ => return Err(ParseError::new("expected `0` or `1`")),
+        }
+        let mut block = false;
+        let mut report = None;
+        for directive in directives.filter(|d| !d.is_empty()) {
+            let mut parts = directive.splitn(2, '=');
+            let name = parts.next().unwrap_or("").trim();
+            let value = parts.next().unwrap_or("").trim();
+            if name.eq_ignore_ascii_case("mode") && value.eq_ignore_ascii_case("block") && !block {
+                block = true;
+            } else if name.eq_ignore_ascii_case("report") && report.is_none() {
+                report = Some(value.parse::<ReportUri>()?);
+            } else {
+                return Err(ParseError::new(format!(
+                    "unknown or repeated directive `{}`",
+                    directive
+                )));
+            }
+        }
+        Ok(match (block, report) {
+            (false, None) => XssOptions::Enabled,
+            (true, None) => XssOptions::Block,
+            (false, Some(uri)) => XssOptions::Report(uri),
+            (true, Some(uri)) => XssOptions::BlockReport(uri),
+        })
+    }
+}
+
+/// A URI for the `report=` directive of `X-XSS-Protection`.
+///
+/// Only visible ASCII other than `;` and `,` is accepted, so the URI can't end the directive
+/// early or break the header.
+///
+/// ## Examples
+/// ```
+/// use armor::ReportUri;
+///
+/// assert!("https://example.com/xss".parse::<ReportUri>().is_ok());
+/// assert!("/xss\r\nSet-Cookie: a=b".parse::<ReportUri>().is_err());
+/// assert!("".parse::<ReportUri>().is_err());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ReportUri(String);
+
+impl ReportUri {
+    /// The URI.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AsRef<str> for ReportUri {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::fmt::Display for ReportUri {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::str::FromStr for ReportUri {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let valid = !s.is_empty()
+            && s.chars()
+                .all(|c| c.is_ascii_graphic() && c != ';' && c != ',');
+        if valid {
+            Ok(ReportUri(s.to_string()))
+        } else {
+            Err(ParseError::new(format!(
+                "invalid report URI `{}`",
+                s.escape_debug()
+            )))
+        }
+    }
+}
+
+/// Sets the `X-XSS-Protection` header to control browsers' XSS auditor.
+///
+/// Defaults to disabling the auditor. See [`XssOptions`].
 ///
 /// [read more](https://helmetjs.github.io/docs/xss-filter/)
 ///
 /// ## Examples
 /// ```
 /// let mut headers = http::HeaderMap::new();
-/// armor::xss_filter(&mut headers);
-/// assert_eq!(headers["X-XSS-Protection"], "1; mode=block");
+/// armor::xss_filter(&mut headers, None);
+/// assert_eq!(headers["X-XSS-Protection"], "0");
+///
+/// let report = armor::XssOptions::Report("https://example.com/xss".parse().unwrap());
+/// armor::xss_filter(&mut headers, Some(report));
+/// assert_eq!(headers["X-XSS-Protection"], "1; report=https://example.com/xss");
+///
+/// let parsed: armor::XssOptions = "1; mode=block".parse().unwrap();
+/// assert_eq!(parsed, armor::XssOptions::Block);
 /// ```
 #[inline]
-pub fn xss_filter(headers: &mut HeaderMap) {
-    headers.insert("X-XSS-Protection", "1; mode=block".parse().unwrap());
+pub fn xss_filter(headers: &mut HeaderMap, options: Option<XssOptions>) {
+    let options = options.unwrap_or(XssOptions::Disabled);
+    headers.insert("X-XSS-Protection", options.value().parse().unwrap());
 }
 
 /// Set the Referrer-Policy level
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ReferrerOptions {
     /// Set to "no-referrer"
     NoReferrer,
@@ -167,6 +534,136 @@ pub enum ReferrerOptions {
     UnsafeUrl,
 }
 
+impl AsRef<str> for ReferrerOptions {
+    fn as_ref(&self) -> &str {
+        match *self {
+            ReferrerOptions::NoReferrer => "no-referrer",
+            ReferrerOptions::NoReferrerDowngrade => "no-referrer-when-downgrade",
+            ReferrerOptions::SameOrigin => "same-origin",
+            ReferrerOptions::Origin => "origin",
+            ReferrerOptions::StrictOrigin => "strict-origin",
+            ReferrerOptions::CrossOrigin => "origin-when-cross-origin",
+            ReferrerOptions::StrictCrossOrigin => "strict-origin-when-cross-origin",
+            ReferrerOptions::UnsafeUrl => "unsafe-url",
+        }
+    }
+}
+
+impl std::str::FromStr for ReferrerOptions {
+    type Err = ParseError;
+
+    /// Parse a single policy token, ignoring ASCII case.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "no-referrer" => Ok(ReferrerOptions::NoReferrer),
+            "no-referrer-when-downgrade" => Ok(ReferrerOptions::NoReferrerDowngrade),
+            "same-origin" => Ok(ReferrerOptions::SameOrigin),
+            "origin" => Ok(ReferrerOptions::Origin),
+            "strict-origin" => Ok(ReferrerOptions::StrictOrigin),
+            "origin-when-cross-origin" => Ok(ReferrerOptions::CrossOrigin),
+            "strict-origin-when-cross-origin" => Ok(ReferrerOptions::StrictCrossOrigin),
+            "unsafe-url" => Ok(ReferrerOptions::UnsafeUrl),
+            other => Err(ParseError::new(format!(
+                "unknown referrer policy `{}`",
+                other
+            ))),
+        }
+    }
+}
+
+/// Build a `Referrer-Policy` with fallbacks.
+///
+/// Browsers use the last policy in the list they recognise, so older browsers can fall back to
+/// an earlier policy when they don't support a newer one. Unknown tokens are kept when parsing so
+/// a value can be round-tripped, but anything that isn't a valid header token is dropped.
+///
+/// [Spec](https://w3c.github.io/webappsec-referrer-policy/#unknown-policy-values)
+///
+/// ## Examples
+/// ```
+/// use armor::{ReferrerOptions, ReferrerPolicy};
+///
+/// let policy = ReferrerPolicy::parse("no-referrer, strict-origin-when-cross-origin, from-the-future");
+/// assert_eq!(policy.effective(), Some(ReferrerOptions::StrictCrossOrigin));
+///
+/// let mut headers = http::HeaderMap::new();
+/// policy.apply(&mut headers);
+/// assert_eq!(headers["Referrer-Policy"], "no-referrer, strict-origin-when-cross-origin, from-the-future");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReferrerPolicy {
+    tokens: Vec<String>,
+}
+
+impl ReferrerPolicy {
+    /// Create a new instance.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parse a `Referrer-Policy` header value.
+    pub fn parse(value: &str) -> Self {
+        let tokens = value
+            .split(',')
+            .map(str::trim)
+            .filter(|token| {
+                !token.is_empty()
+                    && token
+                        .bytes()
+                        .all(|c| c.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&c))
+            })
+            .map(String::from)
+            .collect();
+        Self { tokens }
+    }
+
+    /// Combine all `Referrer-Policy` headers, in order, into a single policy.
+    ///
+    /// Returns `None` when no header is set.
+    pub fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        let mut policy: Option<Self> = None;
+        for value in headers.get_all("Referrer-Policy") {
+            let parsed = Self::parse(value.to_str().unwrap_or(""));
+            policy
+                .get_or_insert_with(Self::new)
+                .tokens
+                .extend(parsed.tokens);
+        }
+        policy
+    }
+
+    /// Add a policy to the end of the list, to be used by browsers that support it.
+    pub fn push(&mut self, policy: ReferrerOptions) -> &mut Self {
+        self.tokens.push(policy.as_ref().to_string());
+        self
+    }
+
+    /// The policy browsers will use: the last recognised token.
+    ///
+    /// Returns `None` when no token is recognised, in which case browsers use their default.
+    pub fn effective(&self) -> Option<ReferrerOptions> {
+        self.tokens
+            .iter()
+            .rev()
+            .find_map(|token| token.parse().ok())
+    }
+
+    /// Create and retrieve the header value
+    pub fn value(&self) -> String {
+        self.tokens.join(", ")
+    }
+
+    /// Sets the `Referrer-Policy` header, replacing any existing headers.
+    ///
+    /// An empty policy leaves the headers unchanged.
+    pub fn apply(&self, headers: &mut HeaderMap) {
+        if self.tokens.is_empty() {
+            return;
+        }
+        headers.insert("Referrer-Policy", self.value().parse().unwrap());
+    }
+}
+
 /// Mitigates referrer leakage by controlling the referer[sic] header in links away from pages
 ///
 /// [read more](https://scotthelme.co.uk/a-new-security-header-referrer-policy/)
@@ -184,16 +681,8 @@ pub enum ReferrerOptions {
 /// ```
 #[inline]
 pub fn referrer_policy(headers: &mut HeaderMap, referrer: Option<ReferrerOptions>) {
-    let policy = match referrer {
-        None | Some(ReferrerOptions::NoReferrer) => "no-referrer",
-        Some(ReferrerOptions::NoReferrerDowngrade) => "no-referrer-when-downgrade",
-        Some(ReferrerOptions::SameOrigin) => "same-origin",
-        Some(ReferrerOptions::Origin) => "origin",
-        Some(ReferrerOptions::StrictOrigin) => "strict-origin",
-        Some(ReferrerOptions::CrossOrigin) => "origin-when-cross-origin",
-        Some(ReferrerOptions::StrictCrossOrigin) => "strict-origin-when-cross-origin",
-        Some(ReferrerOptions::UnsafeUrl) => "unsafe-url",
-    };
+    let policy = referrer.unwrap_or(ReferrerOptions::NoReferrer);
+    let policy = policy.as_ref();
 
     // Allowing for multiple Referrer-Policy headers to be set
     // [Spec](https://w3c.github.io/webappsec-referrer-policy/#unknown-policy-values) Example #13
@@ -203,3 +692,164 @@ pub fn referrer_policy(headers: &mut HeaderMap, referrer: Option<ReferrerOptions
         headers.insert("Referrer-Policy", policy.parse().unwrap());
     }
 }
+
+/// Restricts which browser features the page and its frames can use by setting the
+/// `Permissions-Policy` header.
+///
+/// Defaults to disabling `browsing-topics`, `camera`, `geolocation`, `microphone`, `payment`
+/// and `usb`. See the [`permissions`] module to build a custom policy.
+///
+/// [Mozilla Developer Network](https://developer.mozilla.org/en-US/docs/Web/HTTP/Headers/Permissions-Policy)
+///
+/// ## Examples
+/// ```
+/// let mut headers = http::HeaderMap::new();
+/// armor::permissions_policy(&mut headers, None);
+/// assert_eq!(headers["Permissions-Policy"], "browsing-topics=(), camera=(), geolocation=(), microphone=(), payment=(), usb=()");
+/// ```
+#[inline]
+pub fn permissions_policy(headers: &mut HeaderMap, policy: Option<permissions::PermissionsPolicy>) {
+    policy.unwrap_or_default().apply(headers);
+}
+
+/// Makes the document cross-origin isolated by setting `Cross-Origin-Opener-Policy: same-origin`
+/// and `Cross-Origin-Embedder-Policy: require-corp`.
+///
+/// Cross-origin isolation is required to use `SharedArrayBuffer` and high-resolution timers.
+/// Every cross-origin resource the document loads must then opt in with
+/// `Cross-Origin-Resource-Policy` or CORS. See the [`cross_origin`] module for other values.
+///
+/// [read more](https://web.dev/articles/cross-origin-isolation-guide)
+///
+/// ## Examples
+/// ```
+/// let mut headers = http::HeaderMap::new();
+/// armor::cross_origin_isolated(&mut headers);
+/// assert_eq!(headers["Cross-Origin-Opener-Policy"], "same-origin");
+/// assert_eq!(headers["Cross-Origin-Embedder-Policy"], "require-corp");
+/// ```
+#[inline]
+pub fn cross_origin_isolated(headers: &mut HeaderMap) {
+    cross_origin::opener_policy(cross_origin::OpenerPolicy::SameOrigin).apply(headers);
+    cross_origin::embedder_policy(cross_origin::EmbedderPolicy::RequireCorp).apply(headers);
+}
+
+/// Define a `Clear-Site-Data` directive
+///
+/// [MDN | Clear-Site-Data](https://developer.mozilla.org/en-US/docs/Web/HTTP/Headers/Clear-Site-Data)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ClearSiteData {
+    /// Set directive `"cache"`
+    Cache,
+    /// Set directive `"clientHints"`
+    ClientHints,
+    /// Set directive `"cookies"`
+    Cookies,
+    /// Set directive `"executionContexts"`
+    ExecutionContexts,
+    /// Set directive `"prefetchCache"`
+    PrefetchCache,
+    /// Set directive `"prerenderCache"`
+    PrerenderCache,
+    /// Set directive `"storage"`
+    Storage,
+    /// Set directive `"*"`, clearing all types of data
+    Wildcard,
+}
+
+impl AsRef<str> for ClearSiteData {
+    fn as_ref(&self) -> &str {
+        match *self {
+            ClearSiteData::Cache => "cache",
+            ClearSiteData::ClientHints => "clientHints",
+            ClearSiteData::Cookies => "cookies",
+            ClearSiteData::ExecutionContexts => "executionContexts",
+            ClearSiteData::PrefetchCache => "prefetchCache",
+            ClearSiteData::PrerenderCache => "prerenderCache",
+            ClearSiteData::Storage => "storage",
+            ClearSiteData::Wildcard => "*",
+        }
+    }
+}
+
+impl std::str::FromStr for ClearSiteData {
+    type Err = ParseError;
+
+    /// Parse an unquoted directive name.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "cache" => Ok(ClearSiteData::Cache),
+            "clientHints" => Ok(ClearSiteData::ClientHints),
+            "cookies" => Ok(ClearSiteData::Cookies),
+            "executionContexts" => Ok(ClearSiteData::ExecutionContexts),
+            "prefetchCache" => Ok(ClearSiteData::PrefetchCache),
+            "prerenderCache" => Ok(ClearSiteData::PrerenderCache),
+            "storage" => Ok(ClearSiteData::Storage),
+            "*" => Ok(ClearSiteData::Wildcard),
+            other => Err(ParseError::new(format!(
+                "unknown Clear-Site-Data directive `{}`",
+                other
+            ))),
+        }
+    }
+}
+
+impl ClearSiteData {
+    /// Parse a `Clear-Site-Data` header value.
+    ///
+    /// Directives must be quoted strings. Unknown directives are an error, so this can be used
+    /// to validate values received from upstream services.
+    ///
+    /// ## Examples
+    /// ```
+    /// use armor::ClearSiteData;
+    ///
+    /// let directives = ClearSiteData::parse(r#""cache", "cookies""#).unwrap();
+    /// assert_eq!(directives, vec![ClearSiteData::Cache, ClearSiteData::Cookies]);
+    /// assert!(ClearSiteData::parse("cache").is_err());
+    /// ```
+    pub fn parse(value: &str) -> Result<Vec<Self>, ParseError> {
+        let mut directives = Vec::new();
+        for entry in sfv::parse_list(value)? {
+            let directive = match entry {
+                sfv::ListEntry::Item(ref item) => item.bare_item.as_string(),
+                sfv::ListEntry::InnerList(_) => None,
+            };
+            match directive {
+                Some(directive) => directives.push(directive.parse()?),
+                None => return Err(ParseError::new("directives must be quoted strings")),
+            }
+        }
+        Ok(directives)
+    }
+}
+
+/// Asks the browser to clear data stored for the site by setting the `Clear-Site-Data` header.
+///
+/// Send it from logout and account deletion endpoints. Duplicate directives are only sent once,
+/// and an empty list leaves the headers unchanged.
+///
+/// [MDN | Clear-Site-Data](https://developer.mozilla.org/en-US/docs/Web/HTTP/Headers/Clear-Site-Data)
+///
+/// ## Examples
+/// ```
+/// use armor::ClearSiteData;
+///
+/// let mut headers = http::HeaderMap::new();
+/// armor::clear_site_data(&mut headers, &[ClearSiteData::Cookies, ClearSiteData::Storage]);
+/// assert_eq!(headers["Clear-Site-Data"], r#""cookies", "storage""#);
+/// ```
+#[inline]
+pub fn clear_site_data(headers: &mut HeaderMap, directives: &[ClearSiteData]) {
+    let mut value: Vec<String> = Vec::new();
+    for directive in directives {
+        let quoted = format!("\"{}\"", directive.as_ref());
+        if !value.contains(&quoted) {
+            value.push(quoted);
+        }
+    }
+    if value.is_empty() {
+        return;
+    }
+    headers.insert("Clear-Site-Data", value.join(", ").parse().unwrap());
+}
@@ -0,0 +1,123 @@
+//! Apply `NEL` (Network Error Logging) headers.
+//!
+//! A `NEL` policy asks browsers to report failed (and optionally successful) requests to a
+//! reporting group defined by the `Report-To` header.
+//!
+//! [MDN | Network Error Logging](https://developer.mozilla.org/en-US/docs/Web/HTTP/Network_Error_Logging)
+//!
+//! # Examples
+//!
+//! ```
+//! use armor::csp::{ReportTo, ReportToEndpoint};
+//!
+//! let mut group = ReportTo::new(2592000, vec![ReportToEndpoint::new("https://example.com/nel")]);
+//! group.group("network-errors");
+//!
+//! let mut policy = armor::nel::new(&group, 2592000);
+//! policy.include_subdomains(true).failure_fraction(0.5).unwrap();
+//!
+//! let mut headers = http::HeaderMap::new();
+//! policy.apply(&mut headers);
+//!
+//! assert_eq!(headers["nel"], r#"{"report_to":"network-errors","max_age":2592000,"include_subdomains":true,"failure_fraction":0.5}"#);
+//! assert_eq!(headers["report-to"], r#"{"group":"network-errors","max_age":2592000,"endpoints":[{"url":"https://example.com/nel"}]}"#);
+//! ```
+
+use crate::csp::ReportTo;
+use crate::ParseError;
+use http::HeaderMap;
+use serde::Serialize;
+
+/// Build the `NEL` policy
+#[derive(Serialize, Debug, Clone)]
+pub struct NetworkErrorLogging {
+    report_to: String,
+    max_age: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    include_subdomains: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    success_fraction: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    failure_fraction: Option<f64>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    request_headers: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    response_headers: Vec<String>,
+    #[serde(skip)]
+    group: ReportTo,
+}
+
+impl NetworkErrorLogging {
+    /// Create a new instance reporting to a `Report-To` group for `max_age` seconds.
+    pub fn new(group: &ReportTo, max_age: u32) -> Self {
+        Self {
+            report_to: group.group_name().to_string(),
+            max_age,
+            include_subdomains: None,
+            success_fraction: None,
+            failure_fraction: None,
+            request_headers: Vec::new(),
+            response_headers: Vec::new(),
+            group: group.clone(),
+        }
+    }
+
+    /// Set whether the policy applies to subdomains.
+    pub fn include_subdomains(&mut self, include: bool) -> &mut Self {
+        self.include_subdomains = Some(include);
+        self
+    }
+
+    /// Set the fraction of successful requests to report, between `0.0` and `1.0`.
+    /// Browsers default to `0.0`.
+    pub fn success_fraction(&mut self, fraction: f64) -> Result<&mut Self, ParseError> {
+        self.success_fraction = Some(validate_fraction("success_fraction", fraction)?);
+        Ok(self)
+    }
+
+    /// Set the fraction of failed requests to report, between `0.0` and `1.0`.
+    /// Browsers default to `1.0`.
+    pub fn failure_fraction(&mut self, fraction: f64) -> Result<&mut Self, ParseError> {
+        self.failure_fraction = Some(validate_fraction("failure_fraction", fraction)?);
+        Ok(self)
+    }
+
+    /// Include a request header's value in reports.
+    pub fn request_header<T: AsRef<str>>(&mut self, name: T) -> &mut Self {
+        self.request_headers.push(name.as_ref().to_string());
+        self
+    }
+
+    /// Include a response header's value in reports.
+    pub fn response_header<T: AsRef<str>>(&mut self, name: T) -> &mut Self {
+        self.response_headers.push(name.as_ref().to_string());
+        self
+    }
+
+    /// Create and retrieve the policy value
+    pub fn value(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+
+    /// Sets the `NEL` HTTP header, and adds the reporting group to the `Report-To` header.
+    pub fn apply(&self, headers: &mut HeaderMap) {
+        headers.insert("NEL", self.value().parse().unwrap());
+        self.group.apply(headers);
+    }
+}
+
+/// Create a new instance.
+pub fn new(group: &ReportTo, max_age: u32) -> NetworkErrorLogging {
+    NetworkErrorLogging::new(group, max_age)
+}
+
+fn validate_fraction(name: &str, fraction: f64) -> Result<f64, ParseError> {
+    if (0.0..=1.0).contains(&fraction) {
+        Ok(fraction)
+    } else {
+        Err(ParseError::new(format!(
+            "{} must be between 0.0 and 1.0, got {}",
+            name, fraction
+        )))
+    }
+}
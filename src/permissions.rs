@@ -0,0 +1,666 @@
+//! Apply `Permissions-Policy` headers.
+//!
+//! `Permissions-Policy` headers control which browser features a document and the
+//! frames it embeds are allowed to use.
+//!
+//! [Mozilla Developer Network](https://developer.mozilla.org/en-US/docs/Web/HTTP/Headers/Permissions-Policy)
+//!
+//! # Examples
+//!
+//! ```
+//! use armor::permissions::{Allow, Feature};
+//!
+//! let mut policy = armor::permissions::new();
+//! policy
+//!     .deny(Feature::Camera)
+//!     .allow(Feature::Fullscreen, Allow::SameOrigin)
+//!     .allow(Feature::Geolocation, Allow::SameOrigin)
+//!     .allow_origin(Feature::Geolocation, "https://maps.example.com")
+//!     .unwrap();
+//!
+//! let mut headers = http::HeaderMap::new();
+//! policy.apply(&mut headers);
+//!
+//! assert_eq!(headers["permissions-policy"], r#"camera=(), fullscreen=(self), geolocation=(self "https://maps.example.com")"#);
+//! ```
+
+use crate::sfv::{self, BareItem, Dictionary, InnerList, Item, ListEntry};
+use crate::ParseError;
+use http::HeaderMap;
+use std::fmt;
+use std::str::FromStr;
+
+/// A policy-controlled feature.
+///
+/// [read more](https://github.com/w3c/webappsec-permissions-policy/blob/main/features.md)
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Feature {
+    /// Set feature `accelerometer`
+    Accelerometer,
+    /// Set feature `ambient-light-sensor`
+    AmbientLightSensor,
+    /// Set feature `attribution-reporting`
+    AttributionReporting,
+    /// Set feature `autoplay`
+    Autoplay,
+    /// Set feature `bluetooth`
+    Bluetooth,
+    /// Set feature `browsing-topics`
+    BrowsingTopics,
+    /// Set feature `camera`
+    Camera,
+    /// Set feature `clipboard-read`
+    ClipboardRead,
+    /// Set feature `clipboard-write`
+    ClipboardWrite,
+    /// Set feature `compute-pressure`
+    ComputePressure,
+    /// Set feature `display-capture`
+    DisplayCapture,
+    /// Set feature `document-domain`
+    DocumentDomain,
+    /// Set feature `encrypted-media`
+    EncryptedMedia,
+    /// Set feature `execution-while-not-rendered`
+    ExecutionWhileNotRendered,
+    /// Set feature `execution-while-out-of-viewport`
+    ExecutionWhileOutOfViewport,
+    /// Set feature `fullscreen`
+    Fullscreen,
+    /// Set feature `gamepad`
+    Gamepad,
+    /// Set feature `geolocation`
+    Geolocation,
+    /// Set feature `gyroscope`
+    Gyroscope,
+    /// Set feature `hid`
+    Hid,
+    /// Set feature `identity-credentials-get`
+    IdentityCredentialsGet,
+    /// Set feature `idle-detection`
+    IdleDetection,
+    /// Set feature `local-fonts`
+    LocalFonts,
+    /// Set feature `magnetometer`
+    Magnetometer,
+    /// Set feature `microphone`
+    Microphone,
+    /// Set feature `midi`
+    Midi,
+    /// Set feature `otp-credentials`
+    OtpCredentials,
+    /// Set feature `payment`
+    Payment,
+    /// Set feature `picture-in-picture`
+    PictureInPicture,
+    /// Set feature `publickey-credentials-create`
+    PublickeyCredentialsCreate,
+    /// Set feature `publickey-credentials-get`
+    PublickeyCredentialsGet,
+    /// Set feature `screen-wake-lock`
+    ScreenWakeLock,
+    /// Set feature `serial`
+    Serial,
+    /// Set feature `speaker-selection`
+    SpeakerSelection,
+    /// Set feature `storage-access`
+    StorageAccess,
+    /// Set feature `sync-xhr`
+    SyncXhr,
+    /// Set feature `usb`
+    Usb,
+    /// Set feature `web-share`
+    WebShare,
+    /// Set feature `window-management`
+    WindowManagement,
+    /// Set feature `xr-spatial-tracking`
+    XrSpatialTracking,
+    /// Set a feature not known to this crate
+    Other(FeatureName),
+}
+
+impl AsRef<str> for Feature {
+    fn as_ref(&self) -> &str {
+        match *self {
+            Feature::Accelerometer => "accelerometer",
+            Feature::AmbientLightSensor => "ambient-light-sensor",
+            Feature::AttributionReporting => "attribution-reporting",
+            Feature::Autoplay => "autoplay",
+            Feature::Bluetooth => "bluetooth",
+            Feature::BrowsingTopics => "browsing-topics",
+            Feature::Camera => "camera",
+            Feature::ClipboardRead => "clipboard-read",
+            Feature::ClipboardWrite => "clipboard-write",
+            Feature::ComputePressure => "compute-pressure",
+            Feature::DisplayCapture => "display-capture",
+            Feature::DocumentDomain => "document-domain",
+            Feature::EncryptedMedia => "encrypted-media",
+            Feature::ExecutionWhileNotRendered => "execution-while-not-rendered",
+            Feature::ExecutionWhileOutOfViewport => "execution-while-out-of-viewport",
+            Feature::Fullscreen => "fullscreen",
+            Feature::Gamepad => "gamepad",
+            Feature::Geolocation => "geolocation",
+            Feature::Gyroscope => "gyroscope",
+            Feature::Hid => "hid",
+            Feature::IdentityCredentialsGet => "identity-credentials-get",
+            Feature::IdleDetection => "idle-detection",
+            Feature::LocalFonts => "local-fonts",
+            Feature::Magnetometer => "magnetometer",
+            Feature::Microphone => "microphone",
+            Feature::Midi => "midi",
+            Feature::OtpCredentials => "otp-credentials",
+            Feature::Payment => "payment",
+            Feature::PictureInPicture => "picture-in-picture",
+            Feature::PublickeyCredentialsCreate => "publickey-credentials-create",
+            Feature::PublickeyCredentialsGet => "publickey-credentials-get",
+            Feature::ScreenWakeLock => "screen-wake-lock",
+            Feature::Serial => "serial",
+            Feature::SpeakerSelection => "speaker-selection",
+            Feature::StorageAccess => "storage-access",
+            Feature::SyncXhr => "sync-xhr",
+            Feature::Usb => "usb",
+            Feature::WebShare => "web-share",
+            Feature::WindowManagement => "window-management",
+            Feature::XrSpatialTracking => "xr-spatial-tracking",
+            Feature::Other(ref name) => name.as_str(),
+        }
+    }
+}
+
+impl fmt::Display for Feature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_ref())
+    }
+}
+
+impl FromStr for Feature {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let feature = match s {
+            "accelerometer" => Feature::Accelerometer,
+            "ambient-light-sensor" => Feature::AmbientLightSensor,
+            "attribution-reporting" => Feature::AttributionReporting,
+            "autoplay" => Feature::Autoplay,
+            "bluetooth" => Feature::Bluetooth,
+            "browsing-topics" => Feature::BrowsingTopics,
+            "camera" => Feature::Camera,
+            "clipboard-read" => Feature::ClipboardRead,
+            "clipboard-write" => Feature::ClipboardWrite,
+            "compute-pressure" => Feature::ComputePressure,
+            "display-capture" => Feature::DisplayCapture,
+            "document-domain" => Feature::DocumentDomain,
+            "encrypted-media" => Feature::EncryptedMedia,
+            "execution-while-not-rendered" => Feature::ExecutionWhileNotRendered,
+            "execution-while-out-of-viewport" => Feature::ExecutionWhileOutOfViewport,
+            "fullscreen" => Feature::Fullscreen,
+            "gamepad" => Feature::Gamepad,
+            "geolocation" => Feature::Geolocation,
+            "gyroscope" => Feature::Gyroscope,
+            "hid" => Feature::Hid,
+            "identity-credentials-get" => Feature::IdentityCredentialsGet,
+            "idle-detection" => Feature::IdleDetection,
+            "local-fonts" => Feature::LocalFonts,
+            "magnetometer" => Feature::Magnetometer,
+            "microphone" => Feature::Microphone,
+            "midi" => Feature::Midi,
+            "otp-credentials" => Feature::OtpCredentials,
+            "payment" => Feature::Payment,
+            "picture-in-picture" => Feature::PictureInPicture,
+            "publickey-credentials-create" => Feature::PublickeyCredentialsCreate,
+            "publickey-credentials-get" => Feature::PublickeyCredentialsGet,
+            "screen-wake-lock" => Feature::ScreenWakeLock,
+            "serial" => Feature::Serial,
+            "speaker-selection" => Feature::SpeakerSelection,
+            "storage-access" => Feature::StorageAccess,
+            "sync-xhr" => Feature::SyncXhr,
+            "usb" => Feature::Usb,
+            "web-share" => Feature::WebShare,
+            "window-management" => Feature::WindowManagement,
+            "xr-spatial-tracking" => Feature::XrSpatialTracking,
+            other => Feature::Other(other.parse()?),
+        };
+        Ok(feature)
+    }
+}
+
+/// The name of a feature not known to this crate, a Structured Field key like `"new-api"`.
+///
+/// ## Examples
+/// ```
+/// use armor::permissions::{Feature, FeatureName};
+///
+/// let name: FeatureName = "new-api".parse().unwrap();
+/// assert_eq!(Feature::Other(name).as_ref(), "new-api");
+/// assert!("Bad Key".parse::<FeatureName>().is_err());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FeatureName(String);
+
+impl FeatureName {
+    /// The feature name.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AsRef<str> for FeatureName {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for FeatureName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl FromStr for FeatureName {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if sfv::is_key(s) {
+            Ok(FeatureName(s.to_string()))
+        } else {
+            Err(ParseError::new(format!(
+                "invalid feature `{}`",
+                s.escape_debug()
+            )))
+        }
+    }
+}
+
+/// Define an allowlist entry
+///
+/// [read more](https://developer.mozilla.org/en-US/docs/Web/HTTP/Headers/Permissions-Policy#allowlists)
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Allow {
+    /// Set entry `*`, allowing all origins
+    Wildcard,
+    /// Set entry `self`
+    SameOrigin,
+    /// Set entry `src`, allowing the origin of an `<iframe>`'s `src`
+    Src,
+    /// Set entry to a specific origin, e.g. `"https://example.com"`
+    Origin(Origin),
+}
+
+impl From<Origin> for Allow {
+    fn from(origin: Origin) -> Self {
+        Allow::Origin(origin)
+    }
+}
+
+impl FromStr for Allow {
+    type Err = ParseError;
+
+    /// Parse `*`, `self`, `src` or an origin.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "*" => Ok(Allow::Wildcard),
+            "self" => Ok(Allow::SameOrigin),
+            "src" => Ok(Allow::Src),
+            origin => origin.parse().map(Allow::Origin),
+        }
+    }
+}
+
+/// An origin in an allowlist, like `https://example.com` or `https://*.example.com:8443`.
+///
+/// Origins are validated when they're created, so a policy can always be serialized.
+///
+/// ## Examples
+/// ```
+/// use armor::permissions::Origin;
+///
+/// assert!("https://maps.example.com".parse::<Origin>().is_ok());
+/// assert!("https://*.example.com:8443".parse::<Origin>().is_ok());
+/// assert!("https://example.com/path".parse::<Origin>().is_err());
+/// assert!("https://example.com\r\nSet-Cookie: a=b".parse::<Origin>().is_err());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Origin(String);
+
+impl Origin {
+    /// The serialized origin.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Whether the host starts with a `*.` wildcard label.
+    pub fn is_wildcard(&self) -> bool {
+        self.0.contains("://*.")
+    }
+}
+
+impl AsRef<str> for Origin {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl FromStr for Origin {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if is_origin(s) {
+            Ok(Origin(s.to_string()))
+        } else {
+            Err(ParseError::new(format!(
+                "invalid origin `{}`",
+                s.escape_debug()
+            )))
+        }
+    }
+}
+
+/// Whether a string is a `scheme://host[:port]` origin, allowing a leading `*.` wildcard label.
+fn is_origin(origin: &str) -> bool {
+    let (scheme, authority) = match origin.split_once("://") {
+        Some(parts) => parts,
+        None => return false,
+    };
+    let valid_scheme = scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+        && scheme
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c));
+    let port_start = match authority.rfind(']') {
+        Some(end) => authority[end..].find(':').map(|index| end + index),
+        None => authority.rfind(':'),
+    };
+    let (host, port) = match port_start {
+        Some(index) => (&authority[..index], Some(&authority[index + 1..])),
+        None => (authority, None),
+    };
+    let host = host.strip_prefix("*.").unwrap_or(host);
+    let valid_host = match host.strip_prefix('[') {
+        Some(ip) => ip
+            .strip_suffix(']')
+            .is_some_and(|ip| ip.parse::<std::net::Ipv6Addr>().is_ok()),
+        None => {
+            !host.is_empty()
+                && host.split('.').all(|label| {
+                    !label.is_empty()
+                        && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+                })
+        }
+    };
+    let valid_port = port.is_none_or(|port| port.parse::<u16>().is_ok());
+    valid_scheme && valid_host && valid_port
+}
+
+impl Allow {
+    fn to_item(&self) -> Item {
+        let bare_item = match *self {
+            Allow::Wildcard => BareItem::Token("*".to_string()),
+            Allow::SameOrigin => BareItem::Token("self".to_string()),
+            Allow::Src => BareItem::Token("src".to_string()),
+            Allow::Origin(ref origin) => BareItem::String(origin.0.clone()),
+        };
+        Item::new(bare_item)
+    }
+
+    fn from_item(item: &Item) -> Option<Self> {
+        match item.bare_item {
+            BareItem::Token(ref token) => match token.as_str() {
+                "*" => Some(Allow::Wildcard),
+                "self" => Some(Allow::SameOrigin),
+                "src" => Some(Allow::Src),
+                _ => None,
+            },
+            BareItem::String(ref origin) => origin.parse().ok().map(Allow::Origin),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Allow {
+    /// Formats the entry as it appears in the header, e.g. `self` or `"https://example.com"`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Allow::Wildcard => f.write_str("*"),
+            Allow::SameOrigin => f.write_str("self"),
+            Allow::Src => f.write_str("src"),
+            // Origins can't contain `"` or `\`, so they never need escaping.
+            Allow::Origin(ref origin) => write!(f, "\"{}\"", origin),
+        }
+    }
+}
+
+/// Build the Permissions-Policy
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PermissionsPolicy {
+    directives: Vec<(Feature, Vec<Allow>)>,
+    report_only_flag: bool,
+    feature_policy_flag: bool,
+}
+
+impl Default for PermissionsPolicy {
+    /// Disables `browsing-topics`, `camera`, `geolocation`, `microphone`, `payment` and `usb`.
+    fn default() -> Self {
+        let mut policy = Self::new();
+        policy
+            .deny(Feature::BrowsingTopics)
+            .deny(Feature::Camera)
+            .deny(Feature::Geolocation)
+            .deny(Feature::Microphone)
+            .deny(Feature::Payment)
+            .deny(Feature::Usb);
+        policy
+    }
+}
+
+impl PermissionsPolicy {
+    /// Create a new instance.
+    pub fn new() -> Self {
+        Self {
+            directives: Vec::new(),
+            report_only_flag: false,
+            feature_policy_flag: false,
+        }
+    }
+
+    /// Parse a `Permissions-Policy` header value.
+    ///
+    /// Members that aren't valid allowlists are ignored, like browsers do.
+    ///
+    /// ## Examples
+    /// ```
+    /// use armor::permissions::{Allow, Feature, PermissionsPolicy};
+    ///
+    /// let policy = PermissionsPolicy::parse(r#"camera=(), geolocation=(self "https://a.example")"#).unwrap();
+    /// assert_eq!(policy.allowlist(&Feature::Camera), Some(&[][..]));
+    /// assert_eq!(
+    ///     policy.allowlist(&Feature::Geolocation),
+    ///     Some(&[Allow::SameOrigin, "https://a.example".parse().unwrap()][..])
+    /// );
+    /// ```
+    pub fn parse(value: &str) -> Result<Self, ParseError> {
+        let mut policy = Self::new();
+        for (key, entry) in sfv::parse_dictionary(value)? {
+            let allowlist = match entry {
+                ListEntry::Item(ref item) => Allow::from_item(item).map(|allow| vec![allow]),
+                ListEntry::InnerList(ref list) => list.items.iter().map(Allow::from_item).collect(),
+            };
+            if let Some(allowlist) = allowlist {
+                policy.directives.push((key.parse()?, allowlist));
+            }
+        }
+        Ok(policy)
+    }
+
+    fn entry(&mut self, feature: Feature) -> &mut Vec<Allow> {
+        let idx = match self.directives.iter().position(|(f, _)| *f == feature) {
+            Some(idx) => idx,
+            None => {
+                self.directives.push((feature, Vec::new()));
+                self.directives.len() - 1
+            }
+        };
+        &mut self.directives[idx].1
+    }
+
+    /// Add an entry to a feature's allowlist.
+    pub fn allow<T: Into<Allow>>(&mut self, feature: Feature, allow: T) -> &mut Self {
+        let allow = allow.into();
+        let allowlist = self.entry(feature);
+        if !allowlist.contains(&allow) {
+            allowlist.push(allow);
+        }
+        self
+    }
+
+    /// Add an origin to a feature's allowlist, failing if it isn't a valid origin.
+    ///
+    /// ## Examples
+    /// ```
+    /// use armor::permissions::Feature;
+    ///
+    /// let mut policy = armor::permissions::new();
+    /// policy.allow_origin(Feature::Geolocation, "https://maps.example.com").unwrap();
+    /// assert_eq!(policy.value(), r#"geolocation=("https://maps.example.com")"#);
+    /// assert!(policy.allow_origin(Feature::Camera, "https://a.example\n").is_err());
+    /// ```
+    pub fn allow_origin<T: AsRef<str>>(
+        &mut self,
+        feature: Feature,
+        origin: T,
+    ) -> Result<&mut Self, ParseError> {
+        let origin: Origin = origin.as_ref().parse()?;
+        Ok(self.allow(feature, origin))
+    }
+
+    /// Disable a feature by giving it an empty allowlist.
+    pub fn deny(&mut self, feature: Feature) -> &mut Self {
+        self.entry(feature).clear();
+        self
+    }
+
+    /// Get the allowlist for a feature, if the policy defines one.
+    pub fn allowlist(&self, feature: &Feature) -> Option<&[Allow]> {
+        self.directives
+            .iter()
+            .find(|(f, _)| f == feature)
+            .map(|(_, allowlist)| allowlist.as_slice())
+    }
+
+    /// Iterate over the features and their allowlists.
+    pub fn iter(&self) -> impl Iterator<Item = (&Feature, &[Allow])> {
+        self.directives
+            .iter()
+            .map(|(feature, allowlist)| (feature, allowlist.as_slice()))
+    }
+
+    /// Change the header to `Permissions-Policy-Report-Only`
+    pub fn report_only(&mut self) -> &mut Self {
+        self.report_only_flag = true;
+        self
+    }
+
+    /// Also emit the equivalent legacy `Feature-Policy` header.
+    ///
+    /// Browsers that predate `Permissions-Policy` only understand `Feature-Policy`. It has no
+    /// report-only variant, so nothing is emitted for it when `report_only` is set.
+    ///
+    /// [MDN | Feature-Policy](https://developer.mozilla.org/en-US/docs/Web/HTTP/Headers/Feature-Policy)
+    pub fn feature_policy(&mut self) -> &mut Self {
+        self.feature_policy_flag = true;
+        self
+    }
+
+    /// Create and retrieve the policy value
+    pub fn value(&self) -> String {
+        let dict: Dictionary = self
+            .directives
+            .iter()
+            .map(|(feature, allowlist)| {
+                let entry = if allowlist.contains(&Allow::Wildcard) {
+                    ListEntry::Item(Allow::Wildcard.to_item())
+                } else {
+                    let items = allowlist.iter().map(Allow::to_item).collect();
+                    ListEntry::InnerList(InnerList::new(items))
+                };
+                (feature.to_string(), entry)
+            })
+            .collect();
+        sfv::serialize_dictionary(&dict).expect("features are keys and origins are validated")
+    }
+
+    /// Create and retrieve the equivalent legacy `Feature-Policy` value
+    ///
+    /// Feature-Policy only accepts exact origins, so wildcard origins like
+    /// `https://*.example.com` are left out, which makes the legacy policy stricter.
+    ///
+    /// ## Examples
+    /// ```
+    /// use armor::permissions::{Allow, Feature};
+    ///
+    /// let mut policy = armor::permissions::new();
+    /// policy
+    ///     .deny(Feature::Camera)
+    ///     .allow(Feature::Geolocation, Allow::SameOrigin)
+    ///     .allow_origin(Feature::Geolocation, "https://maps.example.com")
+    ///     .unwrap()
+    ///     .allow(Feature::Fullscreen, Allow::Wildcard);
+    ///
+    /// assert_eq!(policy.feature_policy_value(), "camera 'none'; geolocation 'self' https://maps.example.com; fullscreen *");
+    /// ```
+    pub fn feature_policy_value(&self) -> String {
+        let directives: Vec<String> = self
+            .directives
+            .iter()
+            .map(|(feature, allowlist)| {
+                let sources: Vec<&str> = if allowlist.is_empty() {
+                    vec!["'none'"]
+                } else if allowlist.contains(&Allow::Wildcard) {
+                    vec!["*"]
+                } else {
+                    allowlist
+                        .iter()
+                        .filter_map(|allow| match *allow {
+                            Allow::Wildcard => Some("*"),
+                            Allow::SameOrigin => Some("'self'"),
+                            Allow::Src => Some("'src'"),
+                            Allow::Origin(ref origin) if origin.is_wildcard() => None,
+                            Allow::Origin(ref origin) => Some(origin.as_str()),
+                        })
+                        .collect()
+                };
+                let sources = if sources.is_empty() {
+                    vec!["'none'"]
+                } else {
+                    sources
+                };
+                format!("{} {}", feature, sources.join(" "))
+            })
+            .collect();
+        directives.join("; ")
+    }
+
+    /// Sets the `Permissions-Policy` HTTP header, and `Feature-Policy` if enabled.
+    pub fn apply(&self, headers: &mut HeaderMap) {
+        // Features are keys and origins are validated, so both values are valid headers.
+        let val = self.value().parse().unwrap();
+        if !self.report_only_flag {
+            headers.insert("Permissions-Policy", val);
+            if self.feature_policy_flag {
+                let val = self.feature_policy_value().parse().unwrap();
+                headers.insert("Feature-Policy", val);
+            }
+        } else {
+            headers.insert("Permissions-Policy-Report-Only", val);
+        }
+    }
+}
+
+/// Create a new instance.
+pub fn new() -> PermissionsPolicy {
+    PermissionsPolicy::new()
+}
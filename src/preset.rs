@@ -0,0 +1,407 @@
+use crate::cookies::CookiePolicy;
+use crate::cross_origin::{
+    self, CrossOriginEmbedderPolicy, CrossOriginOpenerPolicy, CrossOriginResourcePolicy,
+    OpenerPolicy, ResourcePolicy,
+};
+use crate::csp::{self, ContentSecurityPolicy, Source};
+use crate::framing::{self, FramingConflict, FramingPolicy};
+use crate::leaks::LeakStripper;
+use crate::permissions::PermissionsPolicy;
+use crate::{
+    CrossDomainOptions, FrameOptions, HstsOptions, ReferrerOptions, RequestContext, XssOptions,
+};
+use http::HeaderMap;
+
+/// A versioned set of default protections.
+///
+/// New protections and changed recommendations are added as new presets, so the headers an
+/// existing preset produces don't change when armor is upgraded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Preset {
+    /// The headers [`armor`](crate::armor) applies: the defaults armor shipped with in 2019.
+    ///
+    /// Keeps `X-XSS-Protection: 1; mode=block` and `X-DNS-Prefetch-Control: on`, and doesn't
+    /// set a `Content-Security-Policy`.
+    Helmet2019,
+    /// Current defaults, matching helmet v7.
+    ///
+    /// Disables the XSS auditor and DNS prefetching, and sets a `Content-Security-Policy`,
+    /// `Cross-Origin-Opener-Policy`, `Cross-Origin-Resource-Policy`, `Permissions-Policy`,
+    /// `Referrer-Policy`, `Origin-Agent-Cluster`, `X-Permitted-Cross-Domain-Policies` and
+    /// `X-Download-Options`.
+    Modern,
+}
+
+/// Configure which protections to apply.
+///
+/// Start from a [`Preset`] and adjust individual protections. Setters that take an `Option`
+/// disable the protection when passed `None`.
+///
+/// ## Examples
+/// ```
+/// use armor::{Armor, FrameOptions, Preset};
+///
+/// let mut headers = http::HeaderMap::new();
+/// Armor::preset(Preset::Modern)
+///     .frameguard(Some(FrameOptions::Deny))
+///     .cross_origin_resource_policy(None)
+///     .apply(&mut headers);
+///
+/// assert_eq!(headers["X-Frame-Options"], "deny");
+/// assert_eq!(headers["X-XSS-Protection"], "0");
+/// assert_eq!(headers["Cross-Origin-Opener-Policy"], "same-origin");
+/// assert_eq!(headers.get("Cross-Origin-Resource-Policy"), None);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Armor {
+    content_security_policy: Option<ContentSecurityPolicy>,
+    cookies: Option<CookiePolicy>,
+    cross_origin_embedder_policy: Option<CrossOriginEmbedderPolicy>,
+    cross_origin_opener_policy: Option<CrossOriginOpenerPolicy>,
+    cross_origin_resource_policy: Option<CrossOriginResourcePolicy>,
+    dns_prefetch_control: Option<bool>,
+    dont_sniff_mimetype: bool,
+    frameguard: Option<FrameOptions>,
+    framing: Option<FramingPolicy>,
+    hide_powered_by: bool,
+    hsts: Option<HstsOptions>,
+    ie_no_open: bool,
+    leaks: Option<LeakStripper>,
+    origin_agent_cluster: bool,
+    permitted_cross_domain_policies: Option<CrossDomainOptions>,
+    permissions_policy: Option<PermissionsPolicy>,
+    referrer_policy: Option<ReferrerOptions>,
+    xss_filter: Option<XssOptions>,
+}
+
+impl Default for Armor {
+    /// Uses the [`Preset::Modern`] defaults.
+    fn default() -> Self {
+        Self::preset(Preset::Modern)
+    }
+}
+
+impl Armor {
+    /// Create a new instance using the [`Preset::Modern`] defaults.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a new instance from a preset.
+    pub fn preset(preset: Preset) -> Self {
+        match preset {
+            Preset::Helmet2019 => Self {
+                content_security_policy: None,
+                cookies: None,
+                cross_origin_embedder_policy: None,
+                cross_origin_opener_policy: None,
+                cross_origin_resource_policy: None,
+                dns_prefetch_control: Some(true),
+                dont_sniff_mimetype: true,
+                frameguard: Some(FrameOptions::SameOrigin),
+                framing: None,
+                hide_powered_by: true,
+                hsts: Some(HstsOptions::default()),
+                ie_no_open: false,
+                leaks: None,
+                origin_agent_cluster: false,
+                permitted_cross_domain_policies: None,
+                permissions_policy: None,
+                referrer_policy: None,
+                xss_filter: Some(XssOptions::Block),
+            },
+            Preset::Modern => Self {
+                content_security_policy: Some(modern_csp()),
+                cookies: None,
+                cross_origin_embedder_policy: None,
+                cross_origin_opener_policy: Some(cross_origin::opener_policy(
+                    OpenerPolicy::SameOrigin,
+                )),
+                cross_origin_resource_policy: Some(cross_origin::resource_policy(
+                    ResourcePolicy::SameOrigin,
+                )),
+                dns_prefetch_control: Some(false),
+                dont_sniff_mimetype: true,
+                frameguard: Some(FrameOptions::SameOrigin),
+                framing: None,
+                hide_powered_by: true,
+                hsts: Some(HstsOptions {
+                    max_age: 15_552_000,
+                    include_subdomains: true,
+                    preload: false,
+                }),
+                ie_no_open: true,
+                leaks: None,
+                origin_agent_cluster: true,
+                permitted_cross_domain_policies: Some(CrossDomainOptions::None),
+                permissions_policy: Some(PermissionsPolicy::default()),
+                referrer_policy: Some(ReferrerOptions::NoReferrer),
+                xss_filter: Some(XssOptions::Disabled),
+            },
+        }
+    }
+
+    /// Set the `Content-Security-Policy`.
+    pub fn content_security_policy(&mut self, policy: Option<ContentSecurityPolicy>) -> &mut Self {
+        self.content_security_policy = policy;
+        self
+    }
+
+    /// Harden outgoing `Set-Cookie` headers. Disabled in every preset.
+    ///
+    /// Violations that can't be fixed are ignored; use [`CookiePolicy::apply`] directly to
+    /// inspect them.
+    pub fn cookies(&mut self, policy: Option<CookiePolicy>) -> &mut Self {
+        self.cookies = policy;
+        self
+    }
+
+    /// Set the `Cross-Origin-Embedder-Policy`.
+    pub fn cross_origin_embedder_policy(
+        &mut self,
+        policy: Option<CrossOriginEmbedderPolicy>,
+    ) -> &mut Self {
+        self.cross_origin_embedder_policy = policy;
+        self
+    }
+
+    /// Set the `Cross-Origin-Opener-Policy`.
+    pub fn cross_origin_opener_policy(
+        &mut self,
+        policy: Option<CrossOriginOpenerPolicy>,
+    ) -> &mut Self {
+        self.cross_origin_opener_policy = policy;
+        self
+    }
+
+    /// Set the `Cross-Origin-Resource-Policy`.
+    pub fn cross_origin_resource_policy(
+        &mut self,
+        policy: Option<CrossOriginResourcePolicy>,
+    ) -> &mut Self {
+        self.cross_origin_resource_policy = policy;
+        self
+    }
+
+    /// Set whether browsers may prefetch DNS for links on the page.
+    ///
+    /// Defaults to `Some(false)` in [`Preset::Modern`], which keeps visited hostnames private.
+    pub fn dns_prefetch_control(&mut self, allow: Option<bool>) -> &mut Self {
+        self.dns_prefetch_control = allow;
+        self
+    }
+
+    /// Set whether to send `X-Content-Type-Options: nosniff`.
+    pub fn dont_sniff_mimetype(&mut self, enabled: bool) -> &mut Self {
+        self.dont_sniff_mimetype = enabled;
+        self
+    }
+
+    /// Set the `X-Frame-Options` level.
+    pub fn frameguard(&mut self, guard: Option<FrameOptions>) -> &mut Self {
+        self.frameguard = guard;
+        self.framing = None;
+        self
+    }
+
+    /// Derive `X-Frame-Options` and the CSP `frame-ancestors` directive from a single policy.
+    ///
+    /// The directive is merged into the configured `Content-Security-Policy` when the headers
+    /// are applied, or sent as a policy of its own if there is none. `X-Frame-Options` is
+    /// omitted, and removed if already present, when it can't express the policy. Replaces
+    /// [`Armor::frameguard`].
+    ///
+    /// ## Examples
+    /// ```
+    /// use armor::framing::FramingPolicy;
+    ///
+    /// let mut armor = armor::Armor::new();
+    /// armor.framing(FramingPolicy::deny());
+    ///
+    /// let mut headers = http::HeaderMap::new();
+    /// armor.apply(&mut headers);
+    /// assert_eq!(headers["X-Frame-Options"], "deny");
+    /// assert!(headers["Content-Security-Policy"].to_str().unwrap().contains("frame-ancestors 'none'"));
+    /// ```
+    pub fn framing(&mut self, policy: FramingPolicy) -> &mut Self {
+        self.frameguard = None;
+        self.framing = Some(policy);
+        self
+    }
+
+    /// Check whether the configured `X-Frame-Options` and CSP `frame-ancestors` directive allow
+    /// different sites to frame the page. See [`framing::check`].
+    ///
+    /// ## Examples
+    /// ```
+    /// let mut armor = armor::Armor::new();
+    /// assert_eq!(armor.framing_conflict(), None);
+    ///
+    /// armor.frameguard(Some(armor::FrameOptions::Deny));
+    /// assert!(armor.framing_conflict().is_some());
+    /// ```
+    pub fn framing_conflict(&self) -> Option<FramingConflict> {
+        let mut headers = HeaderMap::new();
+        self.apply(&mut headers);
+        framing::check(&headers).err()
+    }
+
+    /// Set whether to remove the `X-Powered-By` header.
+    pub fn hide_powered_by(&mut self, enabled: bool) -> &mut Self {
+        self.hide_powered_by = enabled;
+        self
+    }
+
+    /// Set the `Strict-Transport-Security` options.
+    pub fn hsts(&mut self, options: Option<HstsOptions>) -> &mut Self {
+        self.hsts = options;
+        self
+    }
+
+    /// Set whether to send `X-Download-Options: noopen`. Only enabled in [`Preset::Modern`].
+    pub fn ie_no_open(&mut self, enabled: bool) -> &mut Self {
+        self.ie_no_open = enabled;
+        self
+    }
+
+    /// Strip or replace headers that leak implementation details. Disabled in every preset.
+    pub fn leaks(&mut self, stripper: Option<LeakStripper>) -> &mut Self {
+        self.leaks = stripper;
+        self
+    }
+
+    /// Set whether to send `Origin-Agent-Cluster: ?1`. Only enabled in [`Preset::Modern`].
+    pub fn origin_agent_cluster(&mut self, enabled: bool) -> &mut Self {
+        self.origin_agent_cluster = enabled;
+        self
+    }
+
+    /// Set the `X-Permitted-Cross-Domain-Policies` level. Only set in [`Preset::Modern`].
+    pub fn permitted_cross_domain_policies(
+        &mut self,
+        policy: Option<CrossDomainOptions>,
+    ) -> &mut Self {
+        self.permitted_cross_domain_policies = policy;
+        self
+    }
+
+    /// Set the `Permissions-Policy`.
+    pub fn permissions_policy(&mut self, policy: Option<PermissionsPolicy>) -> &mut Self {
+        self.permissions_policy = policy;
+        self
+    }
+
+    /// Set the `Referrer-Policy` level.
+    pub fn referrer_policy(&mut self, referrer: Option<ReferrerOptions>) -> &mut Self {
+        self.referrer_policy = referrer;
+        self
+    }
+
+    /// Set the `X-XSS-Protection` mode.
+    pub fn xss_filter(&mut self, options: Option<XssOptions>) -> &mut Self {
+        self.xss_filter = options;
+        self
+    }
+
+    /// Apply all configured protections.
+    pub fn apply(&self, headers: &mut HeaderMap) {
+        self.apply_common(headers);
+        if let Some(ref policy) = self.cookies {
+            policy.apply(headers);
+        }
+        if let Some(ref options) = self.hsts {
+            options.apply(headers);
+        }
+    }
+
+    /// Apply all configured protections that make sense for the given request.
+    ///
+    /// `Strict-Transport-Security` and the `Secure` attribute of hardened cookies are only set
+    /// for HTTPS requests to non-local hosts. See [`hsts_for`](crate::hsts_for) and
+    /// [`CookiePolicy::apply_for`].
+    pub fn apply_for(&self, headers: &mut HeaderMap, ctx: &RequestContext) {
+        self.apply_common(headers);
+        if let Some(ref policy) = self.cookies {
+            policy.apply_for(headers, ctx);
+        }
+        if let Some(ref options) = self.hsts {
+            options.apply_for(headers, ctx);
+        }
+    }
+
+    /// Apply the protections that don't depend on the request.
+    fn apply_common(&self, headers: &mut HeaderMap) {
+        if let Some(ref policy) = self.content_security_policy {
+            policy.apply(headers);
+        }
+        if let Some(ref policy) = self.cross_origin_embedder_policy {
+            policy.apply(headers);
+        }
+        if let Some(ref policy) = self.cross_origin_opener_policy {
+            policy.apply(headers);
+        }
+        if let Some(ref policy) = self.cross_origin_resource_policy {
+            policy.apply(headers);
+        }
+        if let Some(allow) = self.dns_prefetch_control {
+            crate::dns_prefetch_control(headers, allow);
+        }
+        if self.dont_sniff_mimetype {
+            crate::dont_sniff_mimetype(headers);
+        }
+        // Applied after the configured policy so the directive is merged into it.
+        match (&self.framing, &self.frameguard) {
+            (Some(policy), _) => policy.apply(headers),
+            (None, Some(guard)) => crate::frameguard(headers, Some(guard.clone())),
+            (None, None) => {}
+        }
+        if self.hide_powered_by {
+            crate::hide_powered_by(headers);
+        }
+        if self.ie_no_open {
+            crate::ie_no_open(headers);
+        }
+        if let Some(ref stripper) = self.leaks {
+            stripper.apply(headers);
+        }
+        if self.origin_agent_cluster {
+            crate::origin_agent_cluster(headers);
+        }
+        if let Some(ref policy) = self.permitted_cross_domain_policies {
+            crate::permitted_cross_domain_policies(headers, Some(policy.clone()));
+        }
+        if let Some(ref policy) = self.permissions_policy {
+            policy.apply(headers);
+        }
+        if let Some(referrer) = self.referrer_policy {
+            headers.remove("Referrer-Policy");
+            crate::referrer_policy(headers, Some(referrer));
+        }
+        if let Some(ref options) = self.xss_filter {
+            crate::xss_filter(headers, Some(options.clone()));
+        }
+    }
+}
+
+/// The `Content-Security-Policy` helmet v7 sets by default.
+fn modern_csp() -> ContentSecurityPolicy {
+    let mut policy = csp::new();
+    policy
+        .default_src(Source::SameOrigin)
+        .base_uri(Source::SameOrigin)
+        .font_src(Source::SameOrigin)
+        .font_src(Source::HTTPS)
+        .font_src(Source::Data)
+        .form_action(Source::SameOrigin)
+        .frame_ancestors(Source::SameOrigin)
+        .img_src(Source::SameOrigin)
+        .img_src(Source::Data)
+        .object_src(Source::None)
+        .script_src(Source::SameOrigin)
+        .script_src_attr(Source::None)
+        .style_src(Source::SameOrigin)
+        .style_src(Source::HTTPS)
+        .style_src(Source::UnsafeInline)
+        .upgrade_insecure_requests();
+    policy
+}
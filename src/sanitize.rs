@@ -0,0 +1,220 @@
+//! Strip request headers that can bypass access control.
+//!
+//! Some proxies and frameworks honor headers that rewrite the request's URL or method, such as
+//! `X-Original-URL` or `X-HTTP-Method-Override`, which lets clients reach paths that a proxy
+//! in front of the application was supposed to block. Forwarding headers like
+//! `X-Forwarded-For` are only trustworthy when a proxy set them. A [`RequestSanitizer`] removes
+//! denied headers from every request, and forwarding headers from requests that didn't come
+//! from a trusted proxy.
+//!
+//! # Examples
+//!
+//! ```
+//! use armor::sanitize::RemovalReason;
+//!
+//! let mut sanitizer = armor::sanitize::new();
+//! sanitizer.trust_proxy("10.0.0.0/8").unwrap();
+//!
+//! let mut headers = http::HeaderMap::new();
+//! headers.insert("X-Original-URL", "/admin".parse().unwrap());
+//! headers.insert("X-Forwarded-For", "203.0.113.7".parse().unwrap());
+//!
+//! let removed = sanitizer.sanitize(&mut headers, "198.51.100.1".parse().unwrap());
+//! assert_eq!(removed.len(), 2);
+//! assert_eq!(removed[0].reason(), RemovalReason::Denied);
+//! assert_eq!(removed[1].name(), "x-forwarded-for");
+//! assert_eq!(removed[1].reason(), RemovalReason::UntrustedProxy);
+//! assert!(headers.is_empty());
+//! ```
+
+use crate::host;
+use crate::ParseError;
+use http::header::HeaderName;
+use http::{HeaderMap, Request};
+use ipnet::IpNet;
+use std::fmt;
+use std::net::IpAddr;
+
+/// Headers that rewrite the request's URL or method.
+const DENIED_HEADERS: &[&str] = &[
+    "x-original-url",
+    "x-rewrite-url",
+    "x-override-url",
+    "x-http-method-override",
+    "x-http-method",
+    "x-method-override",
+];
+
+/// Headers that describe the client or the original request, set by proxies.
+const FORWARDING_HEADERS: &[&str] = &[
+    "forwarded",
+    "x-forwarded-*",
+    "x-real-ip",
+    "x-client-ip",
+    "true-client-ip",
+    "x-originating-ip",
+    "x-remote-ip",
+    "x-remote-addr",
+    "x-host",
+];
+
+/// Why a header was removed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RemovalReason {
+    /// The header is on the denylist.
+    Denied,
+    /// A forwarding header was sent by a client that isn't a trusted proxy.
+    UntrustedProxy,
+}
+
+/// A header removed by a [`RequestSanitizer`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemovedHeader {
+    name: HeaderName,
+    reason: RemovalReason,
+}
+
+impl RemovedHeader {
+    /// The name of the removed header.
+    pub fn name(&self) -> &str {
+        self.name.as_str()
+    }
+
+    /// Why the header was removed.
+    pub fn reason(&self) -> RemovalReason {
+        self.reason
+    }
+}
+
+impl fmt::Display for RemovedHeader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.reason {
+            RemovalReason::Denied => write!(f, "removed denied header `{}`", self.name),
+            RemovalReason::UntrustedProxy => write!(
+                f,
+                "removed forwarding header `{}` from an untrusted client",
+                self.name
+            ),
+        }
+    }
+}
+
+/// Build the request sanitizer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestSanitizer {
+    denied: Vec<String>,
+    forwarding: Vec<String>,
+    allowed: Vec<String>,
+    trusted_proxies: Vec<IpNet>,
+}
+
+impl Default for RequestSanitizer {
+    /// Denies URL and method override headers, and trusts no proxies.
+    fn default() -> Self {
+        let owned = |names: &[&str]| names.iter().map(|name| name.to_string()).collect();
+        Self {
+            denied: owned(DENIED_HEADERS),
+            forwarding: owned(FORWARDING_HEADERS),
+            allowed: Vec::new(),
+            trusted_proxies: Vec::new(),
+        }
+    }
+}
+
+impl RequestSanitizer {
+    /// Create a new instance.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Always remove a header. A trailing `*` matches every header with that prefix.
+    pub fn deny<T: AsRef<str>>(&mut self, header: T) -> &mut Self {
+        let header = header.as_ref().to_ascii_lowercase();
+        self.allowed.retain(|allowed| *allowed != header);
+        if !self.denied.contains(&header) {
+            self.denied.push(header);
+        }
+        self
+    }
+
+    /// Keep a header that's removed by default, e.g. `X-HTTP-Method-Override` for clients that
+    /// can't send `PUT` or `DELETE`.
+    pub fn allow<T: AsRef<str>>(&mut self, header: T) -> &mut Self {
+        let header = header.as_ref().to_ascii_lowercase();
+        self.denied.retain(|denied| *denied != header);
+        if !self.allowed.contains(&header) {
+            self.allowed.push(header);
+        }
+        self
+    }
+
+    /// Treat a header as a forwarding header, only kept when sent by a trusted proxy. A
+    /// trailing `*` matches every header with that prefix.
+    ///
+    /// `Forwarded`, `X-Forwarded-*`, `X-Real-IP` and similar headers are included by default.
+    pub fn forwarding_header<T: AsRef<str>>(&mut self, header: T) -> &mut Self {
+        let header = header.as_ref().to_ascii_lowercase();
+        if !self.forwarding.contains(&header) {
+            self.forwarding.push(header);
+        }
+        self
+    }
+
+    /// Trust forwarding headers from a proxy address or network, e.g. `10.0.0.0/8` or `::1`.
+    pub fn trust_proxy<T: AsRef<str>>(&mut self, cidr: T) -> Result<&mut Self, ParseError> {
+        self.trusted_proxies.push(host::parse_cidr(cidr.as_ref())?);
+        Ok(self)
+    }
+
+    /// Whether an address is a trusted proxy.
+    pub fn is_trusted(&self, peer: IpAddr) -> bool {
+        host::is_trusted(&self.trusted_proxies, peer)
+    }
+
+    /// Remove dangerous headers from a request received from `peer`.
+    pub fn sanitize_request<B>(&self, req: &mut Request<B>, peer: IpAddr) -> Vec<RemovedHeader> {
+        self.sanitize(req.headers_mut(), peer)
+    }
+
+    /// Remove dangerous headers from request headers received from `peer`, returning what was
+    /// removed in header order.
+    pub fn sanitize(&self, headers: &mut HeaderMap, peer: IpAddr) -> Vec<RemovedHeader> {
+        let trusted = self.is_trusted(peer);
+        let mut removed = Vec::new();
+        for name in headers.keys() {
+            let name_str = name.as_str();
+            let reason = if matches(&self.allowed, name_str) {
+                continue;
+            } else if matches(&self.denied, name_str) {
+                RemovalReason::Denied
+            } else if !trusted && matches(&self.forwarding, name_str) {
+                RemovalReason::UntrustedProxy
+            } else {
+                continue;
+            };
+            removed.push(RemovedHeader {
+                name: name.clone(),
+                reason,
+            });
+        }
+        for header in &removed {
+            headers.remove(&header.name);
+        }
+        removed
+    }
+}
+
+/// Create a new instance.
+pub fn new() -> RequestSanitizer {
+    RequestSanitizer::new()
+}
+
+/// Whether a lowercase header name matches a list of names and `prefix-*` patterns.
+fn matches(patterns: &[String], name: &str) -> bool {
+    patterns
+        .iter()
+        .any(|pattern| match pattern.strip_suffix('*') {
+            Some(prefix) => name.starts_with(prefix),
+            None => pattern == name,
+        })
+}
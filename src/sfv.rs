@@ -0,0 +1,620 @@
+//! Parse and serialize Structured Field Values.
+//!
+//! Structured Fields ([RFC 8941](https://www.rfc-editor.org/rfc/rfc8941)) are the common
+//! syntax used by newer headers such as `Permissions-Policy`, `Reporting-Endpoints` and
+//! `Cross-Origin-Embedder-Policy`. Values are either a [`List`], a [`Dictionary`] or a
+//! single [`Item`], which is decided by the header's definition.
+//!
+//! # Examples
+//!
+//! ```
+//! use armor::sfv::{self, BareItem, ListEntry};
+//!
+//! let dict = sfv::parse_dictionary(r#"default="https://example.com/reports", csp=:AQID:"#).unwrap();
+//! assert_eq!(dict[0].0, "default");
+//! match &dict[1].1 {
+//!     ListEntry::Item(item) => assert_eq!(item.bare_item, BareItem::ByteSeq(vec![1, 2, 3])),
+//!     ListEntry::InnerList(_) => unreachable!(),
+//! }
+//!
+//! assert_eq!(sfv::serialize_dictionary(&dict).unwrap(), r#"default="https://example.com/reports", csp=:AQID:"#);
+//! ```
+
+use crate::ParseError;
+use base64::alphabet;
+use base64::engine::general_purpose::{GeneralPurpose, GeneralPurposeConfig, STANDARD};
+use base64::engine::DecodePaddingMode;
+use base64::Engine;
+use std::fmt::Write;
+
+/// Decodes byte sequences with or without padding, which RFC 8941 §4.2.7 asks parsers to
+/// accept.
+const LENIENT: GeneralPurpose = GeneralPurpose::new(
+    &alphabet::STANDARD,
+    GeneralPurposeConfig::new()
+        .with_decode_padding_mode(DecodePaddingMode::Indifferent)
+        .with_decode_allow_trailing_bits(true),
+);
+
+/// An ordered map of keys to bare items, attached to items and inner lists.
+pub type Parameters = Vec<(String, BareItem)>;
+
+/// A list of items and inner lists.
+pub type List = Vec<ListEntry>;
+
+/// An ordered map of keys to items and inner lists.
+pub type Dictionary = Vec<(String, ListEntry)>;
+
+/// A value without parameters.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BareItem {
+    /// An integer in the range ±999,999,999,999,999
+    Integer(i64),
+    /// A decimal with at most 12 integer and 3 fractional digits
+    Decimal(f64),
+    /// A string of printable ASCII characters
+    String(String),
+    /// A short textual word, e.g. `self`
+    Token(String),
+    /// Binary content, serialized as base64
+    ByteSeq(Vec<u8>),
+    /// A boolean, serialized as `?1` or `?0`
+    Boolean(bool),
+}
+
+impl BareItem {
+    /// Get the value of a token.
+    pub fn as_token(&self) -> Option<&str> {
+        match *self {
+            BareItem::Token(ref token) => Some(token),
+            _ => None,
+        }
+    }
+
+    /// Get the value of a string.
+    pub fn as_string(&self) -> Option<&str> {
+        match *self {
+            BareItem::String(ref string) => Some(string),
+            _ => None,
+        }
+    }
+}
+
+/// A bare item with parameters.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Item {
+    /// The item's value
+    pub bare_item: BareItem,
+    /// The item's parameters
+    pub params: Parameters,
+}
+
+impl Item {
+    /// Create a new item without parameters.
+    pub fn new(bare_item: BareItem) -> Self {
+        Self {
+            bare_item,
+            params: Vec::new(),
+        }
+    }
+
+    /// Add a parameter.
+    pub fn param<T: Into<String>>(mut self, key: T, value: BareItem) -> Self {
+        insert(&mut self.params, key.into(), value);
+        self
+    }
+}
+
+impl From<BareItem> for Item {
+    fn from(bare_item: BareItem) -> Self {
+        Self::new(bare_item)
+    }
+}
+
+/// A parenthesized list of items with parameters.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct InnerList {
+    /// The list's items
+    pub items: Vec<Item>,
+    /// The list's parameters
+    pub params: Parameters,
+}
+
+impl InnerList {
+    /// Create a new inner list without parameters.
+    pub fn new(items: Vec<Item>) -> Self {
+        Self {
+            items,
+            params: Vec::new(),
+        }
+    }
+}
+
+/// A member of a [`List`] or value of a [`Dictionary`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum ListEntry {
+    /// A single item
+    Item(Item),
+    /// An inner list
+    InnerList(InnerList),
+}
+
+impl ListEntry {
+    /// The entry's parameters.
+    pub fn params(&self) -> &Parameters {
+        match *self {
+            ListEntry::Item(ref item) => &item.params,
+            ListEntry::InnerList(ref list) => &list.params,
+        }
+    }
+}
+
+impl From<Item> for ListEntry {
+    fn from(item: Item) -> Self {
+        ListEntry::Item(item)
+    }
+}
+
+impl From<InnerList> for ListEntry {
+    fn from(list: InnerList) -> Self {
+        ListEntry::InnerList(list)
+    }
+}
+
+/// Look up a value in a [`Parameters`] or [`Dictionary`] map.
+pub fn get<'a, V>(map: &'a [(String, V)], key: &str) -> Option<&'a V> {
+    map.iter().find(|(k, _)| k == key).map(|(_, v)| v)
+}
+
+/// Insert into an ordered map, overwriting the value of an existing key in place.
+fn insert<V>(map: &mut Vec<(String, V)>, key: String, value: V) {
+    match map.iter_mut().find(|(k, _)| *k == key) {
+        Some(entry) => entry.1 = value,
+        None => map.push((key, value)),
+    }
+}
+
+/// Parse a header value as an [`Item`].
+pub fn parse_item(input: &str) -> Result<Item, ParseError> {
+    let mut parser = Parser::new(input)?;
+    parser.skip_sp();
+    let item = parser.item()?;
+    parser.finish()?;
+    Ok(item)
+}
+
+/// Parse a header value as a [`List`].
+///
+/// Multiple header lines should be joined with `,` before parsing.
+pub fn parse_list(input: &str) -> Result<List, ParseError> {
+    let mut parser = Parser::new(input)?;
+    parser.skip_sp();
+    let mut list = Vec::new();
+    while !parser.is_empty() {
+        list.push(parser.list_entry()?);
+        if !parser.next_member()? {
+            break;
+        }
+    }
+    parser.finish()?;
+    Ok(list)
+}
+
+/// Parse a header value as a [`Dictionary`].
+///
+/// Multiple header lines should be joined with `,` before parsing.
+pub fn parse_dictionary(input: &str) -> Result<Dictionary, ParseError> {
+    let mut parser = Parser::new(input)?;
+    parser.skip_sp();
+    let mut dict = Vec::new();
+    while !parser.is_empty() {
+        let key = parser.key()?;
+        let entry = if parser.eat(b'=') {
+            parser.list_entry()?
+        } else {
+            let params = parser.params()?;
+            ListEntry::Item(Item {
+                bare_item: BareItem::Boolean(true),
+                params,
+            })
+        };
+        insert(&mut dict, key, entry);
+        if !parser.next_member()? {
+            break;
+        }
+    }
+    parser.finish()?;
+    Ok(dict)
+}
+
+/// Serialize an [`Item`].
+pub fn serialize_item(item: &Item) -> Result<String, ParseError> {
+    let mut out = String::new();
+    write_item(&mut out, item)?;
+    Ok(out)
+}
+
+/// Serialize a [`List`].
+pub fn serialize_list(list: &[ListEntry]) -> Result<String, ParseError> {
+    let mut out = String::new();
+    for (idx, entry) in list.iter().enumerate() {
+        if idx > 0 {
+            out.push_str(", ");
+        }
+        write_list_entry(&mut out, entry)?;
+    }
+    Ok(out)
+}
+
+/// Serialize a [`Dictionary`].
+///
+/// Items with the value `true` are serialized as a bare key.
+pub fn serialize_dictionary(dict: &[(String, ListEntry)]) -> Result<String, ParseError> {
+    let mut out = String::new();
+    for (idx, (key, entry)) in dict.iter().enumerate() {
+        if idx > 0 {
+            out.push_str(", ");
+        }
+        write_key(&mut out, key)?;
+        match *entry {
+            ListEntry::Item(Item {
+                bare_item: BareItem::Boolean(true),
+                ref params,
+            }) => write_params(&mut out, params)?,
+            _ => {
+                out.push('=');
+                write_list_entry(&mut out, entry)?;
+            }
+        }
+    }
+    Ok(out)
+}
+
+fn write_list_entry(out: &mut String, entry: &ListEntry) -> Result<(), ParseError> {
+    match *entry {
+        ListEntry::Item(ref item) => write_item(out, item),
+        ListEntry::InnerList(ref list) => {
+            out.push('(');
+            for (idx, item) in list.items.iter().enumerate() {
+                if idx > 0 {
+                    out.push(' ');
+                }
+                write_item(out, item)?;
+            }
+            out.push(')');
+            write_params(out, &list.params)
+        }
+    }
+}
+
+fn write_item(out: &mut String, item: &Item) -> Result<(), ParseError> {
+    write_bare_item(out, &item.bare_item)?;
+    write_params(out, &item.params)
+}
+
+fn write_params(out: &mut String, params: &[(String, BareItem)]) -> Result<(), ParseError> {
+    for (key, value) in params {
+        out.push(';');
+        write_key(out, key)?;
+        if *value != BareItem::Boolean(true) {
+            out.push('=');
+            write_bare_item(out, value)?;
+        }
+    }
+    Ok(())
+}
+
+fn write_key(out: &mut String, key: &str) -> Result<(), ParseError> {
+    if !is_key(key) {
+        return Err(ParseError::new(format!("invalid key `{}`", key)));
+    }
+    out.push_str(key);
+    Ok(())
+}
+
+fn write_bare_item(out: &mut String, item: &BareItem) -> Result<(), ParseError> {
+    match *item {
+        BareItem::Integer(int) => {
+            if !(-999_999_999_999_999..=999_999_999_999_999).contains(&int) {
+                return Err(ParseError::new("integer out of range"));
+            }
+            write!(out, "{}", int).unwrap();
+        }
+        BareItem::Decimal(dec) => {
+            let rounded = round_half_even(dec * 1000.0) / 1000.0;
+            if !rounded.is_finite() || rounded.abs() >= 1_000_000_000_000.0 {
+                return Err(ParseError::new("decimal out of range"));
+            }
+            let mut formatted = format!("{:.3}", rounded);
+            while formatted.ends_with('0') && !formatted.ends_with(".0") {
+                formatted.pop();
+            }
+            out.push_str(&formatted);
+        }
+        BareItem::String(ref string) => {
+            out.push('"');
+            for c in string.chars() {
+                match c {
+                    '"' | '\\' => {
+                        out.push('\\');
+                        out.push(c);
+                    }
+                    ' '..='~' => out.push(c),
+                    _ => return Err(ParseError::new("strings must be printable ASCII")),
+                }
+            }
+            out.push('"');
+        }
+        BareItem::Token(ref token) => {
+            let mut bytes = token.bytes();
+            let valid = match bytes.next() {
+                Some(c) => (c.is_ascii_alphabetic() || c == b'*') && bytes.all(is_token_char),
+                None => false,
+            };
+            if !valid {
+                return Err(ParseError::new(format!("invalid token `{}`", token)));
+            }
+            out.push_str(token);
+        }
+        BareItem::ByteSeq(ref bytes) => {
+            out.push(':');
+            out.push_str(&STANDARD.encode(bytes));
+            out.push(':');
+        }
+        BareItem::Boolean(boolean) => out.push_str(if boolean { "?1" } else { "?0" }),
+    }
+    Ok(())
+}
+
+fn round_half_even(value: f64) -> f64 {
+    let rounded = value.round();
+    if (value - value.trunc()).abs() == 0.5 && rounded % 2.0 != 0.0 {
+        rounded - value.signum()
+    } else {
+        rounded
+    }
+}
+
+/// Whether a string is a valid key.
+pub(crate) fn is_key(key: &str) -> bool {
+    let mut bytes = key.bytes();
+    match bytes.next() {
+        Some(c) => is_key_start(c) && bytes.all(is_key_char),
+        None => false,
+    }
+}
+
+fn is_key_start(c: u8) -> bool {
+    c.is_ascii_lowercase() || c == b'*'
+}
+
+fn is_key_char(c: u8) -> bool {
+    c.is_ascii_lowercase() || c.is_ascii_digit() || b"_-.*".contains(&c)
+}
+
+fn is_token_char(c: u8) -> bool {
+    c.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~:/".contains(&c)
+}
+
+struct Parser<'a> {
+    input: &'a [u8],
+}
+
+impl<'a> Parser<'a> {
+    fn new(input: &'a str) -> Result<Self, ParseError> {
+        if !input.is_ascii() {
+            return Err(ParseError::new("structured fields must be ASCII"));
+        }
+        Ok(Self {
+            input: input.as_bytes(),
+        })
+    }
+
+    fn is_empty(&self) -> bool {
+        self.input.is_empty()
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.input.first().copied()
+    }
+
+    fn eat(&mut self, c: u8) -> bool {
+        if self.peek() == Some(c) {
+            self.input = &self.input[1..];
+            true
+        } else {
+            false
+        }
+    }
+
+    fn take_while<F: Fn(u8) -> bool>(&mut self, f: F) -> &'a str {
+        let end = self
+            .input
+            .iter()
+            .position(|&c| !f(c))
+            .unwrap_or(self.input.len());
+        let (taken, rest) = self.input.split_at(end);
+        self.input = rest;
+        // The input was checked to be ASCII when the parser was created.
+        std::str::from_utf8(taken).unwrap()
+    }
+
+    fn skip_sp(&mut self) {
+        self.take_while(|c| c == b' ');
+    }
+
+    fn skip_ows(&mut self) {
+        self.take_while(|c| c == b' ' || c == b'\t');
+    }
+
+    fn finish(&mut self) -> Result<(), ParseError> {
+        self.skip_sp();
+        if self.is_empty() {
+            Ok(())
+        } else {
+            Err(ParseError::new("unexpected trailing characters"))
+        }
+    }
+
+    /// Move past the `,` separating list or dictionary members.
+    ///
+    /// Returns `false` when the end of the input was reached.
+    fn next_member(&mut self) -> Result<bool, ParseError> {
+        self.skip_ows();
+        if self.is_empty() {
+            return Ok(false);
+        }
+        if !self.eat(b',') {
+            return Err(ParseError::new("expected `,` between members"));
+        }
+        self.skip_ows();
+        if self.is_empty() {
+            return Err(ParseError::new("trailing `,`"));
+        }
+        Ok(true)
+    }
+
+    fn list_entry(&mut self) -> Result<ListEntry, ParseError> {
+        if self.peek() == Some(b'(') {
+            self.inner_list().map(ListEntry::InnerList)
+        } else {
+            self.item().map(ListEntry::Item)
+        }
+    }
+
+    fn inner_list(&mut self) -> Result<InnerList, ParseError> {
+        self.eat(b'(');
+        let mut items = Vec::new();
+        loop {
+            self.skip_sp();
+            if self.eat(b')') {
+                let params = self.params()?;
+                return Ok(InnerList { items, params });
+            }
+            items.push(self.item()?);
+            match self.peek() {
+                Some(b' ') | Some(b')') => {}
+                _ => return Err(ParseError::new("unterminated inner list")),
+            }
+        }
+    }
+
+    fn item(&mut self) -> Result<Item, ParseError> {
+        let bare_item = self.bare_item()?;
+        let params = self.params()?;
+        Ok(Item { bare_item, params })
+    }
+
+    fn params(&mut self) -> Result<Parameters, ParseError> {
+        let mut params = Vec::new();
+        while self.eat(b';') {
+            self.skip_sp();
+            let key = self.key()?;
+            let value = if self.eat(b'=') {
+                self.bare_item()?
+            } else {
+                BareItem::Boolean(true)
+            };
+            insert(&mut params, key, value);
+        }
+        Ok(params)
+    }
+
+    fn key(&mut self) -> Result<String, ParseError> {
+        match self.peek() {
+            Some(c) if is_key_start(c) => Ok(self.take_while(is_key_char).to_string()),
+            _ => Err(ParseError::new("expected a key")),
+        }
+    }
+
+    fn bare_item(&mut self) -> Result<BareItem, ParseError> {
+        match self.peek() {
+            Some(b'-') | Some(b'0'..=b'9') => self.number(),
+            Some(b'"') => self.string(),
+            Some(b':') => self.byte_seq(),
+            Some(b'?') => self.boolean(),
+            Some(c) if c.is_ascii_alphabetic() || c == b'*' => {
+                Ok(BareItem::Token(self.take_while(is_token_char).to_string()))
+            }
+            _ => Err(ParseError::new("expected an item")),
+        }
+    }
+
+    fn number(&mut self) -> Result<BareItem, ParseError> {
+        let negative = self.eat(b'-');
+        let digits = self.take_while(|c| c.is_ascii_digit() || c == b'.');
+        if !digits.starts_with(|c: char| c.is_ascii_digit()) {
+            return Err(ParseError::new("expected a digit"));
+        }
+        let sign = if negative { -1 } else { 1 };
+        match digits.find('.') {
+            None => {
+                if digits.len() > 15 {
+                    return Err(ParseError::new("integer too long"));
+                }
+                Ok(BareItem::Integer(sign * digits.parse::<i64>().unwrap()))
+            }
+            Some(dot) => {
+                let fraction = &digits[dot + 1..];
+                if dot > 12 || fraction.is_empty() || fraction.len() > 3 || fraction.contains('.') {
+                    return Err(ParseError::new("invalid decimal"));
+                }
+                Ok(BareItem::Decimal(
+                    sign as f64 * digits.parse::<f64>().unwrap(),
+                ))
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<BareItem, ParseError> {
+        self.eat(b'"');
+        let mut string = String::new();
+        loop {
+            match self.peek() {
+                Some(b'"') => {
+                    self.eat(b'"');
+                    return Ok(BareItem::String(string));
+                }
+                Some(b'\\') => {
+                    self.eat(b'\\');
+                    match self.peek() {
+                        Some(c) if c == b'"' || c == b'\\' => {
+                            self.eat(c);
+                            string.push(c as char);
+                        }
+                        _ => return Err(ParseError::new("invalid string escape")),
+                    }
+                }
+                Some(c) if (b' '..=b'~').contains(&c) => {
+                    self.eat(c);
+                    string.push(c as char);
+                }
+                Some(_) => return Err(ParseError::new("invalid character in string")),
+                None => return Err(ParseError::new("unterminated string")),
+            }
+        }
+    }
+
+    fn byte_seq(&mut self) -> Result<BareItem, ParseError> {
+        self.eat(b':');
+        let encoded = self.take_while(|c| c.is_ascii_alphanumeric() || b"+/=".contains(&c));
+        if !self.eat(b':') {
+            return Err(ParseError::new("unterminated byte sequence"));
+        }
+        LENIENT
+            .decode(encoded)
+            .map(BareItem::ByteSeq)
+            .map_err(|_| ParseError::new("invalid base64 in byte sequence"))
+    }
+
+    fn boolean(&mut self) -> Result<BareItem, ParseError> {
+        self.eat(b'?');
+        if self.eat(b'1') {
+            Ok(BareItem::Boolean(true))
+        } else if self.eat(b'0') {
+            Ok(BareItem::Boolean(false))
+        } else {
+            Err(ParseError::new("invalid boolean"))
+        }
+    }
+}
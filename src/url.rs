@@ -0,0 +1,92 @@
+//! Just enough URL parsing to match origins and CSP source expressions.
+
+/// The parts of an absolute URL.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Url {
+    pub(crate) scheme: String,
+    pub(crate) host: Option<String>,
+    pub(crate) port: Option<u16>,
+    pub(crate) path: String,
+}
+
+impl Url {
+    /// Parse an absolute URL, lowercasing the scheme and host.
+    pub(crate) fn parse(url: &str) -> Option<Self> {
+        let (scheme, rest) = url.trim().split_once(':')?;
+        let valid_scheme = scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+            && scheme
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c));
+        if !valid_scheme {
+            return None;
+        }
+        let scheme = scheme.to_ascii_lowercase();
+        let rest = rest.split(['?', '#']).next().unwrap_or("");
+
+        let authority = match rest.strip_prefix("//") {
+            Some(rest) => rest,
+            None => {
+                return Some(Self {
+                    scheme,
+                    host: None,
+                    port: None,
+                    path: rest.to_string(),
+                })
+            }
+        };
+        let (authority, path) = match authority.find('/') {
+            Some(index) => authority.split_at(index),
+            None => (authority, "/"),
+        };
+        let authority = authority.rsplit('@').next().unwrap_or("");
+        let (host, port) = split_port(authority)?;
+        if host.is_empty() {
+            return None;
+        }
+        let port = match port {
+            Some(port) => Some(port.parse().ok()?),
+            None => None,
+        };
+        Some(Self {
+            scheme,
+            host: Some(host.to_ascii_lowercase()),
+            port,
+            path: path.to_string(),
+        })
+    }
+
+    /// The port, or the default port of the scheme.
+    pub(crate) fn effective_port(&self) -> Option<u16> {
+        self.port.or_else(|| default_port(&self.scheme))
+    }
+
+    /// The port, unless it's the default port of the scheme.
+    pub(crate) fn explicit_port(&self) -> Option<u16> {
+        self.port
+            .filter(|port| Some(*port) != default_port(&self.scheme))
+    }
+}
+
+/// Split an authority into its host and port, keeping IPv6 brackets on the host. A `*` port
+/// is returned as is.
+pub(crate) fn split_port(authority: &str) -> Option<(&str, Option<&str>)> {
+    let port_start = match authority.rfind(']') {
+        Some(end) => authority[end..].find(':').map(|index| end + index),
+        None if authority.starts_with('[') => return None,
+        None => authority.rfind(':'),
+    };
+    Some(match port_start {
+        Some(index) => (&authority[..index], Some(&authority[index + 1..])),
+        None => (authority, None),
+    })
+}
+
+/// The default port of a scheme.
+pub(crate) fn default_port(scheme: &str) -> Option<u16> {
+    match scheme {
+        "http" | "ws" => Some(80),
+        "https" | "wss" => Some(443),
+        "ftp" => Some(21),
+        _ => None,
+    }
+}
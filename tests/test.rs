@@ -23,3 +23,1825 @@ fn csp_test() {
 
     assert_eq!(headers["content-security-policy"], "base-uri 'none'; default-src 'self' areweasyncyet.rs; object-src 'none'; script-src 'self' 'unsafe-inline'; upgrade-insecure-requests");
 }
+
+#[test]
+fn hsts_only_for_https_non_local_requests() {
+    let req = http::Request::get("/")
+        .header("Host", "example.com")
+        .header("Forwarded", "for=192.0.2.60;proto=https;by=203.0.113.43")
+        .body(())
+        .unwrap();
+    let mut ctx = armor::RequestContext::from_request(&req);
+
+    let mut headers = http::HeaderMap::new();
+    armor::armor_for(&mut headers, &ctx);
+    assert_eq!(headers.get("Strict-Transport-Security"), None);
+
+    ctx.trust_proxy(true);
+    armor::armor_for(&mut headers, &ctx);
+    assert_eq!(headers["Strict-Transport-Security"], "max-age=5184000");
+
+    ctx.host("dev.localhost");
+    armor::armor_for(&mut headers, &ctx);
+    assert_eq!(headers.get("Strict-Transport-Security"), None);
+}
+
+#[test]
+fn permissions_policy_roundtrip() {
+    use armor::permissions::{Allow, Feature, FeatureName, PermissionsPolicy};
+
+    let mut policy = armor::permissions::new();
+    policy
+        .allow(Feature::Payment, Allow::SameOrigin)
+        .allow_origin(Feature::Payment, "https://pay.example")
+        .unwrap()
+        .allow(Feature::Fullscreen, Allow::Wildcard)
+        .deny(Feature::Usb)
+        .report_only();
+    let mut headers = http::HeaderMap::new();
+    policy.apply(&mut headers);
+
+    let value = headers["permissions-policy-report-only"].to_str().unwrap();
+    assert_eq!(
+        value,
+        r#"payment=(self "https://pay.example"), fullscreen=*, usb=()"#
+    );
+
+    let parsed = PermissionsPolicy::parse(value).unwrap();
+    assert_eq!(parsed.value(), value);
+    assert!(PermissionsPolicy::parse("camera=(self").is_err());
+    assert!(PermissionsPolicy::parse("Camera=()").is_err());
+
+    assert!(policy
+        .allow_origin(Feature::Camera, "https://a.example\u{7}")
+        .is_err());
+    assert!(policy.allow_origin(Feature::Camera, "a.example").is_err());
+    assert!("https://[::1]:8080".parse::<Allow>().is_ok());
+    assert_eq!(Allow::SameOrigin.to_string(), "self");
+    let origin: Allow = "https://a.example".parse().unwrap();
+    assert_eq!(origin.to_string(), r#""https://a.example""#);
+    let parsed = PermissionsPolicy::parse(r#"camera=("https://a.example; b"), usb=()"#).unwrap();
+    assert_eq!(parsed.value(), "usb=()");
+
+    let feature: Feature = "new-api".parse().unwrap();
+    assert_eq!(feature, Feature::Other("new-api".parse().unwrap()));
+    assert_eq!(
+        armor::permissions::new().deny(feature).value(),
+        "new-api=()"
+    );
+    assert!("Bad Key".parse::<Feature>().is_err());
+    assert!("bad\nkey".parse::<FeatureName>().is_err());
+}
+
+#[test]
+fn sfv_roundtrip() {
+    use armor::sfv::{self, BareItem, ListEntry};
+
+    let list =
+        sfv::parse_list(r#"abc;a=1;b=2; cde_456, (ghi;jk=4 l);q="9";r=w, ?0, -12.5"#).unwrap();
+    assert_eq!(list.len(), 4);
+    match &list[1] {
+        ListEntry::InnerList(inner) => {
+            assert_eq!(inner.items.len(), 2);
+            assert_eq!(
+                sfv::get(&inner.params, "q"),
+                Some(&BareItem::String("9".into()))
+            );
+        }
+        ListEntry::Item(_) => panic!("expected an inner list"),
+    }
+    assert_eq!(
+        sfv::serialize_list(&list).unwrap(),
+        r#"abc;a=1;b=2;cde_456, (ghi;jk=4 l);q="9";r=w, ?0, -12.5"#
+    );
+
+    let dict = sfv::parse_dictionary("a=?0, b, c; foo=bar, a=1").unwrap();
+    assert_eq!(
+        sfv::serialize_dictionary(&dict).unwrap(),
+        "a=1, b, c;foo=bar"
+    );
+
+    assert!(sfv::parse_item("1234567890123456").is_err());
+    assert!(sfv::parse_item("1.2345").is_err());
+    assert!(sfv::parse_list("a, b,").is_err());
+    assert!(sfv::parse_item(r#""\x""#).is_err());
+    let padded = sfv::parse_item(":aGk=:").unwrap();
+    assert_eq!(padded.bare_item, BareItem::ByteSeq(b"hi".to_vec()));
+    assert_eq!(sfv::parse_item(":aGk:").unwrap(), padded);
+    assert_eq!(sfv::serialize_item(&padded).unwrap(), ":aGk=:");
+    assert!(sfv::parse_item(":a:").is_err());
+    assert_eq!(
+        sfv::serialize_item(&BareItem::Decimal(1.0005).into()).unwrap(),
+        "1.0"
+    );
+}
+
+#[test]
+fn cross_origin_policies() {
+    use armor::cross_origin::{
+        CrossOriginEmbedderPolicy, CrossOriginOpenerPolicy, CrossOriginResourcePolicy,
+        EmbedderPolicy, OpenerPolicy, ResourcePolicy,
+    };
+
+    let coop =
+        CrossOriginOpenerPolicy::parse(r#"same-origin-allow-popups; report-to="default""#).unwrap();
+    assert_eq!(coop.policy(), OpenerPolicy::SameOriginAllowPopups);
+    assert_eq!(
+        coop.value(),
+        r#"same-origin-allow-popups;report-to="default""#
+    );
+
+    let coep = CrossOriginEmbedderPolicy::parse("credentialless").unwrap();
+    assert_eq!(coep.policy(), EmbedderPolicy::Credentialless);
+
+    let mut coep = CrossOriginEmbedderPolicy::new(EmbedderPolicy::RequireCorp);
+    assert!(coep.report_to("coep\r\nSet-Cookie: a=b").is_err());
+    assert!(coep.report_to("").is_err());
+    coep.report_to("default").unwrap();
+    assert_eq!(coep.value(), r#"require-corp;report-to="default""#);
+    assert!(CrossOriginEmbedderPolicy::parse("require-everything").is_err());
+
+    let mut headers = http::HeaderMap::new();
+    armor::cross_origin::resource_policy(ResourcePolicy::SameSite).apply(&mut headers);
+    assert_eq!(headers["cross-origin-resource-policy"], "same-site");
+    assert_eq!(
+        CrossOriginResourcePolicy::parse("same-site")
+            .unwrap()
+            .policy(),
+        ResourcePolicy::SameSite
+    );
+}
+
+#[test]
+fn permissions_policy_with_legacy_feature_policy() {
+    use armor::permissions::{Allow, Feature};
+
+    let mut policy = armor::permissions::new();
+    policy
+        .deny(Feature::Microphone)
+        .allow(Feature::Payment, Allow::SameOrigin)
+        .allow(Feature::Autoplay, Allow::Src)
+        .feature_policy();
+    let mut headers = http::HeaderMap::new();
+    policy.apply(&mut headers);
+    assert_eq!(
+        headers["permissions-policy"],
+        "microphone=(), payment=(self), autoplay=(src)"
+    );
+    assert_eq!(
+        headers["feature-policy"],
+        "microphone 'none'; payment 'self'; autoplay 'src'"
+    );
+
+    policy
+        .allow_origin(Feature::Geolocation, "https://*.maps.example")
+        .unwrap()
+        .allow_origin(Feature::Payment, "https://pay.example:8443")
+        .unwrap();
+    assert!(policy
+        .allow_origin(Feature::Payment, "https://pay.example; camera *")
+        .is_err());
+    assert_eq!(
+        policy.feature_policy_value(),
+        "microphone 'none'; payment 'self' https://pay.example:8443; autoplay 'src'; geolocation 'none'"
+    );
+
+    let mut headers = http::HeaderMap::new();
+    policy.report_only().apply(&mut headers);
+    assert_eq!(headers.get("feature-policy"), None);
+}
+
+#[test]
+fn presets() {
+    use armor::{Armor, Preset};
+
+    let mut legacy = http::HeaderMap::new();
+    armor::armor(&mut legacy);
+    let mut helmet2019 = http::HeaderMap::new();
+    Armor::preset(Preset::Helmet2019).apply(&mut helmet2019);
+    assert_eq!(legacy, helmet2019);
+
+    // armor() sends exactly the headers it sent before presets existed.
+    let mut baseline = http::HeaderMap::new();
+    baseline.insert("X-DNS-Prefetch-Control", "on".parse().unwrap());
+    baseline.insert("X-Content-Type-Options", "nosniff".parse().unwrap());
+    baseline.insert("X-Frame-Options", "sameorigin".parse().unwrap());
+    baseline.insert(
+        "Strict-Transport-Security",
+        "max-age=5184000".parse().unwrap(),
+    );
+    baseline.insert("X-XSS-Protection", "1; mode=block".parse().unwrap());
+    assert_eq!(legacy, baseline);
+
+    let mut headers = http::HeaderMap::new();
+    headers.insert("X-Powered-By", "Tide/Rust".parse().unwrap());
+    Armor::new().apply(&mut headers);
+    assert_eq!(headers.get("X-Powered-By"), None);
+    assert_eq!(headers["X-DNS-Prefetch-Control"], "off");
+    assert_eq!(headers["X-XSS-Protection"], "0");
+    assert_eq!(headers["Referrer-Policy"], "no-referrer");
+    assert_eq!(headers["Cross-Origin-Resource-Policy"], "same-origin");
+    assert_eq!(
+        headers["Strict-Transport-Security"],
+        "max-age=15552000; includeSubDomains"
+    );
+    assert_eq!(
+        headers["Content-Security-Policy"],
+        "base-uri 'self'; default-src 'self'; font-src 'self' https: data:; form-action 'self'; frame-ancestors 'self'; img-src 'self' data:; object-src 'none'; script-src 'self'; script-src-attr 'none'; style-src 'self' https: 'unsafe-inline'; upgrade-insecure-requests"
+    );
+}
+
+#[test]
+fn xss_filter_modes() {
+    use armor::XssOptions;
+
+    assert_eq!("0".parse::<XssOptions>().unwrap(), XssOptions::Disabled);
+    assert_eq!("1".parse::<XssOptions>().unwrap(), XssOptions::Enabled);
+    assert_eq!(
+        "1;MODE=Block".parse::<XssOptions>().unwrap(),
+        XssOptions::Block
+    );
+    assert_eq!(
+        "1; report=/xss-report".parse::<XssOptions>().unwrap(),
+        XssOptions::Report("/xss-report".parse().unwrap())
+    );
+    let combined = "1; mode=block; report=/r".parse::<XssOptions>().unwrap();
+    assert_eq!(combined, XssOptions::BlockReport("/r".parse().unwrap()));
+    assert_eq!(combined.value(), "1; mode=block; report=/r");
+    assert!("2".parse::<XssOptions>().is_err());
+    assert!("1; report=".parse::<XssOptions>().is_err());
+    assert!("1; mode=block; mode=block".parse::<XssOptions>().is_err());
+    assert!("/r\r\nSet-Cookie: a=b".parse::<armor::ReportUri>().is_err());
+    assert!("/r x".parse::<armor::ReportUri>().is_err());
+
+    let mut headers = http::HeaderMap::new();
+    armor::Armor::new()
+        .xss_filter(Some(XssOptions::Enabled))
+        .apply(&mut headers);
+    assert_eq!(headers["X-XSS-Protection"], "1");
+}
+
+#[test]
+fn dns_prefetch_control_setting() {
+    let mut headers = http::HeaderMap::new();
+    armor::Armor::preset(armor::Preset::Helmet2019)
+        .dns_prefetch_control(Some(false))
+        .apply(&mut headers);
+    assert_eq!(headers["X-DNS-Prefetch-Control"], "off");
+
+    let mut headers = http::HeaderMap::new();
+    armor::Armor::new()
+        .dns_prefetch_control(None)
+        .apply(&mut headers);
+    assert_eq!(headers.get("X-DNS-Prefetch-Control"), None);
+}
+
+#[test]
+fn framing_policy() {
+    use armor::framing::{self, FramingPolicy};
+    use armor::FrameOptions;
+
+    assert_eq!(
+        FramingPolicy::deny().frame_options(),
+        Some(FrameOptions::Deny)
+    );
+    assert_eq!(
+        FramingPolicy::same_origin().frame_options(),
+        Some(FrameOptions::SameOrigin)
+    );
+
+    let mut policy = armor::csp::new();
+    policy
+        .default_src(csp::Source::SameOrigin)
+        .frame_ancestors("https://old.example");
+    let mut framing = FramingPolicy::deny();
+    framing.allow("https://partner.example").unwrap();
+    for source in [
+        "https://a.com; script-src *",
+        "https://a.com, script-src *",
+        "https://a.com https://b.com",
+        "https://a.com\u{7}",
+        "'unsafe-inline'",
+        "",
+    ] {
+        assert!(framing.allow(source).is_err(), "{:?}", source);
+    }
+    assert_eq!(framing.ancestors(), ["https://partner.example"]);
+
+    let mut armor = armor::Armor::new();
+    armor.content_security_policy(Some(policy)).framing(framing);
+    assert_eq!(armor.framing_conflict(), None);
+
+    let mut headers = http::HeaderMap::new();
+    headers.insert("X-Frame-Options", "SAMEORIGIN".parse().unwrap());
+    armor.apply(&mut headers);
+    assert_eq!(headers.get("X-Frame-Options"), None);
+    assert_eq!(
+        headers["Content-Security-Policy"],
+        "default-src 'self'; frame-ancestors https://partner.example"
+    );
+    assert!(framing::check(&headers).is_ok());
+
+    headers.insert("X-Frame-Options", "SAMEORIGIN".parse().unwrap());
+    let conflict = framing::check(&headers).unwrap_err();
+    assert_eq!(conflict.frame_options(), "sameorigin");
+    assert_eq!(conflict.frame_ancestors(), ["https://partner.example"]);
+
+    // The framing policy is applied last, whatever order the setters were called in.
+    let mut policy = armor::csp::new();
+    policy.default_src(csp::Source::SameOrigin);
+    let mut reversed = armor::Armor::new();
+    reversed
+        .framing(FramingPolicy::deny())
+        .content_security_policy(Some(policy));
+    let mut headers = http::HeaderMap::new();
+    reversed.apply(&mut headers);
+    assert_eq!(
+        headers["Content-Security-Policy"],
+        "default-src 'self'; frame-ancestors 'none'"
+    );
+    assert_eq!(headers["X-Frame-Options"], "deny");
+
+    // Existing policies keep their other directives.
+    let mut headers = http::HeaderMap::new();
+    headers.append(
+        "Content-Security-Policy",
+        "script-src 'self'".parse().unwrap(),
+    );
+    headers.append("Content-Security-Policy", "img-src *".parse().unwrap());
+    FramingPolicy::same_origin().apply(&mut headers);
+    let values: Vec<_> = headers.get_all("Content-Security-Policy").iter().collect();
+    assert_eq!(
+        values,
+        [
+            "frame-ancestors 'self'; script-src 'self'",
+            "frame-ancestors 'self'; img-src *"
+        ]
+    );
+}
+
+#[test]
+fn legacy_helmet_protections() {
+    use armor::{Armor, CrossDomainOptions, Preset};
+
+    let mut headers = http::HeaderMap::new();
+    Armor::preset(Preset::Modern).apply(&mut headers);
+    assert_eq!(headers["Origin-Agent-Cluster"], "?1");
+    assert_eq!(headers["X-Permitted-Cross-Domain-Policies"], "none");
+    assert_eq!(headers["X-Download-Options"], "noopen");
+
+    // The 2019 preset, and so armor(), keeps the headers it sent before.
+    let mut headers = http::HeaderMap::new();
+    armor::armor(&mut headers);
+    let mut names: Vec<_> = headers.keys().map(|name| name.as_str()).collect();
+    names.sort_unstable();
+    assert_eq!(
+        names,
+        [
+            "strict-transport-security",
+            "x-content-type-options",
+            "x-dns-prefetch-control",
+            "x-frame-options",
+            "x-xss-protection"
+        ]
+    );
+
+    let mut headers = http::HeaderMap::new();
+    Armor::new()
+        .origin_agent_cluster(false)
+        .ie_no_open(false)
+        .permitted_cross_domain_policies(Some(CrossDomainOptions::ByContentType))
+        .apply(&mut headers);
+    assert_eq!(headers.get("Origin-Agent-Cluster"), None);
+    assert_eq!(headers.get("X-Download-Options"), None);
+    assert_eq!(
+        headers["X-Permitted-Cross-Domain-Policies"],
+        "by-content-type"
+    );
+}
+
+#[test]
+fn clear_site_data_roundtrip() {
+    use armor::ClearSiteData;
+
+    let mut headers = http::HeaderMap::new();
+    armor::clear_site_data(
+        &mut headers,
+        &[
+            ClearSiteData::Cache,
+            ClearSiteData::ExecutionContexts,
+            ClearSiteData::Cache,
+            ClearSiteData::Wildcard,
+        ],
+    );
+    let value = headers["Clear-Site-Data"].to_str().unwrap();
+    assert_eq!(value, r#""cache", "executionContexts", "*""#);
+    assert_eq!(
+        ClearSiteData::parse(value).unwrap(),
+        vec![
+            ClearSiteData::Cache,
+            ClearSiteData::ExecutionContexts,
+            ClearSiteData::Wildcard
+        ]
+    );
+    assert!(ClearSiteData::parse(r#""everything""#).is_err());
+    assert!(ClearSiteData::parse(r#""Cookies""#).is_err());
+
+    let mut headers = http::HeaderMap::new();
+    armor::clear_site_data(&mut headers, &[]);
+    assert_eq!(headers.get("Clear-Site-Data"), None);
+}
+
+#[test]
+fn network_error_logging() {
+    use armor::csp::{ReportTo, ReportToEndpoint};
+
+    let group = ReportTo::new(3600, vec![ReportToEndpoint::new("https://r.example/nel")]);
+    let mut policy = armor::nel::new(&group, 3600);
+    policy
+        .success_fraction(0.0)
+        .unwrap()
+        .request_header("If-None-Match")
+        .response_header("ETag");
+    assert!(policy.failure_fraction(1.5).is_err());
+    assert!(policy.success_fraction(f64::NAN).is_err());
+
+    let mut headers = http::HeaderMap::new();
+    policy.apply(&mut headers);
+    policy.apply(&mut headers);
+    assert_eq!(
+        headers["NEL"],
+        r#"{"report_to":"default","max_age":3600,"success_fraction":0.0,"request_headers":["If-None-Match"],"response_headers":["ETag"]}"#
+    );
+    assert_eq!(headers.get_all("Report-To").iter().count(), 1);
+}
+
+#[test]
+fn referrer_policy_fallbacks() {
+    use armor::{ReferrerOptions, ReferrerPolicy};
+
+    let mut headers = http::HeaderMap::new();
+    armor::referrer_policy(&mut headers, Some(ReferrerOptions::NoReferrer));
+    armor::referrer_policy(&mut headers, Some(ReferrerOptions::StrictCrossOrigin));
+    let policy = ReferrerPolicy::from_headers(&headers).unwrap();
+    assert_eq!(
+        policy.value(),
+        "no-referrer, strict-origin-when-cross-origin"
+    );
+    assert_eq!(policy.effective(), Some(ReferrerOptions::StrictCrossOrigin));
+
+    let mut policy = ReferrerPolicy::new();
+    policy
+        .push(ReferrerOptions::Origin)
+        .push(ReferrerOptions::StrictOrigin);
+    policy.apply(&mut headers);
+    assert_eq!(headers.get_all("Referrer-Policy").iter().count(), 1);
+    assert_eq!(headers["Referrer-Policy"], "origin, strict-origin");
+
+    assert_eq!(
+        ReferrerPolicy::parse("Unsafe-URL, bogus").effective(),
+        Some(ReferrerOptions::UnsafeUrl)
+    );
+    assert_eq!(ReferrerPolicy::parse("bogus").effective(), None);
+    assert_eq!(ReferrerPolicy::from_headers(&http::HeaderMap::new()), None);
+
+    let mut headers = http::HeaderMap::new();
+    ReferrerPolicy::new().apply(&mut headers);
+    ReferrerPolicy::parse(" , ").apply(&mut headers);
+    assert_eq!(headers.get("Referrer-Policy"), None);
+
+    // Malformed tokens are dropped rather than reaching the header.
+    let policy = ReferrerPolicy::parse("no-referrer, bad\ntoken, two words, origin\u{7f}");
+    assert_eq!(policy.value(), "no-referrer");
+    policy.apply(&mut headers);
+    assert_eq!(headers["Referrer-Policy"], "no-referrer");
+}
+
+#[test]
+fn cookie_hardening() {
+    use armor::cookies::{CookieViolation, SameSite};
+
+    let mut headers = http::HeaderMap::new();
+    headers.append("Set-Cookie", "a=1; Secure; httponly;".parse().unwrap());
+    headers.append(
+        "Set-Cookie",
+        "__Host-id=2; Path=/app; Domain=example.com"
+            .parse()
+            .unwrap(),
+    );
+    headers.append("Set-Cookie", "csrf=3; SameSite=None".parse().unwrap());
+    headers.append("Set-Cookie", "bogus".parse().unwrap());
+
+    let mut policy = armor::cookies::new();
+    policy.same_site(Some(SameSite::Strict)).exempt("csrf");
+    let violations = policy.apply(&mut headers);
+
+    let cookies: Vec<_> = headers.get_all("Set-Cookie").iter().collect();
+    assert_eq!(cookies[0], "a=1; Secure; httponly; SameSite=Strict");
+    assert_eq!(
+        cookies[1],
+        "__Host-id=2; Path=/app; Domain=example.com; Secure; HttpOnly; SameSite=Strict"
+    );
+    assert_eq!(cookies[2], "csrf=3; SameSite=None");
+    assert_eq!(cookies[3], "bogus");
+    assert_eq!(
+        violations,
+        vec![
+            CookieViolation::HostPrefixWithDomain("__Host-id".into()),
+            CookieViolation::HostPrefixWithoutRootPath("__Host-id".into()),
+            CookieViolation::SameSiteNoneWithoutSecure("csrf".into()),
+            CookieViolation::Invalid("bogus".into()),
+        ]
+    );
+
+    let (value, violations) = policy.secure(false).harden("__Secure-x=1");
+    assert_eq!(value, "__Secure-x=1; HttpOnly; SameSite=Strict");
+    assert_eq!(
+        violations,
+        vec![CookieViolation::PrefixRequiresSecure("__Secure-x".into())]
+    );
+
+    let mut headers = http::HeaderMap::new();
+    headers.append("Set-Cookie", "a=1".parse().unwrap());
+    armor::Armor::new()
+        .cookies(Some(armor::cookies::new()))
+        .apply(&mut headers);
+    assert_eq!(headers["Set-Cookie"], "a=1; Secure; HttpOnly; SameSite=Lax");
+
+    // `Secure` is only added when the response goes to a deployed HTTPS site.
+    let mut armor = armor::Armor::new();
+    armor.cookies(Some(armor::cookies::new()));
+    let mut ctx = armor::RequestContext::new();
+    for (scheme, host, expected) in [
+        ("http", "example.com", "a=1; HttpOnly; SameSite=Lax"),
+        ("https", "localhost:8443", "a=1; HttpOnly; SameSite=Lax"),
+        (
+            "https",
+            "example.com",
+            "a=1; Secure; HttpOnly; SameSite=Lax",
+        ),
+    ] {
+        ctx.scheme(scheme).host(host);
+        let mut headers = http::HeaderMap::new();
+        headers.append("Set-Cookie", "a=1".parse().unwrap());
+        armor.apply_for(&mut headers, &ctx);
+        assert_eq!(headers["Set-Cookie"], expected);
+    }
+}
+
+#[test]
+fn cors_policies() {
+    use armor::cors::CorsError;
+    use http::{HeaderMap, Method};
+
+    let request = |origin: &str| {
+        let mut headers = HeaderMap::new();
+        headers.insert("Origin", origin.parse().unwrap());
+        headers
+    };
+
+    let mut policy = armor::cors::new();
+    policy
+        .allow_origin("https://example.com")
+        .allow_origin_regex(r"https://pr-\d+\.preview\.dev")
+        .unwrap()
+        .allow_origin_fn(|origin| origin.ends_with(":8080"))
+        .expose_header("X-Request-Id")
+        .unwrap();
+    assert!(policy.expose_header("X-Bad\nName").is_err());
+    assert!(policy.allow_header("X Token").is_err());
+
+    let mut response = HeaderMap::new();
+    policy
+        .apply(&request("https://pr-12.preview.dev"), &mut response)
+        .unwrap();
+    assert_eq!(
+        response["Access-Control-Allow-Origin"],
+        "https://pr-12.preview.dev"
+    );
+    assert_eq!(response["Access-Control-Expose-Headers"], "X-Request-Id");
+    assert_eq!(response["Vary"], "Origin");
+    assert_eq!(response.get("Access-Control-Allow-Credentials"), None);
+
+    let mut response = HeaderMap::new();
+    response.insert("Vary", "origin".parse().unwrap());
+    assert_eq!(
+        policy.apply(&request("https://pr-12.preview.dev.evil"), &mut response),
+        Err(CorsError::OriginNotAllowed(
+            "https://pr-12.preview.dev.evil".into()
+        ))
+    );
+    assert_eq!(response.get_all("Vary").iter().count(), 1);
+    assert_eq!(response.get("Access-Control-Allow-Origin"), None);
+    assert!(policy
+        .apply(&request("http://localhost:8080"), &mut HeaderMap::new())
+        .is_ok());
+
+    let mut preflight = request("https://example.com");
+    preflight.insert("Access-Control-Request-Method", "DELETE".parse().unwrap());
+    assert_eq!(
+        policy.preflight(&Method::OPTIONS, &preflight, &mut HeaderMap::new()),
+        Err(CorsError::MethodNotAllowed("DELETE".into()))
+    );
+    policy.allow_method(Method::DELETE);
+    preflight.insert("Access-Control-Request-Headers", "X-Token".parse().unwrap());
+    assert_eq!(
+        policy.preflight(&Method::OPTIONS, &preflight, &mut HeaderMap::new()),
+        Err(CorsError::HeaderNotAllowed("x-token".into()))
+    );
+    preflight.insert(
+        "Access-Control-Request-Headers",
+        "content-type".parse().unwrap(),
+    );
+    assert_eq!(
+        policy.preflight(&Method::OPTIONS, &preflight, &mut HeaderMap::new()),
+        Err(CorsError::HeaderNotAllowed("content-type".into()))
+    );
+    policy.allow_header("Content-Type").unwrap();
+    assert!(policy
+        .preflight(&Method::OPTIONS, &preflight, &mut HeaderMap::new())
+        .is_ok());
+    preflight.insert("Access-Control-Request-Headers", "X-Token".parse().unwrap());
+    policy.allow_any_header();
+    let mut response = HeaderMap::new();
+    policy
+        .preflight(&Method::OPTIONS, &preflight, &mut response)
+        .unwrap();
+    assert_eq!(
+        response["Access-Control-Allow-Methods"],
+        "GET, HEAD, POST, DELETE"
+    );
+    assert_eq!(response["Access-Control-Allow-Headers"], "x-token");
+    assert_eq!(response.get("Access-Control-Max-Age"), None);
+    assert_eq!(
+        policy.preflight(&Method::GET, &preflight, &mut HeaderMap::new()),
+        Err(CorsError::NotPreflight)
+    );
+
+    // Subdomain patterns also pin the scheme and port.
+    let mut policy = armor::cors::new();
+    policy
+        .allow_origin_suffix("https://*.example.com")
+        .unwrap()
+        .allow_credentials(true);
+    assert!(policy.is_allowed("https://api.example.com"));
+    assert!(policy.is_allowed("https://a.b.example.com:443"));
+    assert!(!policy.is_allowed("http://api.example.com"));
+    assert!(!policy.is_allowed("https://api.example.com:8443"));
+    assert!(!policy.is_allowed("https://example.com"));
+    assert!(!policy.is_allowed("https://evil-example.com"));
+    assert!(!policy.is_allowed("https://api.example.com.evil"));
+    assert!(policy.allow_origin_suffix(".example.com").is_err());
+    assert!(policy.allow_origin_suffix("https://example.com").is_err());
+    assert!(policy
+        .allow_origin_suffix("https://*.example.com/path")
+        .is_err());
+
+    let mut public = armor::cors::new();
+    public.allow_any_origin();
+    let mut response = HeaderMap::new();
+    public
+        .apply(&request("https://anywhere.example"), &mut response)
+        .unwrap();
+    assert_eq!(response["Access-Control-Allow-Origin"], "*");
+    assert_eq!(response.get("Vary"), None);
+
+    public.allow_credentials(true);
+    assert_eq!(public.validate(), Err(CorsError::WildcardWithCredentials));
+    assert_eq!(
+        public.apply(&request("https://anywhere.example"), &mut HeaderMap::new()),
+        Err(CorsError::WildcardWithCredentials)
+    );
+}
+
+#[test]
+fn fetch_metadata_resource_isolation() {
+    use armor::fetch_metadata::DenyReason;
+    use http::{HeaderMap, Method, Uri};
+
+    let headers = |site: &str, mode: &str, dest: &str| {
+        let mut headers = HeaderMap::new();
+        headers.insert("Sec-Fetch-Site", site.parse().unwrap());
+        headers.insert("Sec-Fetch-Mode", mode.parse().unwrap());
+        headers.insert("Sec-Fetch-Dest", dest.parse().unwrap());
+        headers
+    };
+    let uri: Uri = "/api/data?x=1".parse().unwrap();
+
+    let mut policy = armor::fetch_metadata::new();
+    let check = |policy: &armor::fetch_metadata::ResourceIsolationPolicy, method, headers| {
+        policy.check(&method, &uri, &headers)
+    };
+
+    assert_eq!(check(&policy, Method::POST, HeaderMap::new()), Ok(()));
+    assert_eq!(
+        check(
+            &policy,
+            Method::POST,
+            headers("same-origin", "cors", "empty")
+        ),
+        Ok(())
+    );
+    assert_eq!(
+        check(
+            &policy,
+            Method::GET,
+            headers("none", "navigate", "document")
+        ),
+        Ok(())
+    );
+    assert_eq!(
+        check(
+            &policy,
+            Method::GET,
+            headers("cross-site", "navigate", "document")
+        ),
+        Ok(())
+    );
+    assert_eq!(
+        check(
+            &policy,
+            Method::GET,
+            headers("cross-site", "no-cors", "script")
+        ),
+        Err(DenyReason::CrossSiteRequest)
+    );
+    assert_eq!(
+        check(
+            &policy,
+            Method::POST,
+            headers("cross-site", "navigate", "document")
+        ),
+        Err(DenyReason::UnsafeNavigation)
+    );
+    let denied = check(
+        &policy,
+        Method::GET,
+        headers("cross-site", "navigate", "embed"),
+    );
+    assert_eq!(denied, Err(DenyReason::EmbedNavigation));
+    assert_eq!(denied.unwrap_err().code(), "embed-navigation");
+
+    assert_eq!(
+        check(&policy, Method::POST, headers("same-site", "cors", "empty")),
+        Ok(())
+    );
+    policy.allow_same_site(false).exempt_path("/api/");
+    assert_eq!(
+        check(&policy, Method::POST, headers("same-site", "cors", "empty")),
+        Ok(())
+    );
+    assert!(policy.is_exempt("/api"));
+    assert!(!policy.is_exempt("/apis"));
+    assert!(policy.is_exempt("/api/v1/../users"));
+    assert!(policy.is_exempt("/%61pi/users"));
+    assert!(policy.is_exempt("/static/../api/"));
+    assert!(!policy.is_exempt("/api/../admin"));
+    assert!(!policy.is_exempt("/api/%2e%2e/admin"));
+    assert!(!policy.is_exempt("/api%2f..%2fadmin"));
+    assert!(!policy.is_exempt("/api\\..\\admin"));
+    assert!(!policy.is_exempt("//api"));
+    assert!(!policy.is_exempt("api"));
+
+    let req = http::Request::put("/other")
+        .header("Sec-Fetch-Site", "same-site")
+        .body(())
+        .unwrap();
+    assert_eq!(policy.check_request(&req), Err(DenyReason::SameSiteRequest));
+}
+
+#[test]
+fn host_allowlist() {
+    use armor::host::HostRejection;
+    use http::{HeaderMap, Uri};
+
+    let mut allowlist = armor::host::new();
+    allowlist
+        .allow("example.com")
+        .allow("*.example.com:8443")
+        .allow("[::1]:*");
+
+    assert_eq!(
+        allowlist.check_host("Example.COM."),
+        Ok("example.com".into())
+    );
+    assert_eq!(
+        allowlist.check_host("example.com:8080"),
+        Err(HostRejection::NotAllowed("example.com:8080".into()))
+    );
+    assert_eq!(
+        allowlist.check_host("a.b.example.com:8443"),
+        Ok("a.b.example.com:8443".into())
+    );
+    assert_eq!(
+        allowlist.check_host("badexample.com:8443"),
+        Err(HostRejection::NotAllowed("badexample.com:8443".into()))
+    );
+    assert_eq!(allowlist.check_host("[::1]:3000"), Ok("[::1]:3000".into()));
+    assert_eq!(
+        allowlist.check_host("example.com:99999"),
+        Err(HostRejection::Invalid("example.com:99999".into()))
+    );
+    assert_eq!(
+        allowlist.check_host("exa mple.com"),
+        Err(HostRejection::Invalid("exa mple.com".into()))
+    );
+
+    let mut headers = HeaderMap::new();
+    let uri: Uri = "/reset".parse().unwrap();
+    assert_eq!(allowlist.check(&uri, &headers), Err(HostRejection::Missing));
+
+    headers.insert("Host", "example.com".parse().unwrap());
+    headers.insert("X-Forwarded-Host", "evil.com".parse().unwrap());
+    assert_eq!(allowlist.check(&uri, &headers), Ok("example.com".into()));
+    let proxy = "10.0.0.2".parse().unwrap();
+    let client = "203.0.113.7".parse().unwrap();
+    assert_eq!(
+        allowlist.check_from(&uri, &headers, proxy),
+        Ok("example.com".into())
+    );
+    allowlist.trust_proxy("10.0.0.0/24").unwrap();
+    assert!(allowlist.trust_proxy("10.0.0.0/33").is_err());
+    assert_eq!(
+        allowlist.check_from(&uri, &headers, proxy),
+        Err(HostRejection::NotAllowed("evil.com".into()))
+    );
+    assert_eq!(
+        allowlist.check_from(&uri, &headers, "::ffff:10.0.0.2".parse().unwrap()),
+        Err(HostRejection::NotAllowed("evil.com".into()))
+    );
+    assert_eq!(
+        allowlist.check_from(&uri, &headers, client),
+        Ok("example.com".into())
+    );
+    assert_eq!(allowlist.check(&uri, &headers), Ok("example.com".into()));
+
+    let uri: Uri = "https://other.example.com/reset".parse().unwrap();
+    assert_eq!(
+        allowlist.check(&uri, &headers),
+        Err(HostRejection::Mismatch {
+            authority: "other.example.com".into(),
+            host: "example.com".into(),
+        })
+    );
+}
+
+#[test]
+fn csrf_protection() {
+    use armor::csrf::CsrfError;
+    use armor::fetch_metadata::DenyReason;
+
+    let post = |headers: &[(&str, &str)]| {
+        let mut req = http::Request::post("/transfer");
+        for (name, value) in headers {
+            req.header(*name, *value);
+        }
+        req.body(()).unwrap()
+    };
+
+    let mut csrf = armor::csrf::new();
+    csrf.allow_origin("https://partner.example/")
+        .fetch_metadata(Some(armor::fetch_metadata::new()));
+
+    let get = http::Request::get("/").body(()).unwrap();
+    assert_eq!(csrf.check_request(&get, ""), Ok(()));
+    assert_eq!(
+        csrf.check_request(&post(&[]), ""),
+        Err(CsrfError::MissingOrigin)
+    );
+    let referer = post(&[
+        ("Host", "example.com:8443"),
+        ("Referer", "https://example.com:8443/form?x=1"),
+    ]);
+    assert_eq!(csrf.check_request(&referer, ""), Ok(()));
+    let null = post(&[("Host", "example.com"), ("Origin", "null")]);
+    assert_eq!(
+        csrf.check_request(&null, ""),
+        Err(CsrfError::OriginNotAllowed("null".into()))
+    );
+    let partner = post(&[
+        ("Origin", "https://partner.example"),
+        ("Sec-Fetch-Site", "cross-site"),
+    ]);
+    assert_eq!(csrf.check_request(&partner, ""), Ok(()));
+    let cross_site = post(&[
+        ("Host", "example.com"),
+        ("Origin", "https://example.com"),
+        ("Sec-Fetch-Site", "cross-site"),
+    ]);
+    assert_eq!(
+        csrf.check_request(&cross_site, ""),
+        Err(CsrfError::FetchMetadata(DenyReason::CrossSiteRequest))
+    );
+
+    // The whole origin must match: scheme, host and port, with default ports normalized.
+    let same_origin = |origin| post(&[("Host", "example.com"), ("Origin", origin)]);
+    assert_eq!(
+        csrf.check_request(&same_origin("https://example.com:443"), ""),
+        Ok(())
+    );
+    assert_eq!(
+        csrf.check_request(&same_origin("http://example.com"), ""),
+        Err(CsrfError::OriginNotAllowed("http://example.com".into()))
+    );
+    assert_eq!(
+        csrf.check_request(&same_origin("https://example.com:8443"), ""),
+        Err(CsrfError::OriginNotAllowed(
+            "https://example.com:8443".into()
+        ))
+    );
+    let mut plain = armor::csrf::new();
+    plain.scheme("http");
+    assert_eq!(
+        plain.check_request(&same_origin("http://example.com:80"), ""),
+        Ok(())
+    );
+
+    // Exempt paths only skip the Fetch Metadata policy.
+    let mut policy = armor::fetch_metadata::new();
+    policy.exempt_path("/transfer");
+    let mut exempt = armor::csrf::new();
+    exempt.fetch_metadata(Some(policy));
+    let evil = post(&[
+        ("Host", "example.com"),
+        ("Origin", "https://evil.com"),
+        ("Sec-Fetch-Site", "cross-site"),
+    ]);
+    assert_eq!(
+        exempt.check_request(&evil, ""),
+        Err(CsrfError::OriginNotAllowed("https://evil.com".into()))
+    );
+
+    csrf.double_submit("secret").header_name("X-Token");
+    let token = csrf.generate_token("session-a").unwrap();
+    let mut response = http::HeaderMap::new();
+    csrf.set_token_cookie(&mut response, &token).unwrap();
+    assert!(csrf
+        .set_token_cookie(&mut response, "a; Domain=evil")
+        .is_err());
+    assert!(csrf.set_token_cookie(&mut response, "a\nb").is_err());
+    assert!(csrf.cookie_name("bad name").is_err());
+    assert!(csrf.cookie_name("csrf\r\n").is_err());
+    let mut cookies = armor::cookies::new();
+    csrf.configure_cookies(&mut cookies);
+    assert!(cookies.apply(&mut response).is_empty());
+    assert_eq!(
+        response["Set-Cookie"],
+        format!("__Host-csrf={}; Path=/; Secure; SameSite=Strict", token)
+    );
+
+    let cookie = format!("theme=dark; __Host-csrf={}", token);
+    let origin = ("Origin", "https://partner.example");
+    assert_eq!(
+        csrf.check_request(&post(&[origin, ("Cookie", &cookie)]), "session-a"),
+        Err(CsrfError::MissingToken)
+    );
+    let submitted = post(&[origin, ("Cookie", &cookie), ("X-Token", &token)]);
+    assert_eq!(csrf.check_request(&submitted, "session-a"), Ok(()));
+    // A token issued for one session isn't valid in another.
+    assert_eq!(
+        csrf.check_request(&submitted, "session-b"),
+        Err(CsrfError::InvalidToken)
+    );
+
+    let forged = format!("{}A", token);
+    let cookie = format!("__Host-csrf={}", forged);
+    let submitted = post(&[origin, ("Cookie", &cookie), ("X-Token", &forged)]);
+    assert_eq!(
+        csrf.check_request(&submitted, "session-a"),
+        Err(CsrfError::InvalidToken)
+    );
+    let other = armor::csrf::new()
+        .double_submit("other")
+        .generate_token("session-a")
+        .unwrap();
+    let cookie = format!("__Host-csrf={}", other);
+    let submitted = post(&[origin, ("Cookie", &cookie), ("X-Token", &other)]);
+    assert_eq!(
+        csrf.check_request(&submitted, "session-a"),
+        Err(CsrfError::InvalidToken)
+    );
+}
+
+#[test]
+fn request_sanitizer() {
+    use armor::sanitize::RemovalReason;
+    use std::net::IpAddr;
+
+    let request = || {
+        let mut headers = http::HeaderMap::new();
+        headers.insert("Host", "example.com".parse().unwrap());
+        headers.insert("X-Rewrite-URL", "/admin".parse().unwrap());
+        headers.insert("X-HTTP-Method-Override", "DELETE".parse().unwrap());
+        headers.insert("X-Forwarded-Proto", "https".parse().unwrap());
+        headers.insert("X-Real-IP", "203.0.113.7".parse().unwrap());
+        headers.insert("X-Debug", "1".parse().unwrap());
+        headers
+    };
+    let proxy: IpAddr = "::ffff:10.1.2.3".parse().unwrap();
+    let client: IpAddr = "2001:db8::1".parse().unwrap();
+
+    let mut sanitizer = armor::sanitize::new();
+    sanitizer.trust_proxy("10.0.0.0/8").unwrap();
+    assert!(sanitizer.trust_proxy("10.0.0.0/33").is_err());
+    assert!(sanitizer.is_trusted(proxy));
+
+    let mut headers = request();
+    let removed = sanitizer.sanitize(&mut headers, proxy);
+    let names: Vec<_> = removed.iter().map(|header| header.name()).collect();
+    assert_eq!(names, ["x-rewrite-url", "x-http-method-override"]);
+    assert!(removed
+        .iter()
+        .all(|header| header.reason() == RemovalReason::Denied));
+    assert_eq!(headers.len(), 4);
+
+    sanitizer
+        .allow("X-HTTP-Method-Override")
+        .deny("X-Debug")
+        .trust_proxy("::1")
+        .unwrap();
+    let mut headers = request();
+    let removed = sanitizer.sanitize(&mut headers, client);
+    let removed: Vec<_> = removed.iter().map(|header| header.to_string()).collect();
+    assert_eq!(
+        removed,
+        [
+            "removed denied header `x-rewrite-url`",
+            "removed forwarding header `x-forwarded-proto` from an untrusted client",
+            "removed forwarding header `x-real-ip` from an untrusted client",
+            "removed denied header `x-debug`",
+        ]
+    );
+    assert_eq!(headers.len(), 2);
+    assert_eq!(headers["X-HTTP-Method-Override"], "DELETE");
+}
+
+#[test]
+fn leak_stripping() {
+    let response = || {
+        let mut headers = http::HeaderMap::new();
+        headers.insert("Server", "Kestrel".parse().unwrap());
+        headers.insert("X-AspNetMvc-Version", "5.2".parse().unwrap());
+        headers.insert("X-Runtime", "0.012".parse().unwrap());
+        headers.insert("Via", "1.1 varnish".parse().unwrap());
+        headers.append("Server-Timing", "db;dur=53".parse().unwrap());
+        headers.append("Server-Timing", "app;dur=47.2".parse().unwrap());
+        headers.insert("X-Backend", "app-3".parse().unwrap());
+        headers
+    };
+
+    let mut headers = response();
+    armor::leaks::new().apply(&mut headers);
+    assert_eq!(headers.len(), 1);
+    assert_eq!(headers["X-Backend"], "app-3");
+
+    let mut staging = armor::leaks::new();
+    staging
+        .allow("server-timing")
+        .strip("X-Backend")
+        .unwrap()
+        .replace("Server", "webserver")
+        .unwrap();
+    assert!(staging.strip("bad header").is_err());
+    assert!(staging.replace("Server", "bad\nvalue").is_err());
+
+    let mut headers = response();
+    armor::Armor::new().leaks(Some(staging)).apply(&mut headers);
+    assert_eq!(headers["Server"], "webserver");
+    assert_eq!(headers.get_all("Server-Timing").iter().count(), 2);
+    assert_eq!(headers.get("X-Backend"), None);
+    assert_eq!(headers.get("Via"), None);
+
+    let mut headers = http::HeaderMap::new();
+    let mut stripper = armor::leaks::new();
+    stripper.replace("Server", "webserver").unwrap();
+    stripper.apply(&mut headers);
+    assert_eq!(headers.get("Server"), None);
+}
+
+#[test]
+fn audit_report() {
+    use armor::{Armor, Category, Grade, Preset, RequestContext, Severity};
+
+    let mut ctx = RequestContext::new();
+    ctx.scheme("https").host("example.com");
+
+    let mut headers = http::HeaderMap::new();
+    Armor::preset(Preset::Modern).apply_for(&mut headers, &ctx);
+    let report = armor::audit(&headers, &ctx);
+    let findings: Vec<_> = report
+        .findings_at_least(Severity::Low)
+        .map(|finding| finding.to_string())
+        .collect();
+    assert_eq!(
+        findings,
+        ["low (weak) Content-Security-Policy: `'unsafe-inline'` allows injected styles"]
+    );
+    assert_eq!(report.score(), 95);
+    assert_eq!(report.grade(), Grade::A);
+
+    let mut headers = http::HeaderMap::new();
+    headers.insert(
+        "Content-Security-Policy-Report-Only",
+        "default-src *; script-src 'self' 'unsafe-eval' http://cdn.example; block-all-mixed-content"
+            .parse()
+            .unwrap(),
+    );
+    headers.insert(
+        "Strict-Transport-Security",
+        "max-age=86400".parse().unwrap(),
+    );
+    headers.insert("Referrer-Policy", "unsafe-url".parse().unwrap());
+    headers.insert("X-XSS-Protection", "1; mode=block".parse().unwrap());
+    headers.insert("X-Powered-By", "PHP/8.2".parse().unwrap());
+    headers.insert("Server", "webserver".parse().unwrap());
+    headers.insert("Set-Cookie", "sid=1; SameSite=None".parse().unwrap());
+
+    let report = armor::audit(&headers, &ctx);
+    assert_eq!(report.max_severity(), Some(Severity::High));
+    assert_eq!(report.grade(), Grade::F);
+    let has = |category, severity, header: &str, text: &str| {
+        report.findings().iter().any(|finding| {
+            finding.category == category
+                && finding.severity == severity
+                && finding.header == header
+                && finding.message.contains(text)
+        })
+    };
+    assert!(has(
+        Category::Weak,
+        Severity::High,
+        "Content-Security-Policy",
+        "not enforced"
+    ));
+    assert!(has(
+        Category::Weak,
+        Severity::Medium,
+        "Content-Security-Policy",
+        "'unsafe-eval'"
+    ));
+    assert!(has(
+        Category::Weak,
+        Severity::Medium,
+        "Content-Security-Policy",
+        "http://cdn.example"
+    ));
+    assert!(has(
+        Category::Weak,
+        Severity::Medium,
+        "Content-Security-Policy",
+        "object-src"
+    ));
+    assert!(has(
+        Category::Weak,
+        Severity::Info,
+        "Content-Security-Policy",
+        "block-all-mixed-content"
+    ));
+    assert!(has(
+        Category::Missing,
+        Severity::Medium,
+        "Content-Security-Policy",
+        "framing"
+    ));
+    assert!(has(
+        Category::Weak,
+        Severity::Medium,
+        "Strict-Transport-Security",
+        "180 days"
+    ));
+    assert!(has(
+        Category::Weak,
+        Severity::Medium,
+        "Referrer-Policy",
+        "unsafe-url"
+    ));
+    assert!(has(
+        Category::Deprecated,
+        Severity::Low,
+        "X-XSS-Protection",
+        "auditor"
+    ));
+    assert!(has(
+        Category::Missing,
+        Severity::Medium,
+        "X-Content-Type-Options",
+        "sniff"
+    ));
+    assert!(has(
+        Category::Leak,
+        Severity::Low,
+        "X-Powered-By",
+        "PHP/8.2"
+    ));
+    assert!(has(Category::Leak, Severity::Info, "Server", "webserver"));
+    assert!(has(
+        Category::Weak,
+        Severity::Medium,
+        "Set-Cookie",
+        "without `Secure`"
+    ));
+    assert!(has(
+        Category::Weak,
+        Severity::Medium,
+        "Set-Cookie",
+        "`SameSite=None`"
+    ));
+    let severities: Vec<_> = report.findings().iter().map(|f| f.severity).collect();
+    assert!(severities.windows(2).all(|pair| pair[0] >= pair[1]));
+
+    let plain = RequestContext::new();
+    let report = armor::audit(&headers, &plain);
+    assert!(!report
+        .findings()
+        .iter()
+        .any(|finding| finding.header == "Strict-Transport-Security"
+            && finding.severity > Severity::Info));
+    assert_eq!("Medium".parse::<Severity>().unwrap(), Severity::Medium);
+    assert_eq!(Grade::APlus.to_string(), "A+");
+
+    // Every enforced policy counts: one restricts framing, and the other's weaknesses are
+    // covered by the stricter policy.
+    let mut headers = http::HeaderMap::new();
+    Armor::preset(Preset::Modern)
+        .content_security_policy(None)
+        .frameguard(None)
+        .apply_for(&mut headers, &ctx);
+    headers.append(
+        "Content-Security-Policy",
+        "script-src 'self' 'unsafe-inline'".parse().unwrap(),
+    );
+    headers.append(
+        "Content-Security-Policy",
+        "default-src 'self'; object-src 'none'; base-uri 'none'; frame-ancestors 'none'"
+            .parse()
+            .unwrap(),
+    );
+    let report = armor::audit(&headers, &ctx);
+    assert!(report.findings().is_empty(), "{:?}", report.findings());
+}
+
+#[cfg(feature = "cli")]
+#[test]
+fn cli_audit() {
+    use std::io::Write;
+    use std::process::{Command, Stdio};
+
+    let response: &[u8] = b"HTTP/1.1 200 OK\r\nServer: nginx/1.25\r\nBad Header: x\r\nX-Note: caf\xe9\r\nX-Content-Type-Options:\r\n nosniff\r\n\r\n<html>";
+    let run = |args: &[&str]| {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_armor"))
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+        // An invalid option exits before stdin is read, which breaks the pipe.
+        let _ = child.stdin.take().unwrap().write_all(response);
+        child.wait_with_output().unwrap()
+    };
+
+    let output = run(&["audit", "--format", "json", "--fail-on", "high"]);
+    assert_eq!(output.status.code(), Some(1));
+    let reports: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        "warning: line 3: invalid header name `Bad Header`, skipped\n"
+    );
+    assert_eq!(reports[0]["source"], "-");
+    assert_eq!(reports[0]["grade"], "F");
+    let findings = reports[0]["findings"].as_array().unwrap();
+    assert!(!findings
+        .iter()
+        .any(|finding| finding["header"] == "X-Content-Type-Options"));
+    assert!(findings
+        .iter()
+        .any(|finding| finding["header"] == "Server" && finding["category"] == "leak"));
+
+    let output = run(&["audit", "--scheme=http", "--fail-on", "high"]);
+    assert_eq!(output.status.code(), Some(1));
+    let table = String::from_utf8(output.stdout).unwrap();
+    assert!(table.starts_with("-: grade E (50/100)\nSEVERITY  CATEGORY"));
+    assert!(!table.contains("Strict-Transport-Security"));
+
+    let output = run(&["audit", "--fail-on", "critical"]);
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn csp_analysis() {
+    let old = csp::ContentSecurityPolicy::parse(
+        "default-src 'self'; script-src 'self' cdn.example; upgrade-insecure-requests",
+    );
+    let new =
+        csp::ContentSecurityPolicy::parse("default-src 'self'; script-src 'self' 'sha256-abc'");
+    assert_eq!(
+        old.diff(&new),
+        [
+            csp::PolicyChange::RemovedSource {
+                directive: "script-src".to_string(),
+                source: "cdn.example".to_string(),
+            },
+            csp::PolicyChange::AddedSource {
+                directive: "script-src".to_string(),
+                source: "'sha256-abc'".to_string(),
+            },
+            csp::PolicyChange::RemovedDirective {
+                directive: "upgrade-insecure-requests".to_string(),
+                sources: vec![],
+            },
+        ]
+    );
+    assert_eq!(old.diff(&new)[2].to_string(), "- upgrade-insecure-requests");
+    assert!(old.diff(&old).is_empty());
+
+    assert_eq!(
+        csp::hash_source(csp::HashAlgorithm::Sha384, ""),
+        "'sha384-OLBgp1GsljhM2TJ+sbHjaiH9txEUvgdDTAzHv2P24donTt6/529l+9Ua0vFImLlb'"
+    );
+    assert_eq!(
+        "SHA-512".parse::<csp::HashAlgorithm>().unwrap(),
+        csp::HashAlgorithm::Sha512
+    );
+    assert!("md5".parse::<csp::HashAlgorithm>().is_err());
+
+    assert_eq!(
+        csp::describe_directive("Script-Src"),
+        Some("sources of scripts")
+    );
+    assert_eq!(csp::describe_source("data:"), "any `data:` URL");
+    assert_eq!(
+        csp::describe_source("https://cdn.example"),
+        "https://cdn.example only"
+    );
+
+    let html = "<HTML><Meta HTTP-EQUIV=Content-Security-Policy CONTENT=\"script-src 'self'\">\
+                <scripts></scripts><script type=module>\n</script><STYLE>p{}</STYLE>\
+                <script src='/app.js'></script>";
+    assert_eq!(csp::from_html(html).unwrap().value(), "script-src 'self'");
+    assert!(csp::from_html("<meta name=viewport content=width>").is_none());
+    let escaped = "<meta http-equiv=\"Content-Security-Policy\" \
+                   content=\"script-src &#39;self&#x27; &apos;nonce-a&#X2b;b&#61;&apos; &amp;x &bogus\">";
+    assert_eq!(
+        csp::from_html(escaped).unwrap().value(),
+        "script-src 'self' 'nonce-a+b=' &x &bogus"
+    );
+    let hashes = csp::inline_hashes(html, csp::HashAlgorithm::Sha256);
+    assert_eq!(
+        hashes,
+        [
+            ("script", csp::hash_source(csp::HashAlgorithm::Sha256, "\n")),
+            ("style", csp::hash_source(csp::HashAlgorithm::Sha256, "p{}")),
+        ]
+    );
+}
+
+#[cfg(feature = "cli")]
+#[test]
+fn cli_csp() {
+    use std::process::Command;
+
+    let run = |args: &[&str]| {
+        let output = Command::new(env!("CARGO_BIN_EXE_armor"))
+            .arg("csp")
+            .args(args)
+            .output()
+            .unwrap();
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        (output.status.code(), stdout)
+    };
+
+    let policy = "script-src 'self' 'unsafe-inline'; object-src 'none'; upgrade-insecure-requests";
+    assert_eq!(
+        run(&["parse", policy]),
+        (
+            Some(0),
+            "object-src 'none'\nscript-src 'self' 'unsafe-inline'\nupgrade-insecure-requests\n"
+                .to_string()
+        )
+    );
+
+    let (code, output) = run(&["lint", "--format=json", policy]);
+    assert_eq!(code, Some(1));
+    let lints: serde_json::Value = serde_json::from_str(&output).unwrap();
+    assert_eq!(lints[0]["severity"], "high");
+    assert_eq!(lints[0]["directive"], "script-src");
+    assert_eq!(run(&["lint", "--fail-on", "critical", policy]).0, Some(2));
+
+    let (code, output) = run(&["diff", policy, "script-src 'self'; object-src 'none'"]);
+    assert_eq!(code, Some(1));
+    assert_eq!(
+        output,
+        "- script-src 'unsafe-inline'\n- upgrade-insecure-requests\n"
+    );
+
+    let (code, output) = run(&["explain", "img-src data:"]);
+    assert_eq!(code, Some(0));
+    assert!(output.starts_with("img-src: sources of images and favicons\n  data:"));
+
+    let html = std::env::temp_dir().join(format!("armor-cli-csp-{}.html", std::process::id()));
+    std::fs::write(
+        &html,
+        "<meta http-equiv=Content-Security-Policy content=\"default-src 'none'\"><script>alert(1)</script>",
+    )
+    .unwrap();
+    let html = html.to_str().unwrap();
+    assert_eq!(
+        run(&["parse", html]),
+        (Some(0), "default-src 'none'\n".to_string())
+    );
+    let (_, output) = run(&["hash", html]);
+    assert_eq!(
+        output,
+        format!(
+            "{}  script-src  {}\n",
+            csp::hash_source(csp::HashAlgorithm::Sha256, "alert(1)"),
+            html
+        )
+    );
+    std::fs::remove_file(html).unwrap();
+}
+
+#[test]
+fn csp_source_matching() {
+    let policy = csp::ContentSecurityPolicy::parse(
+        "default-src 'self'; img-src *; media-src https://media.example:8443/videos/; \
+         connect-src 'self' wss://live.example; frame-src 'none'; \
+         script-src-elem https://cdn.example/app.js",
+    );
+    let page = "http://example.com/shop";
+    assert!(policy.allows("font-src", "http://example.com/a.woff2", page));
+    assert!(policy.allows("font-src", "https://example.com/a.woff2", page));
+    assert!(!policy.allows("font-src", "http://example.com:8080/a.woff2", page));
+    assert!(!policy.allows("font-src", "http://sub.example.com/a.woff2", page));
+    assert!(policy.allows("img-src", "https://anything.example/x.png", page));
+    assert!(!policy.allows("img-src", "data:image/png;base64,AA", page));
+    assert!(policy.allows("media-src", "https://media.example:8443/videos/a.mp4", page));
+    assert!(!policy.allows("media-src", "https://media.example/videos/a.mp4", page));
+    assert!(!policy.allows("media-src", "https://media.example:8443/audio/a.mp3", page));
+    assert!(policy.allows("connect-src", "wss://live.example/socket", page));
+    assert!(policy.allows("connect-src", "ws://example.com/socket", page));
+    assert!(!policy.allows("frame-src", "https://example.com/", page));
+    assert!(policy.allows("script-src-elem", "https://cdn.example/app.js", page));
+    assert!(policy.allows("script-src-elem", "https://cdn.example/app.js?v=2", page));
+    assert!(!policy.allows("script-src-elem", "https://cdn.example/other.js", page));
+    assert!(!policy.allows("img-src", "not a url", page));
+
+    let policy = csp::ContentSecurityPolicy::parse("script-src 'nonce-abc' 'strict-dynamic'");
+    assert!(policy.allows("script-src", "https://anywhere.example/x.js", page));
+    assert!(policy.allows("img-src", "https://anywhere.example/x.png", page));
+
+    let policy = csp::ContentSecurityPolicy::parse("default-src https:; worker-src blob:");
+    assert!(policy.allows("worker-src", "blob:https://example.com/1234", page));
+    assert!(!policy.allows("worker-src", "https://example.com/worker.js", page));
+    assert!(!policy.allows("script-src", "http://example.com/app.js", page));
+}
+
+#[test]
+fn har_analysis() {
+    use armor::har::{self, Har, ResourceKind};
+
+    let har: Har = r#"{"log": {
+        "pages": [{"id": "page_1", "title": "Shop"}, {"id": "page_2", "title": "Empty"}],
+        "entries": [
+            {"pageref": "page_1", "_resourceType": "document",
+             "request": {"method": "GET", "url": "http://shop.example/"},
+             "response": {"status": 301, "headers": [], "content": {}}},
+            {"pageref": "page_1", "_resourceType": "document",
+             "request": {"method": "GET", "url": "https://shop.example/"},
+             "response": {"status": 200, "content": {"mimeType": "text/html"}, "headers": [
+                 {"name": "content-security-policy-report-only",
+                  "value": "default-src 'self'; img-src 'self' data:; frame-src https://pay.example"},
+                 {"name": "bad header", "value": "ignored"}
+             ]}},
+            {"pageref": "page_1",
+             "request": {"method": "GET", "url": "https://cdn.example/app.js"},
+             "response": {"status": 200, "headers": [], "content": {"mimeType": "application/javascript; charset=utf-8"}}},
+            {"pageref": "page_1", "_resourceType": "image",
+             "request": {"method": "GET", "url": "data:image/png;base64,AA"},
+             "response": {"status": 200, "headers": [], "content": {}}},
+            {"pageref": "page_1", "_resourceType": "Document",
+             "request": {"method": "GET", "url": "https://pay.example/widget"},
+             "response": {"status": 200, "headers": [], "content": {}}},
+            {"request": {"method": "GET", "url": "wss://live.example/"},
+             "response": {"status": 101, "headers": []}}
+        ]
+    }}"#
+    .parse()
+    .unwrap();
+
+    assert_eq!(har.entries().len(), 6);
+    assert!(har.entries()[1]
+        .headers()
+        .contains_key("Content-Security-Policy-Report-Only"));
+    assert_eq!(har.entries()[5].kind(), ResourceKind::Connect);
+
+    let pages = har.pages();
+    assert_eq!(pages.len(), 2);
+    assert_eq!(pages[0].document().unwrap().url(), "https://shop.example/");
+    let kinds: Vec<_> = pages[0].resources().iter().map(|(kind, _)| *kind).collect();
+    assert_eq!(
+        kinds,
+        [
+            ResourceKind::Script,
+            ResourceKind::Image,
+            ResourceKind::Frame
+        ]
+    );
+    assert_eq!(pages[1].id(), "");
+    assert!(pages[1].document().is_none());
+
+    let reports = har::analyze(&har);
+    assert_eq!(reports[0].url.as_deref(), Some("https://shop.example/"));
+    assert!(reports[0].policies[0].is_report_only());
+    let documents: Vec<_> = reports[0]
+        .documents
+        .iter()
+        .map(|(url, _)| url.as_str())
+        .collect();
+    assert_eq!(
+        documents,
+        ["https://shop.example/", "https://pay.example/widget"]
+    );
+    assert_eq!(
+        reports[0].blocked,
+        [har::BlockedLoad {
+            url: "https://cdn.example/app.js".to_string(),
+            kind: ResourceKind::Script,
+            directive: "script-src",
+        }]
+    );
+    assert!(reports[1].documents.is_empty());
+    assert!(reports[1].blocked.is_empty());
+
+    let har: Har = r#"{"log": {"entries": [
+        {"pageref": "page_1", "_resourceType": "document", "request": {"url": "https://example.com/"},
+         "response": {"status": 200, "headers": [
+             {"name": "Content-Security-Policy", "value": "default-src *"},
+             {"name": "Content-Security-Policy", "value": "script-src https://cdn.example"}
+         ]}},
+        {"pageref": "page_1", "_resourceType": "script", "request": {"url": "https://example.com/app.js"},
+         "response": {"status": 200, "headers": []}},
+        {"pageref": "page_1", "_resourceType": "script", "request": {"url": "https://cdn.example/lib.js"},
+         "response": {"status": 200, "headers": []}}
+    ]}}"#
+        .parse()
+        .unwrap();
+    let pages = har.pages();
+    assert_eq!(pages.len(), 1);
+    assert_eq!(pages[0].id(), "page_1");
+    assert_eq!(pages[0].entries().len(), 3);
+
+    // A load must be allowed by every enforced policy.
+    let reports = har::analyze(&har);
+    assert_eq!(reports[0].policies.len(), 2);
+    assert_eq!(
+        reports[0].blocked,
+        [har::BlockedLoad {
+            url: "https://example.com/app.js".to_string(),
+            kind: ResourceKind::Script,
+            directive: "script-src",
+        }]
+    );
+
+    // Redirects are checked too, and the request that follows one is matched without paths.
+    let har: Har = r#"{"log": {"entries": [
+        {"_resourceType": "document", "request": {"url": "https://example.com/"},
+         "response": {"status": 200, "headers": [{"name": "Content-Security-Policy",
+             "value": "default-src 'self'; img-src 'self' https://img.example/logos/"}]}},
+        {"_resourceType": "image", "request": {"url": "https://example.com/old.png"},
+         "response": {"status": 302, "redirectURL": "https://img.example/other/1.png", "headers": []}},
+        {"_resourceType": "image", "request": {"url": "https://img.example/other/1.png"},
+         "response": {"status": 200, "headers": []}},
+        {"_resourceType": "image", "request": {"url": "https://img.example/other/2.png"},
+         "response": {"status": 200, "headers": []}},
+        {"_resourceType": "script", "request": {"url": "https://example.com/go.js"},
+         "response": {"status": 302, "headers": [{"name": "Location", "value": "//evil.example/x.js"}]}},
+        {"_resourceType": "script", "request": {"url": "https://evil.example/x.js"},
+         "response": {"status": 200, "headers": []}},
+        {"_resourceType": "image", "request": {"url": "https://tracker.example/p.gif?id=1"},
+         "response": {"status": 301, "redirectURL": "pixel.gif", "headers": []}},
+        {"_resourceType": "image", "request": {"url": "https://tracker.example/pixel.gif"},
+         "response": {"status": 200, "headers": []}}
+    ]}}"#
+        .parse()
+        .unwrap();
+    let entries = har.entries();
+    assert_eq!(entries[4].redirect_url(), Some("https://evil.example/x.js"));
+    assert_eq!(
+        entries[6].redirect_url(),
+        Some("https://tracker.example/pixel.gif")
+    );
+    assert!(entries[0].redirect_url().is_none());
+    let redirected: Vec<_> = entries.iter().map(|entry| entry.is_redirected()).collect();
+    assert_eq!(
+        redirected,
+        [false, false, true, false, false, true, false, true]
+    );
+    assert_eq!(har.pages()[0].resources().len(), 7);
+    let reports = har::analyze(&har);
+    let blocked: Vec<_> = reports[0]
+        .blocked
+        .iter()
+        .map(|load| load.url.as_str())
+        .collect();
+    assert_eq!(
+        blocked,
+        [
+            "https://img.example/other/2.png",
+            "https://evil.example/x.js",
+            "https://tracker.example/p.gif?id=1",
+            "https://tracker.example/pixel.gif"
+        ]
+    );
+
+    let err = "{\"log\": {}}".parse::<Har>().unwrap_err();
+    assert!(err
+        .to_string()
+        .starts_with("invalid HAR file: missing field `entries`"));
+}
+
+#[test]
+fn har_policy_generation() {
+    use armor::har::{Har, PolicyGenerator};
+
+    let entry = |page: &str, kind: &str, url: &str| {
+        format!(
+            r#"{{"pageref": "{}", "_resourceType": "{}", "request": {{"url": "{}"}},
+                "response": {{"status": 200, "headers": []}}}}"#,
+            page, kind, url
+        )
+    };
+    let entries = [
+        entry("home", "document", "https://example.com/"),
+        entry("home", "script", "https://example.com/app.js"),
+        entry("home", "script", "https://cdn.example.net/lib.js"),
+        entry("home", "image", "data:image/png;base64,AA"),
+        entry("home", "image", "https://a.img.example.org/1.png"),
+        entry("home", "image", "https://b.img.example.org/2.png"),
+        entry("home", "image", "https://c.img.example.org/3.png"),
+        entry("home", "image", "https://img.example.org/4.png"),
+        entry("home", "image", "http://d.img.example.org/5.png"),
+        entry("home", "font", "https://fonts.example.net:8443/a.woff2"),
+        entry("home", "websocket", "wss://example.com/live"),
+        entry("home", "other", "https://other.example/beacon"),
+        entry("account", "document", "https://example.com/account"),
+        entry("account", "fetch", "https://api.example.com/me"),
+        entry("account", "media", "blob:https://example.com/1234"),
+        entry("account", "script", "https://1.2.3.4/x.js"),
+    ];
+    let har: Har = format!(r#"{{"log": {{"entries": [{}]}}}}"#, entries.join(","))
+        .parse()
+        .unwrap();
+
+    let policy = PolicyGenerator::new().generate(&har.pages());
+    assert_eq!(
+        policy.value(),
+        "base-uri 'none'; \
+         connect-src 'self' https://api.example.com; \
+         default-src 'none'; \
+         font-src https://fonts.example.net:8443; \
+         img-src data: http://d.img.example.org https://*.img.example.org https://img.example.org; \
+         media-src blob:; \
+         object-src 'none'; \
+         script-src 'self' https://1.2.3.4 https://cdn.example.net"
+    );
+
+    let pages = har.pages();
+    let policy = PolicyGenerator::new()
+        .subdomain_threshold(4)
+        .generate(&pages[..1]);
+    assert_eq!(
+        policy.directive("img-src").unwrap(),
+        [
+            "data:",
+            "http://d.img.example.org",
+            "https://a.img.example.org",
+            "https://b.img.example.org",
+            "https://c.img.example.org",
+            "https://img.example.org",
+        ]
+    );
+    assert!(policy.directive("media-src").is_none());
+
+    let policy = PolicyGenerator::new().generate(&pages);
+    for page in &pages {
+        let document = page.document().unwrap().url();
+        for (kind, entry) in page.resources() {
+            if let Some(directive) = kind.directive() {
+                assert!(
+                    policy.allows(directive, entry.url(), document),
+                    "{}",
+                    entry.url()
+                );
+            }
+        }
+    }
+
+    // Shared domains are never collapsed, directives can opt out, and redirects count as
+    // loads of the directive that started them.
+    let entries = [
+        entry("home", "document", "https://example.com/"),
+        entry("home", "script", "https://d1.cloudfront.net/a.js"),
+        entry("home", "script", "https://d2.cloudfront.net/b.js"),
+        entry("home", "script", "https://d3.cloudfront.net/c.js"),
+        entry("home", "image", "https://a.co.uk/1.png"),
+        entry("home", "image", "https://b.co.uk/2.png"),
+        entry("home", "image", "https://c.co.uk/3.png"),
+        entry("home", "font", "https://a.static.example.org/1.woff2"),
+        entry("home", "font", "https://b.static.example.org/2.woff2"),
+        entry("home", "font", "https://c.static.example.org/3.woff2"),
+        r#"{"pageref": "home", "request": {"url": "https://go.example/lib.js"},
+            "response": {"status": 302, "redirectURL": "https://d1.cloudfront.net/lib.js",
+                         "content": {"mimeType": "text/html"}, "headers": []}}"#
+            .to_string(),
+        entry("home", "script", "https://d1.cloudfront.net/lib.js"),
+    ];
+    let har: Har = format!(r#"{{"log": {{"entries": [{}]}}}}"#, entries.join(","))
+        .parse()
+        .unwrap();
+    let policy = PolicyGenerator::new()
+        .wildcards("FONT-SRC", false)
+        .generate(&har.pages());
+    assert_eq!(
+        policy.directive("script-src").unwrap(),
+        [
+            "https://d1.cloudfront.net",
+            "https://d2.cloudfront.net",
+            "https://d3.cloudfront.net",
+            "https://go.example",
+        ]
+    );
+    assert_eq!(
+        policy.directive("img-src").unwrap(),
+        ["https://a.co.uk", "https://b.co.uk", "https://c.co.uk"]
+    );
+    assert_eq!(policy.directive("font-src").unwrap().len(), 3);
+    assert!(policy.directive("frame-src").is_none());
+    let policy = PolicyGenerator::new()
+        .wildcards("font-src", false)
+        .wildcards("font-src", true)
+        .generate(&har.pages());
+    assert_eq!(
+        policy.directive("font-src").unwrap(),
+        ["https://*.static.example.org"]
+    );
+}
+
+#[cfg(feature = "cli")]
+#[test]
+fn cli_har() {
+    use std::io::Write;
+    use std::process::{Command, Stdio};
+
+    let capture = r#"{"log": {"entries": [
+        {"request": {"method": "GET", "url": "https://example.com/"},
+         "response": {"status": 200, "content": {"mimeType": "text/html"}, "headers": [
+             {"name": "Content-Security-Policy", "value": "default-src 'self'"}]}},
+        {"request": {"method": "GET", "url": "https://cdn.example/app.css"},
+         "response": {"status": 200, "content": {"mimeType": "text/css"}, "headers": []}}
+    ]}}"#;
+    let run = |args: &[&str], input: &str| {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_armor"))
+            .arg("har")
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .unwrap();
+        let mut stdin = child.stdin.take().unwrap();
+        stdin.write_all(input.as_bytes()).unwrap();
+        drop(stdin);
+        child.wait_with_output().unwrap()
+    };
+
+    let output = run(&["audit", "--format=json", "--fail-on=critical"], capture);
+    assert_eq!(output.status.code(), Some(2));
+
+    let output = run(&["audit", "--format=json", "-"], capture);
+    assert_eq!(output.status.code(), Some(1));
+    let pages: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(pages[0]["url"], "https://example.com/");
+    assert_eq!(pages[0]["documents"][0]["source"], "https://example.com/");
+    assert_eq!(pages[0]["blocked"][0]["directive"], "style-src");
+    assert_eq!(pages[0]["blocked"][0]["url"], "https://cdn.example/app.css");
+
+    let output = run(&["audit"], capture);
+    let table = String::from_utf8(output.stdout).unwrap();
+    assert!(table.starts_with("== page -\nhttps://example.com/: grade "));
+    assert!(table.contains("Content-Security-Policy blocks 1 load(s):\nDIRECTIVE  KIND   URL\n"));
+
+    assert_eq!(run(&["audit"], "{}").status.code(), Some(2));
+
+    let output = run(&["csp", "--threshold", "2"], capture);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "base-uri 'none'; default-src 'none'; object-src 'none'; style-src https://cdn.example\n"
+    );
+    assert_eq!(
+        run(&["csp", "--page", "missing"], capture).status.code(),
+        Some(2)
+    );
+}